@@ -0,0 +1,36 @@
+#!/usr/bin/env python3
+"""
+Download the single-file Pikchr C library used for offline Pikchr rendering.
+build.rs compiles src-tauri/pikchr/pikchr.c with cc when it is present.
+"""
+
+import sys
+import urllib.request
+import urllib.error
+from pathlib import Path
+
+# Paths
+PIKCHR_DIR = Path(__file__).parent.parent / "src-tauri" / "pikchr"
+
+# GitHub mirror of the upstream Fossil repository (https://pikchr.org)
+PIKCHR_URL = "https://raw.githubusercontent.com/drhsqlite/pikchr/master/pikchr.c"
+
+
+def main():
+    PIKCHR_DIR.mkdir(parents=True, exist_ok=True)
+    dest = PIKCHR_DIR / "pikchr.c"
+
+    print(f"Downloading {PIKCHR_URL}...")
+    try:
+        with urllib.request.urlopen(PIKCHR_URL, timeout=60) as response:
+            data = response.read()
+    except urllib.error.URLError as e:
+        print(f"Failed to download pikchr.c: {e}")
+        sys.exit(1)
+
+    dest.write_bytes(data)
+    print(f"Saved {len(data)} bytes to {dest}")
+
+
+if __name__ == "__main__":
+    main()
@@ -3,6 +3,8 @@ use std::fs;
 use std::path::Path;
 
 fn main() {
+    compile_pikchr(Path::new("pikchr"));
+
     let grammars_dir = Path::new("grammars");
     
     if !grammars_dir.exists() {
@@ -292,6 +294,33 @@ fn main() {
     tauri_build::build();
 }
 
+/// Compile the single-file Pikchr library (pikchr.c) if it has been downloaded.
+///
+/// Sets the `pikchr` cfg so the Rust side only links against it when present.
+fn compile_pikchr(pikchr_dir: &Path) {
+    println!("cargo:rustc-check-cfg=cfg(pikchr)");
+
+    let pikchr_c = pikchr_dir.join("pikchr.c");
+    if !pikchr_c.exists() {
+        println!("cargo:warning=pikchr.c not found. Run python scripts/download_pikchr.py to enable Pikchr rendering");
+        // Watch the (empty) directory so a later download triggers a rebuild; a missing
+        // path would instead rerun this script on every build
+        let _ = std::fs::create_dir_all(pikchr_dir);
+        println!("cargo:rerun-if-changed={}", pikchr_dir.display());
+        return;
+    }
+
+    println!("cargo:rerun-if-changed={}", pikchr_c.display());
+
+    cc::Build::new()
+        .file(&pikchr_c)
+        .include(pikchr_dir)
+        .warnings(false)
+        .compile("pikchr");
+
+    println!("cargo:rustc-cfg=pikchr");
+}
+
 fn compile_grammar(name: &str, grammar_dir: &Path, subpath: &str, c_symbol: &str) {
     let src_dir = if subpath.is_empty() {
         grammar_dir.join("src")
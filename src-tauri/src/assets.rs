@@ -0,0 +1,449 @@
+//! Image asset cleanup: unused, missing and duplicate images
+//!
+//! Scans a folder, or the folder of a single document, for images nothing
+//! links to inside image folders (those named like the `image_directory`
+//! pasted images are saved to), image links that resolve to nothing, and
+//! images with identical content. Links are counted from every note of the
+//! open workspace, ignored and non-UTF-8 ones included, so an image is only
+//! unused when no note anywhere links to it. References are resolved the way
+//! the link checker and refactoring rename do: relative and root-absolute
+//! paths, the image folder fallback, `![[embeds]]` by name and `<img src>`.
+//!
+//! Cleanup needs the workspace holding the folder or document, so notes
+//! outside it are read too. It rescans first and only touches what is still
+//! reported. Merging duplicates rewrites every link to a copy so it points at
+//! the kept file, in the style it was written in, before deleting the copies.
+
+use crate::file_tree::is_image;
+use crate::links::{extract_links, has_scheme, percent_decode, LinkKind};
+use crate::workspace::quick_open::FileList;
+use crate::workspace::graph::Files;
+use crate::workspace::refactor::{remove_empty_image_dir, resolve, write_edits, FileEdit, Rename};
+use crate::workspace::{is_markdown, relative_path, WorkspaceState};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AssetOptions {
+    /// Folder pasted images are saved to, relative to the document (see `save_image`)
+    pub image_directory: String,
+}
+
+impl Default for AssetOptions {
+    fn default() -> Self {
+        Self { image_directory: "img".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Asset {
+    pub path: String,
+    pub relative_path: String,
+    pub size: u64,
+    /// Links to it from the scanned notes
+    pub references: usize,
+}
+
+/// An image link that resolves to nothing, at a 1-based line and UTF-16 column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingAsset {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub target: String,
+}
+
+/// Images with identical content; `keep` is the most referenced copy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    /// SHA-256 of the content, hex
+    pub hash: String,
+    pub size: u64,
+    pub keep: Asset,
+    pub copies: Vec<Asset>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetReport {
+    pub files_checked: usize,
+    pub unused: Vec<Asset>,
+    pub missing: Vec<MissingAsset>,
+    pub duplicates: Vec<DuplicateGroup>,
+}
+
+/// What to clean up, as reported by a scan.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CleanupRequest {
+    /// Paths of unused images to delete
+    pub delete: Vec<String>,
+    /// Hashes of duplicate groups to merge into their kept copy
+    pub deduplicate: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupResult {
+    pub deleted: Vec<String>,
+    /// Notes whose links now point at a kept copy
+    pub edits: Vec<FileEdit>,
+}
+
+/// Everything a scan found, with what cleanup needs to act on it.
+struct Scan {
+    root: PathBuf,
+    /// All files under the root, ignored ones included, by relative path
+    files: BTreeSet<String>,
+    /// Notes that are valid UTF-8, so their links can be rewritten
+    notes: Vec<String>,
+    /// Images linked from notes that aren't UTF-8
+    pinned: HashSet<String>,
+    report: AssetReport,
+}
+
+fn is_in_image_dir(key: &str, image_directory: &str) -> bool {
+    let mut folders: Vec<&str> = key.split('/').collect();
+    folders.pop();
+    folders.contains(&image_directory)
+}
+
+fn hash_file(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    Some(Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Every file under `root` whatever the ignore files say: a note `.gitignore`
+/// hides still keeps the images it links to.
+fn all_files(root: &Path) -> BTreeSet<String> {
+    ignore::WalkBuilder::new(root)
+        .standard_filters(false)
+        .filter_entry(|entry| entry.depth() == 0 || !matches!(entry.file_name().to_str(), Some(".git" | "node_modules")))
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| relative_path(root, entry.path()))
+        .collect()
+}
+
+/// References are read from `workspace` when it holds `path`, so notes
+/// outside the scanned folder still count; otherwise from the folder, or the
+/// document's own folder.
+fn scan_files(path: &Path, workspace: Option<&Path>, options: &AssetOptions) -> Result<Scan, String> {
+    if !path.exists() {
+        return Err(format!("{} does not exist", path.display()));
+    }
+    let fallback = if path.is_dir() { path } else { path.parent().unwrap_or(Path::new("")) };
+    let root = workspace.filter(|root| path.starts_with(root)).unwrap_or(fallback).to_path_buf();
+    let (document, folder) = if path.is_dir() {
+        let folder = relative_path(&root, path);
+        (None, (!folder.is_empty()).then(|| format!("{}/", folder)))
+    } else {
+        (Some(relative_path(&root, path)), None)
+    };
+    let image_dir = options.image_directory.as_str();
+    let files = all_files(&root);
+    let resolver = Files::new(files.clone());
+    let is_checked = |key: &str| match (&document, &folder) {
+        (Some(document), _) => document == key,
+        (None, folder) => folder.as_ref().is_none_or(|folder| key.starts_with(folder.as_str())),
+    };
+
+    // References come from every note, so an image another note uses isn't unused
+    let mut references: HashMap<String, usize> = HashMap::new();
+    let mut checked_references = HashSet::new();
+    let mut notes = Vec::new();
+    let mut pinned = HashSet::new();
+    let mut report = AssetReport::default();
+    for key in files.iter().filter(|key| is_markdown(Path::new(key))) {
+        let file = root.join(key);
+        let Ok(bytes) = fs::read(&file) else { continue };
+        // Links in a note that isn't UTF-8 still count, they just can't be rewritten
+        let utf8 = std::str::from_utf8(&bytes).is_ok();
+        let content = String::from_utf8_lossy(&bytes);
+        if utf8 {
+            notes.push(key.clone());
+        }
+        let checked = is_checked(key);
+        report.files_checked += usize::from(checked);
+        for link in extract_links(&content) {
+            let target = link.target.trim();
+            if target.is_empty() || has_scheme(target) {
+                continue;
+            }
+            match resolve(&resolver, key, &link, image_dir) {
+                Some(found) => {
+                    if checked {
+                        checked_references.insert(found.clone());
+                    }
+                    if !utf8 {
+                        pinned.insert(found.clone());
+                    }
+                    *references.entry(found).or_default() += 1;
+                }
+                None if checked => {
+                    let name = target.split(['#', '?', '|']).next().unwrap_or(target);
+                    let is_image_link = link.kind == LinkKind::Image || (link.kind == LinkKind::Embed && is_image(Path::new(name)));
+                    // A document's links may leave the scanned root, which the scan doesn't cover
+                    let outside = link.kind == LinkKind::Image && file.parent().is_some_and(|dir| dir.join(percent_decode(name)).is_file());
+                    if is_image_link && !outside {
+                        report.missing.push(MissingAsset {
+                            file: file.to_string_lossy().to_string(),
+                            line: link.line,
+                            column: link.column,
+                            target: link.target.clone(),
+                        });
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    // A single document only answers for its own image folder and images, a
+    // folder inside the workspace for what lies in it
+    let scope = match &document {
+        Some(key) => {
+            let dir = key.rsplit_once('/').map_or("", |(dir, _)| dir);
+            Some(if dir.is_empty() { format!("{}/", image_dir) } else { format!("{}/{}/", dir, image_dir) })
+        }
+        None => folder.clone(),
+    };
+    let in_scope = |key: &str| scope.as_ref().is_none_or(|scope| key.starts_with(scope.as_str()));
+    let asset = |key: &String, size: u64| Asset {
+        path: root.join(key).to_string_lossy().to_string(),
+        relative_path: key.clone(),
+        size,
+        references: references.get(key).copied().unwrap_or(0),
+    };
+
+    // Candidates are the files the ignore rules let through
+    let visible: BTreeSet<String> = FileList::scan(&root).keys().cloned().collect();
+    let mut by_size: BTreeMap<u64, Vec<&String>> = BTreeMap::new();
+    for key in visible.iter().filter(|key| is_image(Path::new(key))) {
+        let size = fs::metadata(root.join(key)).map_or(0, |m| m.len());
+        if in_scope(key) && is_in_image_dir(key, image_dir) && !references.contains_key(key) {
+            report.unused.push(asset(key, size));
+        }
+        by_size.entry(size).or_default().push(key);
+    }
+
+    // Only files of equal size can be equal, so only those are hashed
+    for (size, keys) in by_size.into_iter().filter(|(size, keys)| *size > 0 && keys.len() > 1) {
+        let mut by_hash: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+        for key in keys {
+            if let Some(hash) = hash_file(&root.join(key)) {
+                by_hash.entry(hash).or_default().push(key);
+            }
+        }
+        for (hash, keys) in by_hash.into_iter().filter(|(_, keys)| keys.len() > 1) {
+            if !keys.iter().any(|key| in_scope(key) || checked_references.contains(*key)) {
+                continue;
+            }
+            let mut copies: Vec<Asset> = keys.into_iter().map(|key| asset(key, size)).collect();
+            copies.sort_by(|a, b| {
+                b.references
+                    .cmp(&a.references)
+                    .then_with(|| a.relative_path.len().cmp(&b.relative_path.len()))
+                    .then_with(|| a.relative_path.cmp(&b.relative_path))
+            });
+            let keep = copies.remove(0);
+            report.duplicates.push(DuplicateGroup { hash, size, keep, copies });
+        }
+    }
+
+    report.missing.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+    Ok(Scan { root, files, notes, pinned, report })
+}
+
+/// Unused, missing and duplicate images in a folder, or for one document,
+/// counting references from the open `workspace` when it holds `path`.
+pub fn scan(path: &Path, workspace: Option<&Path>, options: &AssetOptions) -> Result<AssetReport, String> {
+    scan_files(path, workspace, options).map(|scan| scan.report)
+}
+
+/// Delete unused images and merge duplicates as requested, after a fresh scan.
+/// Only inside a workspace, where every note linking to the images is seen.
+pub fn clean(path: &Path, workspace: Option<&Path>, options: &AssetOptions, request: &CleanupRequest) -> Result<CleanupResult, String> {
+    if !workspace.is_some_and(|root| path.starts_with(root)) {
+        return Err("Open the workspace holding it to clean up images".to_string());
+    }
+    let Scan { root, files, notes, pinned, report } = scan_files(path, workspace, options)?;
+    let requested: HashSet<&str> = request.delete.iter().map(String::as_str).collect();
+    let mut remove: Vec<String> = report
+        .unused
+        .iter()
+        .filter(|asset| requested.contains(asset.path.as_str()))
+        .map(|asset| asset.relative_path.clone())
+        .collect();
+
+    let mut moves = HashMap::new();
+    for group in report.duplicates.iter().filter(|group| request.deduplicate.contains(&group.hash)) {
+        for copy in &group.copies {
+            if pinned.contains(&copy.relative_path) {
+                return Err(format!("{} is linked from a note that isn't UTF-8, so its links can't be rewritten", copy.path));
+            }
+            moves.insert(copy.relative_path.clone(), group.keep.relative_path.clone());
+            remove.push(copy.relative_path.clone());
+        }
+    }
+    let mut edits = Vec::new();
+    if !moves.is_empty() {
+        let rename = Rename::new(files, moves, &options.image_directory);
+        for key in &notes {
+            edits.extend(rename.edit(&root, key)?);
+        }
+        write_edits(&edits)?;
+    }
+
+    let mut deleted = Vec::new();
+    for key in remove {
+        let path = root.join(&key);
+        fs::remove_file(&path).map_err(|e| format!("Cannot delete {}: {}", path.display(), e))?;
+        if let Some(parent) = path.parent() {
+            remove_empty_image_dir(parent, &options.image_directory);
+        }
+        deleted.push(path.to_string_lossy().to_string());
+    }
+    Ok(CleanupResult { deleted, edits })
+}
+
+/// Root of the open workspace, if any.
+fn workspace_root(state: &WorkspaceState) -> Option<PathBuf> {
+    state.with(|workspace| Ok(workspace.root.clone())).ok()
+}
+
+/// Report unused, missing and duplicate images in a markdown file or folder.
+#[tauri::command]
+pub async fn scan_assets(
+    state: State<'_, WorkspaceState>,
+    path: String,
+    options: Option<AssetOptions>,
+) -> Result<AssetReport, String> {
+    let workspace = workspace_root(&state);
+    tauri::async_runtime::spawn_blocking(move || scan(Path::new(&path), workspace.as_deref(), &options.unwrap_or_default()))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()))
+}
+
+/// Delete unused images and merge duplicates, rewriting links to the kept copy.
+#[tauri::command]
+pub async fn clean_assets(
+    state: State<'_, WorkspaceState>,
+    path: String,
+    options: Option<AssetOptions>,
+    request: CleanupRequest,
+) -> Result<CleanupResult, String> {
+    let workspace = workspace_root(&state);
+    tauri::async_runtime::spawn_blocking(move || clean(Path::new(&path), workspace.as_deref(), &options.unwrap_or_default(), &request))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    const FILES: &[(&str, &str)] = &[
+        ("a.md", "![one](img/one.png) ![copy](img/one-copy.png) ![[two.png]] ![gone](img/gone.png)\n\n`![x](img/code.png)`\n"),
+        ("notes/b.md", "<img src=\"img/shot.png\"> ![dup](../img/one-copy.png) ![[missing.png]]\n"),
+        ("img/one.png", "same bytes"),
+        ("img/one-copy.png", "same bytes"),
+        ("img/two.png", "two"),
+        ("img/unused.png", "unused"),
+        ("notes/img/shot.png", "shot"),
+        ("notes/img/old.gif", "old"),
+        ("photos/loose.png", "not in an image folder"),
+    ];
+
+    #[test]
+    fn test_scan_finds_unused_missing_and_duplicates() {
+        let root = TempDir::with_files("assets-scan", FILES);
+        let report = scan(&root, None, &AssetOptions::default()).unwrap();
+        assert_eq!(report.files_checked, 2);
+        let unused: Vec<&str> = report.unused.iter().map(|a| a.relative_path.as_str()).collect();
+        assert_eq!(unused, ["img/unused.png", "notes/img/old.gif"]);
+        let missing: Vec<(&str, usize)> = report.missing.iter().map(|m| (m.target.as_str(), m.line)).collect();
+        assert_eq!(missing, [("img/gone.png", 1), ("missing.png", 1)]);
+        assert_eq!(report.duplicates.len(), 1);
+        let group = &report.duplicates[0];
+        assert_eq!((group.keep.relative_path.as_str(), group.keep.references), ("img/one-copy.png", 2));
+        assert_eq!(group.copies.iter().map(|a| a.relative_path.as_str()).collect::<Vec<_>>(), ["img/one.png"]);
+
+        // A document answers for its own image folder only
+        let report = scan(&root.join("notes/b.md"), None, &AssetOptions::default()).unwrap();
+        assert_eq!(report.files_checked, 1);
+        assert_eq!(report.unused.iter().map(|a| a.relative_path.as_str()).collect::<Vec<_>>(), ["img/old.gif"]);
+        assert_eq!(report.missing.len(), 1);
+    }
+
+    #[test]
+    fn test_clean_deletes_and_merges_duplicates() {
+        let root = TempDir::with_files("assets-clean", FILES);
+        let options = AssetOptions::default();
+        let report = scan(&root, None, &options).unwrap();
+        let request = CleanupRequest {
+            delete: vec![report.unused[1].path.clone(), root.join("img/one.png").to_string_lossy().to_string()],
+            deduplicate: vec![report.duplicates[0].hash.clone()],
+        };
+        assert!(clean(&root, None, &options, &request).is_err());
+        let result = clean(&root, Some(&root), &options, &request).unwrap();
+        assert_eq!(result.deleted.len(), 2);
+        assert!(!root.join("notes/img/old.gif").exists() && root.join("notes/img/shot.png").exists());
+        assert!(!root.join("img/one.png").exists() && root.join("img/unused.png").exists());
+        let a = fs::read_to_string(root.join("a.md")).unwrap();
+        assert!(a.starts_with("![one](img/one-copy.png) ![copy](img/one-copy.png)"), "{}", a);
+        assert!(a.contains("`![x](img/code.png)`"));
+
+        let report = scan(&root, None, &options).unwrap();
+        assert!(report.duplicates.is_empty());
+    }
+
+    #[test]
+    fn test_scans_count_references_from_the_whole_workspace() {
+        let root = TempDir::with_files(
+            "assets-workspace",
+            &[
+                (".gitignore", "drafts/\n"),
+                ("a/doc.md", "![pic](img/pic.png)\n"),
+                ("a/img/pic.png", "pic"),
+                ("a/img/elsewhere.png", "elsewhere"),
+                ("a/img/hidden.png", "hidden"),
+                ("a/img/latin1.png", "latin1"),
+                ("a/img/unused.png", "unused"),
+                ("b/other.md", "![x](../a/img/elsewhere.png)\n"),
+                ("drafts/draft.md", "![x](../a/img/hidden.png)\n"),
+            ],
+        );
+        root.write("b/latin1.md", b"caf\xe9 ![x](../a/img/latin1.png)\n");
+        let options = AssetOptions::default();
+        let document = root.join("a/doc.md");
+
+        let report = scan(&document, Some(&root), &options).unwrap();
+        assert_eq!(report.files_checked, 1);
+        assert_eq!(report.unused.iter().map(|a| a.relative_path.as_str()).collect::<Vec<_>>(), ["a/img/unused.png"]);
+
+        // A folder in the workspace sees the links from notes outside it too
+        let folder = root.join("a");
+        let report = scan(&folder, Some(&root), &options).unwrap();
+        assert_eq!(report.files_checked, 1);
+        assert_eq!(report.unused.iter().map(|a| a.relative_path.as_str()).collect::<Vec<_>>(), ["a/img/unused.png"]);
+
+        // Without the workspace the scanned folder is all there is to go on
+        let request = CleanupRequest { delete: vec![root.join("a/img/elsewhere.png").to_string_lossy().to_string()], ..Default::default() };
+        assert!(clean(&document, None, &options, &request).is_err());
+        assert!(clean(&folder, None, &options, &request).is_err());
+        assert!(clean(&document, Some(&root), &options, &request).unwrap().deleted.is_empty());
+        assert!(clean(&folder, Some(&root), &options, &request).unwrap().deleted.is_empty());
+        assert!(root.join("a/img/elsewhere.png").exists());
+    }
+}
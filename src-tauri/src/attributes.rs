@@ -0,0 +1,426 @@
+//! Pandoc attribute syntax: fenced divs and bracketed spans
+//!
+//! ```markdown
+//! ::: warning
+//! Careful, this is **markdown**.
+//! :::
+//!
+//! :::: {.columns #intro}
+//! ::: column
+//! Left
+//! :::
+//! ::::
+//!
+//! Some [small caps]{.smallcaps lang=en} text.
+//! ```
+//!
+//! Fences are rewritten to `<div>` HTML blocks before parsing (blank lines are
+//! inserted so comrak parses the content as markdown); [`remap_sourcepos`]
+//! then maps node positions back to the original lines. Spans are matched in
+//! the AST so their content can contain inline markup.
+
+use comrak::arena_tree::Node;
+use comrak::nodes::{Ast, AstNode, LineColumn, NodeValue};
+use comrak::Arena;
+use regex::Regex;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+
+/// Presentational attributes `key=val` may set, besides `id`, `class` and
+/// `data-*`. Anything else (`style`, `href`, `src`, event handlers...) could
+/// load or run content in the preview and is dropped.
+const ALLOWED_KEYS: &[&str] = &["lang", "dir", "title", "width", "height"];
+
+fn is_allowed_key(key: &str) -> bool {
+    // `data-sourcepos` is ours; a second one would break scroll sync
+    ALLOWED_KEYS.contains(&key) || (key.starts_with("data-") && key != "data-sourcepos")
+}
+
+/// Parse a pandoc attribute block (`{.class #id key=val key="quoted val"}`)
+/// or a bare class name into HTML attributes, or `None` if it's malformed.
+/// Keys outside the allow-list are dropped.
+pub fn parse_attributes(spec: &str) -> Option<String> {
+    let spec = spec.trim();
+    let inner = match spec.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+        Some(inner) => inner,
+        None if is_name(spec) => return Some(format!("class=\"{}\"", html_escape(spec))),
+        None => return None,
+    };
+
+    let mut id = None;
+    let mut classes = Vec::new();
+    let mut pairs = Vec::new();
+    let mut rest = inner.trim_start();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let (name, tail) = split_token(after);
+            if !is_name(name) {
+                return None;
+            }
+            classes.push(name);
+            rest = tail;
+        } else if let Some(after) = rest.strip_prefix('#') {
+            let (name, tail) = split_token(after);
+            if !is_name(name) {
+                return None;
+            }
+            id = Some(name);
+            rest = tail;
+        } else {
+            let (key, tail) = rest.split_once('=')?;
+            if !is_name(key) {
+                return None;
+            }
+            let (value, tail) = match tail.strip_prefix('"') {
+                Some(quoted) => {
+                    let (value, tail) = quoted.split_once('"')?;
+                    (value, tail)
+                }
+                None => split_token(tail),
+            };
+            let key = key.to_ascii_lowercase();
+            match key.as_str() {
+                "id" if is_name(value) => id = Some(value),
+                "class" => classes.extend(value.split_whitespace().filter(|c| is_name(c))),
+                _ if is_allowed_key(&key) => pairs.push((key, value)),
+                _ => {}
+            }
+            rest = tail;
+        }
+        rest = rest.trim_start();
+    }
+
+    let mut attrs = Vec::new();
+    if let Some(id) = id {
+        attrs.push(format!("id=\"{}\"", html_escape(id)));
+    }
+    if !classes.is_empty() {
+        attrs.push(format!("class=\"{}\"", html_escape(&classes.join(" "))));
+    }
+    for (key, value) in pairs {
+        attrs.push(format!("{}=\"{}\"", key, html_escape(value)));
+    }
+    Some(attrs.join(" "))
+}
+
+fn split_token(s: &str) -> (&str, &str) {
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'))
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn fence_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^ {0,3}:{3,}\s*(\{[^}]*\}|[^\s{}:]+)?\s*:*\s*$").unwrap())
+}
+
+/// Rewrite `:::` fences to `<div>`/`</div>` HTML blocks.
+///
+/// Returns the new text and, for each of its lines, the 1-based line it came
+/// from in `content`. Fences inside code blocks are left alone; unclosed divs
+/// are closed at the end of the document.
+pub fn preprocess_fenced_divs(content: &str) -> (String, Vec<usize>) {
+    if !content.contains(":::") {
+        let lines = content.lines().count().max(1);
+        return (content.to_string(), (1..=lines).collect());
+    }
+
+    let mut out: Vec<String> = Vec::new();
+    let mut line_map = Vec::new();
+    // Index in `out` of each open div's tag, with its first source line
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut code_fence: Option<(char, usize)> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        let trimmed = line.trim_start();
+
+        let fence_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+        if let Some(c) = fence_char {
+            let len = trimmed.chars().take_while(|x| *x == c).count();
+            if len >= 3 {
+                match code_fence {
+                    None => code_fence = Some((c, len)),
+                    Some((open_char, open_len)) if open_char == c && len >= open_len && trimmed[len..].trim().is_empty() => {
+                        code_fence = None
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let caps = if code_fence.is_none() { fence_re().captures(line) } else { None };
+        let Some(caps) = caps else {
+            out.push(line.to_string());
+            line_map.push(number);
+            continue;
+        };
+
+        match caps.get(1).map(|m| m.as_str()) {
+            Some(spec) => match parse_attributes(spec) {
+                Some(attrs) => {
+                    open.push((out.len(), number));
+                    // Completed with data-sourcepos once the closing fence is known
+                    out.push(attrs);
+                    out.push(String::new());
+                    line_map.extend([number, number]);
+                }
+                None => {
+                    out.push(line.to_string());
+                    line_map.push(number);
+                }
+            },
+            None => match open.pop() {
+                Some((tag, start)) => {
+                    out[tag] = div_tag(&out[tag], start, number, line.len());
+                    out.extend([String::new(), "</div>".to_string(), String::new()]);
+                    line_map.extend([number, number, number]);
+                }
+                None => {
+                    out.push(line.to_string());
+                    line_map.push(number);
+                }
+            },
+        }
+    }
+
+    let last = line_map.last().copied().unwrap_or(1);
+    while let Some((tag, start)) = open.pop() {
+        out[tag] = div_tag(&out[tag], start, last, 1);
+        out.extend([String::new(), "</div>".to_string()]);
+        line_map.extend([last, last]);
+    }
+
+    let mut text = out.join("\n");
+    if content.ends_with('\n') {
+        text.push('\n');
+    }
+    (text, line_map)
+}
+
+fn div_tag(attrs: &str, start: usize, end: usize, end_column: usize) -> String {
+    let attrs = if attrs.is_empty() { String::new() } else { format!(" {}", attrs) };
+    format!("<div{} data-sourcepos=\"{}:1-{}:{}\">", attrs, start, end, end_column.max(1))
+}
+
+/// Map node positions from preprocessed lines back to the original document.
+pub fn remap_sourcepos<'a>(root: &'a AstNode<'a>, line_map: &[usize]) {
+    let map = |line: usize| match line {
+        0 => 0,
+        _ => line_map.get(line - 1).or(line_map.last()).copied().unwrap_or(line),
+    };
+    for node in root.descendants() {
+        let mut data = node.data.borrow_mut();
+        data.sourcepos.start.line = map(data.sourcepos.start.line);
+        data.sourcepos.end.line = map(data.sourcepos.end.line);
+    }
+}
+
+fn span_close_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\](\{[^{}]*\})").unwrap())
+}
+
+fn new_node<'a>(arena: &'a Arena<AstNode<'a>>, value: NodeValue, line: usize) -> &'a AstNode<'a> {
+    arena.alloc(Node::new(RefCell::new(Ast::new(value, LineColumn { line, column: 0 }))))
+}
+
+fn text_of(node: &AstNode<'_>) -> Option<String> {
+    match &node.data.borrow().value {
+        NodeValue::Text(text) => Some(text.clone()),
+        _ => None,
+    }
+}
+
+/// Find the `[` matching a `]` at `close` in `node`'s text, searching back
+/// through earlier text siblings.
+fn find_opener<'a>(node: &'a AstNode<'a>, close: usize) -> Option<(&'a AstNode<'a>, usize)> {
+    let mut depth = 0usize;
+    let mut current = Some(node);
+    let mut limit = close;
+    while let Some(candidate) = current {
+        if let Some(text) = text_of(candidate) {
+            for (i, c) in text[..limit].char_indices().rev() {
+                match c {
+                    ']' => depth += 1,
+                    '[' if depth == 0 => return Some((candidate, i)),
+                    '[' => depth -= 1,
+                    _ => {}
+                }
+            }
+        }
+        current = candidate.previous_sibling();
+        limit = current.and_then(text_of).map_or(0, |t| t.len());
+    }
+    None
+}
+
+/// Insert a text node before `anchor` unless `text` is empty.
+fn insert_text<'a>(arena: &'a Arena<AstNode<'a>>, anchor: &'a AstNode<'a>, text: &str, line: usize) {
+    if !text.is_empty() {
+        anchor.insert_before(new_node(arena, NodeValue::Text(text.to_string()), line));
+    }
+}
+
+/// Turn `[inline content]{attrs}` under `root` into `<span attrs>` elements.
+///
+/// Expects adjacent text nodes to be merged (see `crossref::merge_adjacent_text`).
+pub fn process_spans<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let mut queue: VecDeque<&'a AstNode<'a>> = root
+        .descendants()
+        .filter(|n| matches!(n.data.borrow().value, NodeValue::Text(_)))
+        .collect();
+
+    while let Some(node) = queue.pop_front() {
+        let Some(text) = text_of(node) else { continue };
+        if node.parent().is_none() || !text.contains("]{") {
+            continue;
+        }
+        let line = node.data.borrow().sourcepos.start.line;
+
+        for caps in span_close_re().captures_iter(&text) {
+            let close = caps.get(0).unwrap().start();
+            let Some(attrs) = parse_attributes(&caps[1]) else { continue };
+            let Some((opener, open)) = find_opener(node, close) else { continue };
+            let open_tag = if attrs.is_empty() { "<span>".to_string() } else { format!("<span {}>", attrs) };
+            let after = caps.get(0).unwrap().end();
+
+            if std::ptr::eq(opener, node) {
+                insert_text(arena, node, &text[..open], line);
+                node.insert_before(new_node(arena, NodeValue::HtmlInline(open_tag), line));
+                insert_text(arena, node, &text[open + 1..close], line);
+            } else {
+                let opener_text = text_of(opener).unwrap_or_default();
+                insert_text(arena, opener, &opener_text[..open], line);
+                opener.insert_before(new_node(arena, NodeValue::HtmlInline(open_tag), line));
+                insert_text(arena, opener, &opener_text[open + 1..], line);
+                opener.detach();
+                insert_text(arena, node, &text[..close], line);
+            }
+            node.insert_before(new_node(arena, NodeValue::HtmlInline("</span>".to_string()), line));
+
+            // Continue with the rest of the text, which may close an enclosing span
+            let rest = new_node(arena, NodeValue::Text(text[after..].to_string()), line);
+            node.insert_before(rest);
+            node.detach();
+            queue.push_front(rest);
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comrak::{format_html, parse_document, ComrakOptions};
+
+    fn render(markdown: &str) -> String {
+        let (processed, line_map) = preprocess_fenced_divs(markdown);
+        let mut options = ComrakOptions::default();
+        options.render.unsafe_ = true;
+        options.render.sourcepos = true;
+        let arena = Arena::new();
+        let root = parse_document(&arena, &processed, &options);
+        remap_sourcepos(root, &line_map);
+        crate::crossref::merge_adjacent_text(root);
+        process_spans(&arena, root);
+        let mut html = Vec::new();
+        format_html(root, &options, &mut html).unwrap();
+        String::from_utf8(html).unwrap()
+    }
+
+    #[test]
+    fn test_parse_attributes() {
+        assert_eq!(parse_attributes("warning").unwrap(), "class=\"warning\"");
+        assert_eq!(
+            parse_attributes("{.smallcaps .big #intro lang=en title=\"A b\"}").unwrap(),
+            "id=\"intro\" class=\"smallcaps big\" lang=\"en\" title=\"A b\""
+        );
+        assert_eq!(parse_attributes("{onclick=alert(1) .x}").unwrap(), "class=\"x\"");
+        assert_eq!(
+            parse_attributes("{#a class=\"b c\" data-role=note DATA-x=1 dir=rtl}").unwrap(),
+            "id=\"a\" class=\"b c\" data-role=\"note\" data-x=\"1\" dir=\"rtl\""
+        );
+        assert!(parse_attributes("{.}").is_none());
+        assert!(parse_attributes("two words").is_none());
+    }
+
+    #[test]
+    fn test_parse_attributes_drops_unsafe_keys() {
+        for key in ["style", "href", "src", "srcdoc", "onclick", "ONLOAD", "data-sourcepos", "xlink:href", "formaction"] {
+            let spec = format!("{{.x {}=\"v\"}}", key);
+            assert_eq!(parse_attributes(&spec).unwrap(), "class=\"x\"", "{}", key);
+        }
+        let html = render("::: {.x data-sourcepos=9:9-9:9 style=color:red}\nText\n:::\n");
+        assert!(html.contains("<div class=\"x\" data-sourcepos=\"1:1-3:3\">"), "{}", html);
+        assert!(!html.contains("9:9") && !html.contains("style="), "{}", html);
+    }
+
+    #[test]
+    fn test_fenced_div() {
+        let html = render("::: warning\nCareful, **bold**.\n:::\n\nAfter\n");
+        assert!(html.contains("<div class=\"warning\" data-sourcepos=\"1:1-3:3\">"), "{}", html);
+        assert!(html.contains("bold</strong>"), "{}", html);
+        assert!(html.contains("<p data-sourcepos=\"2:1-2:18\">Careful"), "{}", html);
+        assert!(html.contains("<p data-sourcepos=\"5:1-5:5\">After</p>"), "{}", html);
+    }
+
+    #[test]
+    fn test_nested_divs() {
+        let html = render(":::: {.columns #cols}\n::: column\nLeft\n:::\n::: column\nRight\n:::\n::::\n");
+        assert!(html.contains("<div id=\"cols\" class=\"columns\" data-sourcepos=\"1:1-8:4\">"), "{}", html);
+        assert!(html.contains("<div class=\"column\" data-sourcepos=\"2:1-4:3\">"), "{}", html);
+        assert!(html.contains("<div class=\"column\" data-sourcepos=\"5:1-7:3\">"), "{}", html);
+        assert_eq!(html.matches("</div>").count(), 3);
+        let right = html.find("Right").unwrap();
+        assert!(html[right..].matches("</div>").count() == 2, "{}", html);
+    }
+
+    #[test]
+    fn test_unclosed_div_and_code_fences() {
+        let html = render("```\n::: note\n```\n\n::: note\nOpen\n");
+        assert!(html.contains("<code>::: note\n</code>"), "{}", html);
+        assert!(html.contains("<div class=\"note\""), "{}", html);
+        assert!(html.trim_end().ends_with("</div>"), "{}", html);
+
+        let (text, map) = preprocess_fenced_divs("no fences\nhere\n");
+        assert_eq!(text, "no fences\nhere\n");
+        assert_eq!(map, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_bracketed_spans() {
+        let html = render("Some [small caps]{.smallcaps #sc lang=en} text.\n");
+        assert!(
+            html.contains("Some <span id=\"sc\" class=\"smallcaps\" lang=\"en\">small caps</span> text."),
+            "{}",
+            html
+        );
+
+        let html = render("A [**bold** and [inner]{.b}]{.a} end, [not a span] {.x}\n");
+        assert!(html.contains("A <span class=\"a\"><strong"), "{}", html);
+        assert!(html.contains("bold</strong> and <span class=\"b\">inner</span></span> end"), "{}", html);
+        assert!(html.contains("[not a span] {.x}"), "{}", html);
+    }
+
+    #[test]
+    fn test_links_and_code_are_not_spans() {
+        let html = render("[link](https://example.com){.x} and `[a]{.b}`\n");
+        assert!(html.contains("link</a>{.x}"), "{}", html);
+        assert!(html.contains(">[a]{.b}</code>"), "{}", html);
+        assert!(!html.contains("<span"), "{}", html);
+    }
+}
@@ -0,0 +1,649 @@
+//! Citations and bibliography
+//!
+//! Resolves pandoc-style citations against the bibliography named in the
+//! document frontmatter and appends a references section:
+//!
+//! ```yaml
+//! bibliography: refs.bib        # BibTeX/BibLaTeX, CSL-JSON (.json) or Hayagriva YAML
+//! csl: ieee                     # apa (default), ieee, chicago-author-date, ... or a .csl file
+//! reference-section-title: Literatur
+//! ```
+//!
+//! `[@smith2020, p. 4; @doe2019]` is a parenthetical citation, `[-@smith2020]`
+//! suppresses the author and a bare `@smith2020` is a narrative citation.
+//! Formatting is done by hayagriva's CSL processor. Parsed bibliographies and
+//! styles are cached, keyed by path and modification time.
+
+use comrak::arena_tree::Node;
+use comrak::nodes::{Ast, AstNode, LineColumn, NodeHtmlBlock, NodeValue};
+use comrak::Arena;
+use hayagriva::archive::{self, ArchivedStyle};
+use hayagriva::citationberg::json as csl_json;
+use hayagriva::citationberg::taxonomy::Locator;
+use hayagriva::citationberg::{IndependentStyle, Locale, LocaleCode, Style};
+use hayagriva::{
+    BibliographyDriver, BibliographyRequest, BufWriteFormat, CitationItem, CitationRequest, CitePurpose,
+    LocatorPayload, Rendered, SpecificLocator,
+};
+use regex::Regex;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use crate::RenderWarning;
+
+const DEFAULT_STYLE: &str = "apa";
+const DEFAULT_TITLE: &str = "References";
+
+/// Citation settings read from frontmatter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CitationConfig {
+    /// Bibliography files, resolved against the document's directory
+    pub bibliography: Vec<PathBuf>,
+    /// Archived style name (`apa`, `ieee`, ...) or path to a `.csl` file
+    pub style: String,
+    /// Heading of the appended references section
+    pub title: String,
+    /// Locale for terms such as "and" or "p.", e.g. `de-DE`
+    pub locale: Option<String>,
+}
+
+impl CitationConfig {
+    /// Read the config, or `None` if the document has no `bibliography:`.
+    pub fn from_frontmatter(frontmatter: &serde_yaml::Value, base_dir: Option<&Path>) -> Option<Self> {
+        let resolve = |p: &str| match base_dir {
+            Some(dir) if Path::new(p).is_relative() => dir.join(p),
+            _ => PathBuf::from(p),
+        };
+        let string = |key: &str| frontmatter.get(key).and_then(|v| v.as_str()).map(str::to_string);
+
+        let bibliography: Vec<PathBuf> = match frontmatter.get("bibliography")? {
+            serde_yaml::Value::String(path) => vec![resolve(path)],
+            serde_yaml::Value::Sequence(paths) => paths.iter().filter_map(|p| p.as_str()).map(resolve).collect(),
+            _ => return None,
+        };
+
+        let style = string("csl")
+            .or_else(|| string("citation-style"))
+            .map(|s| if s.ends_with(".csl") { resolve(&s).to_string_lossy().to_string() } else { s })
+            .unwrap_or_else(|| DEFAULT_STYLE.to_string());
+
+        Some(Self {
+            bibliography,
+            style,
+            title: string("reference-section-title").unwrap_or_else(|| DEFAULT_TITLE.to_string()),
+            locale: string("lang"),
+        })
+    }
+}
+
+/// Entries from one or more bibliography files.
+enum Library {
+    /// BibTeX/BibLaTeX or Hayagriva YAML
+    Hayagriva(hayagriva::Library),
+    /// CSL-JSON, keyed by item id
+    CslJson(HashMap<String, csl_json::Item>),
+}
+
+impl Library {
+    fn contains(&self, key: &str) -> bool {
+        match self {
+            Library::Hayagriva(lib) => lib.get(key).is_some(),
+            Library::CslJson(items) => items.contains_key(key),
+        }
+    }
+}
+
+type LibraryCache = Mutex<HashMap<PathBuf, (SystemTime, Arc<Library>)>>;
+
+fn library_cache() -> &'static LibraryCache {
+    static CACHE: OnceLock<LibraryCache> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Parse one bibliography file by extension.
+fn parse_library(path: &Path) -> Result<Library, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Cannot read bibliography {}: {}", path.display(), e))?;
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+
+    match ext.as_str() {
+        "json" => {
+            let items: Vec<csl_json::Item> =
+                serde_json::from_str(&text).map_err(|e| format!("Invalid CSL-JSON in {}: {}", path.display(), e))?;
+            Ok(Library::CslJson(
+                items
+                    .into_iter()
+                    .filter_map(|item| Some((item.id()?.into_owned(), item)))
+                    .collect(),
+            ))
+        }
+        "yaml" | "yml" => hayagriva::io::from_yaml_str(&text)
+            .map(Library::Hayagriva)
+            .map_err(|e| format!("Invalid Hayagriva YAML in {}: {}", path.display(), e)),
+        _ => hayagriva::io::from_biblatex_str(&text).map(Library::Hayagriva).map_err(|errors| {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            format!("Invalid BibTeX in {}: {}", path.display(), messages.join("; "))
+        }),
+    }
+}
+
+/// Load a bibliography file, reusing the parsed copy while the file is unchanged.
+fn load_library(path: &Path) -> Result<Arc<Library>, String> {
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Cannot read bibliography {}: {}", path.display(), e))?;
+
+    if let Some((cached_at, library)) = library_cache().lock().unwrap().get(path) {
+        if *cached_at == modified {
+            return Ok(library.clone());
+        }
+    }
+
+    let library = Arc::new(parse_library(path)?);
+    library_cache()
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), (modified, library.clone()));
+    Ok(library)
+}
+
+/// All configured bibliography files; they must be in the same family of formats.
+struct Bibliography(Vec<Arc<Library>>);
+
+impl Bibliography {
+    fn load(paths: &[PathBuf]) -> Result<Self, String> {
+        let mut libraries: Vec<Arc<Library>> = Vec::new();
+        for path in paths {
+            let library = load_library(path)?;
+            if let Some(first) = libraries.first() {
+                if std::mem::discriminant(first.as_ref()) != std::mem::discriminant(library.as_ref()) {
+                    return Err(format!(
+                        "Cannot mix CSL-JSON with BibTeX/YAML bibliographies ({})",
+                        path.display()
+                    ));
+                }
+            }
+            libraries.push(library);
+        }
+        if libraries.is_empty() {
+            return Err("No bibliography files given".to_string());
+        }
+        Ok(Self(libraries))
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.0.iter().any(|library| library.contains(key))
+    }
+
+    fn is_csl_json(&self) -> bool {
+        matches!(self.0[0].as_ref(), Library::CslJson(_))
+    }
+
+    fn entry(&self, key: &str) -> Option<&hayagriva::Entry> {
+        self.0.iter().find_map(|library| match library.as_ref() {
+            Library::Hayagriva(lib) => lib.get(key),
+            Library::CslJson(_) => None,
+        })
+    }
+
+    fn csl_item(&self, key: &str) -> Option<&csl_json::Item> {
+        self.0.iter().find_map(|library| match library.as_ref() {
+            Library::CslJson(items) => items.get(key),
+            Library::Hayagriva(_) => None,
+        })
+    }
+}
+
+/// Load an archived style by name, or a `.csl` file by path.
+fn load_style(name: &str) -> Result<Arc<IndependentStyle>, String> {
+    static STYLES: OnceLock<Mutex<HashMap<String, Arc<IndependentStyle>>>> = OnceLock::new();
+    let styles = STYLES.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(style) = styles.lock().unwrap().get(name) {
+        return Ok(style.clone());
+    }
+
+    let style = if name.ends_with(".csl") {
+        let xml = fs::read_to_string(name).map_err(|e| format!("Cannot read citation style {}: {}", name, e))?;
+        IndependentStyle::from_xml(&xml).map_err(|e| format!("Invalid citation style {}: {}", name, e))?
+    } else {
+        match ArchivedStyle::by_name(name).map(ArchivedStyle::get) {
+            Some(Style::Independent(style)) => style,
+            Some(Style::Dependent(_)) | None => return Err(format!("Unknown citation style: {}", name)),
+        }
+    };
+
+    let style = Arc::new(style);
+    styles.lock().unwrap().insert(name.to_string(), style.clone());
+    Ok(style)
+}
+
+fn locales() -> &'static [Locale] {
+    static LOCALES: OnceLock<Vec<Locale>> = OnceLock::new();
+    LOCALES.get_or_init(archive::locales)
+}
+
+/// One cited key with its options.
+struct CiteItem {
+    key: String,
+    /// Text before the key, as in `[see @key]`.
+    prefix: Option<String>,
+    suppress_author: bool,
+    locator: Option<(Locator, String)>,
+}
+
+impl CiteItem {
+    fn locator(&self) -> Option<SpecificLocator<'_>> {
+        self.locator
+            .as_ref()
+            .map(|(kind, value)| SpecificLocator(*kind, LocatorPayload::Str(value)))
+    }
+
+    fn purpose(&self, narrative: bool) -> Option<CitePurpose> {
+        if self.suppress_author {
+            Some(CitePurpose::Year)
+        } else if narrative {
+            Some(CitePurpose::Prose)
+        } else {
+            None
+        }
+    }
+}
+
+/// A citation in the document and the placeholder node it will be rendered into.
+struct Cite<'a> {
+    items: Vec<CiteItem>,
+    narrative: bool,
+    placeholder: &'a AstNode<'a>,
+}
+
+/// Citation key: starts and ends with a word character, internal punctuation allowed.
+const KEY: &str = r"\w(?:[\w:.#$%&+?<>~/-]*\w)?";
+
+fn bracket_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\[([^\[\]]*@[^\[\]]*)\]").unwrap())
+}
+
+fn item_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(&format!(r"^\s*(?:(.*?)\s+)?(-)?@({})\s*(?:,\s*(.*?))?\s*$", KEY)).unwrap())
+}
+
+fn bare_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(&format!(r"@({})", KEY)).unwrap())
+}
+
+/// Split a locator like `p. 4` or `chap. 2` into its kind and value; bare values are pages.
+fn parse_locator(text: &str) -> Option<(Locator, String)> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let (label, value) = match text.split_once(char::is_whitespace) {
+        Some((label, value)) => (label.to_ascii_lowercase(), value.trim()),
+        None => (String::new(), text),
+    };
+    let kind = match label.as_str() {
+        "p." | "pp." | "page" | "pages" => Locator::Page,
+        "ch." | "chap." | "chapter" | "chapters" => Locator::Chapter,
+        "sec." | "section" | "sections" | "§" | "§§" => Locator::Section,
+        "para." | "paragraph" | "¶" => Locator::Paragraph,
+        "fig." | "figure" => Locator::Figure,
+        "vol." | "volume" => Locator::Volume,
+        "l." | "ll." | "line" | "lines" => Locator::Line,
+        "n." | "note" => Locator::Note,
+        _ => return Some((Locator::Page, text.to_string())),
+    };
+    Some((kind, value.to_string()))
+}
+
+/// Parse the inside of `[...]` into cite items, or `None` if it isn't a citation group.
+fn parse_group(content: &str) -> Option<Vec<CiteItem>> {
+    content
+        .split(';')
+        .map(|part| {
+            let caps = item_re().captures(part)?;
+            Some(CiteItem {
+                key: caps[3].to_string(),
+                prefix: caps.get(1).map(|p| p.as_str().to_string()),
+                suppress_author: caps.get(2).is_some(),
+                locator: caps.get(4).and_then(|l| parse_locator(l.as_str())),
+            })
+        })
+        .collect()
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn new_node<'a>(arena: &'a Arena<AstNode<'a>>, value: NodeValue, line: usize) -> &'a AstNode<'a> {
+    arena.alloc(Node::new(RefCell::new(Ast::new(value, LineColumn { line, column: 0 }))))
+}
+
+/// Resolve citations under `root` and append the references section.
+pub fn process<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    root: &'a AstNode<'a>,
+    config: &CitationConfig,
+    warnings: &mut Vec<RenderWarning>,
+) {
+    let warn = |warnings: &mut Vec<RenderWarning>, kind: &'static str, message: String, line: usize| {
+        warnings.push(RenderWarning { kind, message, line });
+    };
+
+    let library = match Bibliography::load(&config.bibliography) {
+        Ok(library) => library,
+        Err(message) => return warn(warnings, "bibliography", message, 1),
+    };
+    let style = match load_style(&config.style) {
+        Ok(style) => style,
+        Err(message) => return warn(warnings, "bibliography", message, 1),
+    };
+
+    let mut cites: Vec<Cite<'a>> = Vec::new();
+    for node in root.descendants().collect::<Vec<_>>() {
+        let text = match &node.data.borrow().value {
+            NodeValue::Text(text) if text.contains('@') => text.clone(),
+            _ => continue,
+        };
+        if node.ancestors().any(|a| matches!(a.data.borrow().value, NodeValue::Link(_))) {
+            continue;
+        }
+        let line = node.data.borrow().sourcepos.start.line;
+
+        // (start, end, items, narrative) of each citation in this text node
+        let mut found: Vec<(usize, usize, Vec<CiteItem>, bool)> = Vec::new();
+        for caps in bracket_re().captures_iter(&text) {
+            if let Some(items) = parse_group(&caps[1]) {
+                // With prefix text, `[ask @someone]` is prose unless a key actually exists
+                let prefixed = items.iter().any(|item| item.prefix.is_some());
+                if prefixed && !items.iter().any(|item| library.contains(&item.key)) {
+                    continue;
+                }
+                let whole = caps.get(0).unwrap();
+                found.push((whole.start(), whole.end(), items, false));
+            }
+        }
+        for caps in bare_re().captures_iter(&text) {
+            let whole = caps.get(0).unwrap();
+            let inside_group = found.iter().any(|(s, e, _, _)| whole.start() >= *s && whole.end() <= *e);
+            let after_word = text[..whole.start()].chars().next_back().is_some_and(|c| c.is_alphanumeric());
+            // Bare @words are only citations when the key exists (not @mentions or e-mails)
+            if !inside_group && !after_word && library.contains(&caps[1]) {
+                let item = CiteItem { key: caps[1].to_string(), prefix: None, suppress_author: false, locator: None };
+                found.push((whole.start(), whole.end(), vec![item], true));
+            }
+        }
+        if found.is_empty() {
+            continue;
+        }
+        found.sort_by_key(|(start, ..)| *start);
+
+        let mut last = 0;
+        for (start, end, items, narrative) in found {
+            if start > last {
+                node.insert_before(new_node(arena, NodeValue::Text(text[last..start].to_string()), line));
+            }
+
+            let (known, missing): (Vec<CiteItem>, Vec<CiteItem>) =
+                items.into_iter().partition(|item| library.contains(&item.key));
+            for item in &missing {
+                warn(warnings, "missing-citation", format!("Citation key not found: @{}", item.key), line);
+            }
+
+            let placeholder = if known.is_empty() {
+                let keys: Vec<String> = missing.iter().map(|i| format!("@{}", html_escape(&i.key))).collect();
+                NodeValue::HtmlInline(format!(
+                    "<span class=\"citation citation-missing\">{}</span>",
+                    keys.join("; ")
+                ))
+            } else {
+                NodeValue::HtmlInline(String::new())
+            };
+            let placeholder_node = new_node(arena, placeholder, line);
+            node.insert_before(placeholder_node);
+            if !known.is_empty() {
+                cites.push(Cite { items: known, narrative, placeholder: placeholder_node });
+            }
+            last = end;
+        }
+        if last < text.len() {
+            node.insert_before(new_node(arena, NodeValue::Text(text[last..].to_string()), line));
+        }
+        node.detach();
+    }
+
+    if cites.is_empty() {
+        return;
+    }
+
+    let locale = config.locale.clone().map(LocaleCode);
+    // The driver is generic over a trait hayagriva doesn't export, so expand once per entry type
+    macro_rules! drive {
+        ($lookup:expr) => {{
+            let mut driver = BibliographyDriver::new();
+            for cite in &cites {
+                let items = cite
+                    .items
+                    .iter()
+                    .filter_map(|item| {
+                        let entry = $lookup(item.key.as_str())?;
+                        Some(CitationItem::new(entry, item.locator(), None, false, item.purpose(cite.narrative)))
+                    })
+                    .collect();
+                driver.citation(CitationRequest::new(items, &style, locale.clone(), locales(), None));
+            }
+            driver.finish(BibliographyRequest { style: &style, locale: locale.clone(), locale_files: locales() })
+        }};
+    }
+    let rendered: Rendered = if library.is_csl_json() {
+        drive!(|key: &str| library.csl_item(key))
+    } else {
+        drive!(|key: &str| library.entry(key))
+    };
+
+    for (cite, citation) in cites.iter().zip(&rendered.citations) {
+        let mut html = String::new();
+        let _ = citation.citation.write_buf(&mut html, BufWriteFormat::Html);
+        // CSL has no prefixes; like pandoc, the first item's goes inside the parentheses
+        // of author-date styles and before the citation otherwise
+        if let Some(prefix) = cite.items.first().and_then(|item| item.prefix.as_deref()) {
+            let prefix = html_escape(prefix);
+            html = match html.strip_prefix('(') {
+                Some(rest) => format!("({} {}", prefix, rest),
+                None => format!("{} {}", prefix, html),
+            };
+        }
+        let keys: Vec<&str> = cite.items.iter().map(|i| i.key.as_str()).collect();
+        let inner = match keys.as_slice() {
+            [key] => format!("<a href=\"#ref-{}\">{}</a>", html_escape(key), html),
+            _ => html,
+        };
+        cite.placeholder.data.borrow_mut().value = NodeValue::HtmlInline(format!(
+            "<span class=\"citation\" data-cites=\"{}\">{}</span>",
+            html_escape(&keys.join(" ")),
+            inner
+        ));
+    }
+
+    if let Some(bibliography) = rendered.bibliography {
+        let mut html = format!(
+            "<section class=\"references\" id=\"refs\">\n<h2>{}</h2>\n",
+            html_escape(&config.title)
+        );
+        for item in bibliography.items {
+            html.push_str(&format!("<div class=\"csl-entry\" id=\"ref-{}\">", html_escape(&item.key)));
+            if let Some(first) = item.first_field {
+                html.push_str("<span class=\"csl-left-margin\">");
+                let _ = first.write_buf(&mut html, BufWriteFormat::Html);
+                html.push_str("</span>");
+            }
+            let _ = item.content.write_buf(&mut html, BufWriteFormat::Html);
+            html.push_str("</div>\n");
+        }
+        html.push_str("</section>\n");
+
+        let last_line = root.data.borrow().sourcepos.end.line;
+        root.append(new_node(arena, NodeValue::HtmlBlock(NodeHtmlBlock { block_type: 6, literal: html }), last_line));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use comrak::{format_html, parse_document, ComrakOptions};
+
+    const BIBTEX: &str = r#"
+@article{smith2020,
+  author = {Smith, Jane and Doe, John},
+  title = {Rendering Markdown at Scale},
+  journal = {Journal of Documents},
+  year = {2020},
+  volume = {12},
+  pages = {1--20},
+}
+@book{knuth1984,
+  author = {Knuth, Donald E.},
+  title = {The TeXbook},
+  publisher = {Addison-Wesley},
+  year = {1984},
+}
+"#;
+
+    const CSL_JSON: &str = r#"[
+  {"id": "lovelace1843", "type": "article-journal", "title": "Notes on the Analytical Engine",
+   "author": [{"family": "Lovelace", "given": "Ada"}], "issued": {"date-parts": [[1843]]}}
+]"#;
+
+    /// The file's path, with the directory that must outlive its use.
+    fn write_temp(name: &str, content: &str) -> (TempDir, PathBuf) {
+        let dir = TempDir::new("citations");
+        let path = dir.write(name, content);
+        (dir, path)
+    }
+
+    fn config(bibliography: PathBuf, style: &str) -> CitationConfig {
+        CitationConfig {
+            bibliography: vec![bibliography],
+            style: style.to_string(),
+            title: DEFAULT_TITLE.to_string(),
+            locale: None,
+        }
+    }
+
+    fn render(markdown: &str, config: &CitationConfig) -> (String, Vec<RenderWarning>) {
+        let mut options = ComrakOptions::default();
+        options.render.unsafe_ = true;
+        let arena = Arena::new();
+        let root = parse_document(&arena, markdown, &options);
+        crate::crossref::merge_adjacent_text(root);
+        let mut warnings = Vec::new();
+        process(&arena, root, config, &mut warnings);
+        let mut html = Vec::new();
+        format_html(root, &options, &mut html).unwrap();
+        (String::from_utf8(html).unwrap(), warnings)
+    }
+
+    #[test]
+    fn test_config_from_frontmatter() {
+        let yaml: serde_yaml::Value =
+            serde_yaml::from_str("bibliography: [refs.bib, /abs/more.json]\ncsl: ieee\nlang: de-DE\n").unwrap();
+        let config = CitationConfig::from_frontmatter(&yaml, Some(Path::new("/notes"))).unwrap();
+        assert_eq!(config.bibliography, vec![PathBuf::from("/notes/refs.bib"), PathBuf::from("/abs/more.json")]);
+        assert_eq!(config.style, "ieee");
+        assert_eq!(config.locale.as_deref(), Some("de-DE"));
+        assert_eq!(config.title, DEFAULT_TITLE);
+
+        let none: serde_yaml::Value = serde_yaml::from_str("title: x").unwrap();
+        assert!(CitationConfig::from_frontmatter(&none, None).is_none());
+    }
+
+    #[test]
+    fn test_parse_group_and_locators() {
+        let items = parse_group("@smith2020, p. 4; -@knuth1984").unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].key, "smith2020");
+        assert_eq!(items[0].locator, Some((Locator::Page, "4".to_string())));
+        assert!(items[1].suppress_author);
+        assert_eq!(parse_locator("chap. 3"), Some((Locator::Chapter, "3".to_string())));
+        assert_eq!(parse_locator("12-14"), Some((Locator::Page, "12-14".to_string())));
+        assert!(parse_group("see the @ sign").is_none());
+
+        let items = parse_group("see @smith2020, p. 3; also -@knuth1984").unwrap();
+        assert_eq!(items[0].prefix.as_deref(), Some("see"));
+        assert_eq!(items[0].key, "smith2020");
+        assert_eq!(items[0].locator, Some((Locator::Page, "3".to_string())));
+        assert_eq!(items[1].prefix.as_deref(), Some("also"));
+        assert!(items[1].suppress_author);
+        // An @ inside a word is not a key
+        assert!(parse_group("mail me at me@example.com").is_none());
+    }
+
+    #[test]
+    fn test_apa_citations_and_references() {
+        let (_dir, bib) = write_temp("apa.bib", BIBTEX);
+        let (html, warnings) = render(
+            "As shown [@smith2020, p. 4], and @knuth1984 agrees.\n\nEmail me@example.com.\n",
+            &config(bib, "apa"),
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(html.contains("data-cites=\"smith2020\""), "{}", html);
+        assert!(html.contains("2020"), "{}", html);
+        assert!(html.contains("href=\"#ref-knuth1984\""), "{}", html);
+        assert!(html.contains("<section class=\"references\" id=\"refs\">"), "{}", html);
+        assert!(html.contains("id=\"ref-smith2020\""), "{}", html);
+        assert!(html.contains("Rendering Markdown at Scale"), "{}", html);
+        assert!(html.contains("me@example.com"), "{}", html);
+    }
+
+    #[test]
+    fn test_prefix_text() {
+        let (_dir, bib) = write_temp("prefix.bib", BIBTEX);
+        let (html, warnings) = render("As argued [see @smith2020, p. 3], but [ask @someone].\n", &config(bib, "apa"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(html.contains("data-cites=\"smith2020\""), "{}", html);
+        assert!(html.contains("(see "), "{}", html);
+        // Prefixed groups without a known key stay prose
+        assert!(html.contains("[ask @someone]"), "{}", html);
+    }
+
+    #[test]
+    fn test_ieee_numeric_style() {
+        let (_dir, bib) = write_temp("ieee.bib", BIBTEX);
+        let (html, _) = render("First [@knuth1984], then [@smith2020].\n", &config(bib, "ieee"));
+        assert!(html.contains("[1]"), "{}", html);
+        assert!(html.contains("[2]"), "{}", html);
+        assert!(html.contains("csl-left-margin"), "{}", html);
+    }
+
+    #[test]
+    fn test_csl_json_and_chicago() {
+        let (_dir, json) = write_temp("refs.json", CSL_JSON);
+        let (html, warnings) = render("See [@lovelace1843].\n", &config(json, "chicago-author-date"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(html.contains("Lovelace"), "{}", html);
+        assert!(html.contains("1843"), "{}", html);
+    }
+
+    #[test]
+    fn test_missing_keys_and_files_are_reported() {
+        let (_dir, bib) = write_temp("missing.bib", BIBTEX);
+        let (html, warnings) = render("Text\n\nSee [@nobody2000; @smith2020].\n", &config(bib, "apa"));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, "missing-citation");
+        assert_eq!(warnings[0].line, 3);
+        assert!(html.contains("data-cites=\"smith2020\""), "{}", html);
+
+        let (_, warnings) = render("[@a]\n", &config(PathBuf::from("/nonexistent/refs.bib"), "apa"));
+        assert_eq!(warnings[0].kind, "bibliography");
+    }
+}
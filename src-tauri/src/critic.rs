@@ -0,0 +1,386 @@
+//! CriticMarkup review annotations
+//!
+//! `{++added++}`, `{--removed--}`, `{~~old~>new~~}`, `{==highlight==}` and
+//! `{>>comment<<}` (optionally attached to a highlight) are rendered as
+//! tracked-change HTML, and can be accepted or rejected to finalise a document.
+//! Markup inside code spans, fenced code blocks (backtick or tilde) and
+//! indented code blocks is left alone.
+//!
+//! Offsets are UTF-8 byte offsets into the document.
+
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Addition,
+    Deletion,
+    Substitution,
+    Highlight,
+    Comment,
+}
+
+/// One CriticMarkup annotation and what it resolves to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CriticChange {
+    pub kind: ChangeKind,
+    /// Byte range of the markup, including the braces and any attached comment
+    pub start: usize,
+    pub end: usize,
+    /// Text left in place when the change is rejected
+    pub original: String,
+    /// Text left in place when the change is accepted
+    pub replacement: String,
+    pub comment: Option<String>,
+}
+
+fn critic_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?s)`[^`\n]*`|\{\+\+(.*?)\+\+\}|\{--(.*?)--\}|\{~~(.*?)~>(.*?)~~\}|\{==(.*?)==\}(?:\{>>(.*?)<<\})?|\{>>(.*?)<<\}",
+        )
+        .unwrap()
+    })
+}
+
+/// Width of the leading whitespace in columns, with tabs to the next multiple of four.
+fn indent_width(line: &str) -> usize {
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += 4 - width % 4,
+            _ => break,
+        }
+    }
+    width
+}
+
+fn is_list_item(line: &str) -> bool {
+    let line = line.trim_start();
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let rest = if digits > 0 {
+        line[digits..].strip_prefix(['.', ')'])
+    } else {
+        line.strip_prefix(['-', '*', '+'])
+    };
+    rest.is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
+/// Byte ranges of fenced and indented code blocks, found line by line.
+fn code_blocks(content: &str) -> Vec<Range<usize>> {
+    let mut blocks = Vec::new();
+    // (fence character, fence length, block start)
+    let mut fence: Option<(char, usize, usize)> = None;
+    let mut indented: Option<usize> = None;
+    let mut after_blank = true;
+    let mut in_list = false;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let text = line.trim_end_matches(['\n', '\r']);
+        let indent = indent_width(text);
+        let trimmed = text.trim_start();
+
+        if let Some((marker, len, block_start)) = fence {
+            let run = trimmed.chars().take_while(|&c| c == marker).count();
+            if indent < 4 && run >= len && trimmed[run..].trim().is_empty() {
+                blocks.push(block_start..offset);
+                fence = None;
+            }
+            continue;
+        }
+
+        let blank = trimmed.is_empty();
+        if let Some(block_start) = indented {
+            if blank || indent >= 4 {
+                continue;
+            }
+            blocks.push(block_start..start);
+            indented = None;
+        }
+
+        if indent >= 4 && after_blank && !in_list {
+            indented = Some(start);
+            continue;
+        }
+        if indent < 4 {
+            if let Some(marker @ ('`' | '~')) = trimmed.chars().next() {
+                let run = trimmed.chars().take_while(|&c| c == marker).count();
+                // Backtick fences can't have backticks in their info string
+                if run >= 3 && !(marker == '`' && trimmed[run..].contains('`')) {
+                    fence = Some((marker, run, start));
+                    continue;
+                }
+            }
+            if !blank {
+                in_list = is_list_item(trimmed);
+            }
+        }
+        after_blank = blank;
+    }
+
+    // Unclosed blocks run to the end of the document
+    if let Some((_, _, block_start)) = fence {
+        blocks.push(block_start..content.len());
+    }
+    if let Some(block_start) = indented {
+        blocks.push(block_start..content.len());
+    }
+    blocks
+}
+
+fn to_change(caps: &Captures, base: usize) -> Option<CriticChange> {
+    let whole = caps.get(0).unwrap();
+    let text = |i: usize| caps.get(i).map(|m| m.as_str().to_string());
+    let (kind, original, replacement, comment) = if let Some(added) = text(1) {
+        (ChangeKind::Addition, String::new(), added, None)
+    } else if let Some(removed) = text(2) {
+        (ChangeKind::Deletion, removed, String::new(), None)
+    } else if let (Some(old), Some(new)) = (text(3), text(4)) {
+        (ChangeKind::Substitution, old, new, None)
+    } else if let Some(highlighted) = text(5) {
+        (ChangeKind::Highlight, highlighted.clone(), highlighted, text(6))
+    } else if let Some(comment) = text(7) {
+        (ChangeKind::Comment, String::new(), String::new(), Some(comment))
+    } else {
+        // A code span
+        return None;
+    };
+    Some(CriticChange {
+        kind,
+        start: base + whole.start(),
+        end: base + whole.end(),
+        original,
+        replacement,
+        comment,
+    })
+}
+
+/// All annotations in `content`, in document order.
+pub fn parse(content: &str) -> Vec<CriticChange> {
+    if !content.contains('{') {
+        return Vec::new();
+    }
+    let mut changes = Vec::new();
+    let mut last = 0;
+    let end = content.len()..content.len();
+    for block in code_blocks(content).into_iter().chain(std::iter::once(end)) {
+        let prose = &content[last..block.start];
+        changes.extend(critic_re().captures_iter(prose).filter_map(|caps| to_change(&caps, last)));
+        last = block.end;
+    }
+    changes
+}
+
+/// Resolve every change for which `accept` returns `Some`.
+fn resolve(content: &str, mut decide: impl FnMut(&CriticChange) -> Option<bool>) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+    for change in parse(content) {
+        let Some(accept) = decide(&change) else { continue };
+        result.push_str(&content[last..change.start]);
+        result.push_str(if accept { &change.replacement } else { &change.original });
+        last = change.end;
+    }
+    result.push_str(&content[last..]);
+    result
+}
+
+pub fn accept_all(content: &str) -> String {
+    resolve(content, |_| Some(true))
+}
+
+pub fn reject_all(content: &str) -> String {
+    resolve(content, |_| Some(false))
+}
+
+/// Accept or reject the change containing `offset` (its closing brace included).
+pub fn apply_at(content: &str, offset: usize, accept: bool) -> Result<String, String> {
+    let mut found = false;
+    let result = resolve(content, |change| {
+        if !found && change.start <= offset && offset <= change.end {
+            found = true;
+            Some(accept)
+        } else {
+            None
+        }
+    });
+    if found {
+        Ok(result)
+    } else {
+        Err(format!("No CriticMarkup change at offset {}", offset))
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn comment_html(comment: &str) -> String {
+    format!(
+        "<span class=\"critic critic-comment\" title=\"{}\">{}</span>",
+        html_escape(comment.trim()),
+        html_escape(comment.trim())
+    )
+}
+
+/// Replace annotations with tracked-change HTML before markdown parsing.
+///
+/// Added, removed and highlighted text stays markdown; comments are plain text.
+pub fn render(content: &str) -> Cow<'_, str> {
+    let changes = parse(content);
+    if changes.is_empty() {
+        return Cow::Borrowed(content);
+    }
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+    for change in changes {
+        result.push_str(&content[last..change.start]);
+        last = change.end;
+        let html = match change.kind {
+            ChangeKind::Addition => format!("<ins class=\"critic critic-addition\">{}</ins>", change.replacement),
+            ChangeKind::Deletion => format!("<del class=\"critic critic-deletion\">{}</del>", change.original),
+            ChangeKind::Substitution => format!(
+                "<del class=\"critic critic-substitution\">{}</del><ins class=\"critic critic-substitution\">{}</ins>",
+                change.original, change.replacement
+            ),
+            ChangeKind::Highlight => format!(
+                "<mark class=\"critic critic-highlight\">{}</mark>{}",
+                change.original,
+                change.comment.as_deref().map(comment_html).unwrap_or_default()
+            ),
+            ChangeKind::Comment => comment_html(change.comment.as_deref().unwrap_or_default()),
+        };
+        result.push_str(&html);
+    }
+    result.push_str(&content[last..]);
+    Cow::Owned(result)
+}
+
+/// List the CriticMarkup annotations in a document.
+#[tauri::command]
+pub fn critic_list_changes(content: String) -> Vec<CriticChange> {
+    parse(&content)
+}
+
+/// Accept every change and drop all comments.
+#[tauri::command]
+pub fn critic_accept_all(content: String) -> String {
+    accept_all(&content)
+}
+
+/// Reject every change and drop all comments.
+#[tauri::command]
+pub fn critic_reject_all(content: String) -> String {
+    reject_all(&content)
+}
+
+/// Accept or reject the single change at byte `offset`.
+#[tauri::command]
+pub fn critic_apply_change(content: String, offset: usize, accept: bool) -> Result<String, String> {
+    apply_at(&content, offset, accept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "We {++really ++}like {--bad --}{~~cats~>dogs~~}. {==Check this==}{>>source?<<} {>>todo<<}\n";
+
+    #[test]
+    fn test_parse() {
+        let changes = parse(DOC);
+        let kinds: Vec<ChangeKind> = changes.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ChangeKind::Addition,
+                ChangeKind::Deletion,
+                ChangeKind::Substitution,
+                ChangeKind::Highlight,
+                ChangeKind::Comment
+            ]
+        );
+        assert_eq!(&DOC[changes[0].start..changes[0].end], "{++really ++}");
+        assert_eq!(changes[3].comment.as_deref(), Some("source?"));
+        assert_eq!(&DOC[changes[3].start..changes[3].end], "{==Check this==}{>>source?<<}");
+    }
+
+    #[test]
+    fn test_accept_and_reject_all() {
+        assert_eq!(accept_all(DOC), "We really like dogs. Check this \n");
+        assert_eq!(reject_all(DOC), "We like bad cats. Check this \n");
+    }
+
+    #[test]
+    fn test_apply_single_change() {
+        let offset = DOC.find("cats").unwrap();
+        assert_eq!(
+            apply_at(DOC, offset, true).unwrap(),
+            "We {++really ++}like {--bad --}dogs. {==Check this==}{>>source?<<} {>>todo<<}\n"
+        );
+        let start = DOC.find("{--").unwrap();
+        assert_eq!(
+            apply_at(DOC, start, false).unwrap(),
+            "We {++really ++}like bad {~~cats~>dogs~~}. {==Check this==}{>>source?<<} {>>todo<<}\n"
+        );
+        assert!(apply_at(DOC, 0, true).is_err());
+    }
+
+    #[test]
+    fn test_code_is_ignored() {
+        let doc = "`{++no++}`\n\n```\n{--no--}\n```\n{++yes++}";
+        assert_eq!(parse(doc).len(), 1);
+        assert_eq!(accept_all(doc), "`{++no++}`\n\n```\n{--no--}\n```\nyes");
+    }
+
+    #[test]
+    fn test_tilde_and_indented_code_are_ignored() {
+        let doc = "~~~\n{--no--}\n```\n{--still no--}\n~~~\n\n    {++no++}\n\n{++yes++}\n";
+        let changes = parse(doc);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].replacement, "yes");
+        assert_eq!(render(doc).matches("<ins").count(), 1);
+    }
+
+    #[test]
+    fn test_code_span_does_not_cross_lines() {
+        // A stray backtick must not swallow markup on later lines
+        let doc = "Use a ` here\n{++added++} and `{--code--}`";
+        let changes = parse(doc);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Addition);
+    }
+
+    #[test]
+    fn test_indented_text_after_paragraph_or_list_is_prose() {
+        let doc = "Paragraph\n    {++lazy++}\n\n- item\n\n    {++continued++}\n";
+        assert_eq!(parse(doc).len(), 2);
+        // Unclosed fences run to the end
+        assert!(parse("```\n{++no++}\n").is_empty());
+    }
+
+    #[test]
+    fn test_render() {
+        let html = render(DOC);
+        assert!(html.contains("<ins class=\"critic critic-addition\">really </ins>"), "{}", html);
+        assert!(html.contains("<del class=\"critic critic-deletion\">bad </del>"), "{}", html);
+        assert!(html.contains(
+            "<del class=\"critic critic-substitution\">cats</del><ins class=\"critic critic-substitution\">dogs</ins>"
+        ));
+        assert!(html.contains("<mark class=\"critic critic-highlight\">Check this</mark><span class=\"critic critic-comment\" title=\"source?\">source?</span>"));
+        assert!(matches!(render("plain {text}"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,492 @@
+//! Equation, figure and table numbering with cross-references
+//!
+//! pandoc-crossref style labels and references, applied to the comrak AST:
+//!
+//! - `$$ E=mc^2 $$ {#eq:energy}` numbers a display equation
+//! - `![Caption](img.png){#fig:arch}` numbers a figure and shows its caption
+//! - a `Table: Caption {#tbl:results}` paragraph next to a table numbers it
+//! - `@eq:energy` and `[@fig:arch; @tbl:results]` become links like "Eq. 1"
+//!
+//! Numbering is per document. Prefixes come from frontmatter keys named after
+//! pandoc-crossref's (`eqnPrefix`, `figPrefix`, `tblPrefix`, `figureTitle`,
+//! `tableTitle`), and references to unknown labels produce warnings.
+
+use comrak::arena_tree::Node;
+use comrak::nodes::{Ast, AstNode, LineColumn, NodeValue};
+use comrak::Arena;
+use regex::Regex;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::RenderWarning;
+
+/// Labelled element kinds, identified by their label prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    Equation,
+    Figure,
+    Table,
+}
+
+impl Kind {
+    fn from_label(label: &str) -> Option<Self> {
+        match label.split(':').next()? {
+            "eq" => Some(Kind::Equation),
+            "fig" => Some(Kind::Figure),
+            "tbl" => Some(Kind::Table),
+            _ => None,
+        }
+    }
+}
+
+/// Reference and caption prefixes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossrefConfig {
+    /// Prefix for equation references ("Eq. 1")
+    pub eq_prefix: String,
+    /// Prefix for figure references ("Figure 2")
+    pub fig_prefix: String,
+    /// Prefix for table references ("Table 3")
+    pub tbl_prefix: String,
+    /// Caption title for figures ("Figure 2: ...")
+    pub figure_title: String,
+    /// Caption title for tables ("Table 3: ...")
+    pub table_title: String,
+}
+
+impl Default for CrossrefConfig {
+    fn default() -> Self {
+        Self {
+            eq_prefix: "Eq.".to_string(),
+            fig_prefix: "Figure".to_string(),
+            tbl_prefix: "Table".to_string(),
+            figure_title: "Figure".to_string(),
+            table_title: "Table".to_string(),
+        }
+    }
+}
+
+impl CrossrefConfig {
+    /// Read prefixes from parsed frontmatter, keeping defaults for missing keys.
+    ///
+    /// Like pandoc-crossref, a prefix may also be a list; the first entry is used.
+    pub fn from_frontmatter(frontmatter: &serde_yaml::Value) -> Self {
+        let mut config = Self::default();
+        let get = |key: &str| -> Option<String> {
+            match frontmatter.get(key)? {
+                serde_yaml::Value::String(s) => Some(s.clone()),
+                serde_yaml::Value::Sequence(items) => items.first()?.as_str().map(str::to_string),
+                _ => None,
+            }
+        };
+
+        if let Some(v) = get("eqnPrefix") {
+            config.eq_prefix = v;
+        }
+        if let Some(v) = get("figPrefix") {
+            config.fig_prefix = v;
+        }
+        if let Some(v) = get("tblPrefix") {
+            config.tbl_prefix = v;
+        }
+        if let Some(v) = get("figureTitle") {
+            config.figure_title = v;
+        }
+        if let Some(v) = get("tableTitle") {
+            config.table_title = v;
+        }
+        config
+    }
+
+    fn prefix(&self, kind: Kind) -> &str {
+        match kind {
+            Kind::Equation => &self.eq_prefix,
+            Kind::Figure => &self.fig_prefix,
+            Kind::Table => &self.tbl_prefix,
+        }
+    }
+}
+
+/// `{#eq:label}` at the start of the text following a labelled element.
+fn label_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^\s*\{#((?:eq|fig|tbl):[^\s{}]+)\}").unwrap())
+}
+
+/// A table caption paragraph: `Table: Caption {#tbl:label}` or `: Caption {#tbl:label}`.
+fn table_caption_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^(?:Table)?:\s*").unwrap())
+}
+
+fn table_label_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\s*\{#(tbl:[^\s{}]+)\}\s*$").unwrap())
+}
+
+/// `[@eq:a; @fig:b]` groups and bare `@eq:a` references.
+fn reference_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"\[\s*(@(?:eq|fig|tbl):[\w:.-]*\w(?:\s*[;,]\s*@(?:eq|fig|tbl):[\w:.-]*\w)*)\s*\]|@((?:eq|fig|tbl):[\w:.-]*\w)",
+        )
+        .unwrap()
+    })
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn new_node<'a>(arena: &'a Arena<AstNode<'a>>, value: NodeValue, line: usize) -> &'a AstNode<'a> {
+    arena.alloc(Node::new(RefCell::new(Ast::new(value, LineColumn { line, column: 0 }))))
+}
+
+fn text_of(node: &AstNode<'_>) -> Option<String> {
+    match &node.data.borrow().value {
+        NodeValue::Text(text) => Some(text.clone()),
+        _ => None,
+    }
+}
+
+fn set_text(node: &AstNode<'_>, text: String) {
+    node.data.borrow_mut().value = NodeValue::Text(text);
+}
+
+fn start_line(node: &AstNode<'_>) -> usize {
+    node.data.borrow().sourcepos.start.line
+}
+
+/// Plain text content of a node's descendants (e.g. an image's alt text).
+fn collect_text<'a>(node: &'a AstNode<'a>) -> String {
+    node.descendants()
+        .filter_map(|n| match &n.data.borrow().value {
+            NodeValue::Text(t) | NodeValue::Code(comrak::nodes::NodeCode { literal: t, .. }) => Some(t.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Merge runs of adjacent text nodes; comrak splits text at brackets and
+/// other special characters, which would hide `[@fig:x]` from the regexes.
+pub fn merge_adjacent_text<'a>(root: &'a AstNode<'a>) {
+    for node in root.descendants().collect::<Vec<_>>() {
+        let Some(mut text) = text_of(node) else { continue };
+        // Skip nodes already merged into a predecessor
+        if node.parent().is_none() {
+            continue;
+        }
+        let mut merged = false;
+        while let Some(next) = node.next_sibling() {
+            let Some(next_text) = text_of(next) else { break };
+            text.push_str(&next_text);
+            let end = next.data.borrow().sourcepos.end;
+            node.data.borrow_mut().sourcepos.end = end;
+            next.detach();
+            merged = true;
+        }
+        if merged {
+            set_text(node, text);
+        }
+    }
+}
+
+/// Number labelled elements and resolve references under `root`.
+///
+/// Expects adjacent text nodes to be merged (see `merge_adjacent_text`).
+pub fn process<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    root: &'a AstNode<'a>,
+    config: &CrossrefConfig,
+    warnings: &mut Vec<RenderWarning>,
+) {
+    let mut labels: HashMap<String, (Kind, usize)> = HashMap::new();
+    let mut counters: HashMap<Kind, usize> = HashMap::new();
+    let mut register = |label: &str, kind: Kind, line: usize, warnings: &mut Vec<RenderWarning>| -> usize {
+        let counter = counters.entry(kind).or_insert(0);
+        *counter += 1;
+        if labels.contains_key(label) {
+            warnings.push(RenderWarning {
+                kind: "duplicate-label",
+                message: format!("Duplicate label {{#{}}}", label),
+                line,
+            });
+        } else {
+            labels.insert(label.to_string(), (kind, *counter));
+        }
+        *counter
+    };
+
+    // Pass 1: number labelled equations, figures and tables in document order
+    for node in root.descendants().collect::<Vec<_>>() {
+        let kind = match &node.data.borrow().value {
+            NodeValue::Math(math) if math.display_math => Kind::Equation,
+            NodeValue::Image(_) => Kind::Figure,
+            NodeValue::Table(..) => Kind::Table,
+            _ => continue,
+        };
+
+        if kind == Kind::Table {
+            let caption = [node.next_sibling(), node.previous_sibling()]
+                .into_iter()
+                .flatten()
+                .find_map(|p| table_caption(p).map(|label| (p, label)));
+            if let Some((paragraph, label)) = caption {
+                let number = register(&label, kind, start_line(paragraph), warnings);
+                label_table_caption(arena, paragraph, &label, number, config);
+            }
+            continue;
+        }
+
+        let Some(next) = node.next_sibling() else { continue };
+        let Some(text) = text_of(next) else { continue };
+        let Some(caps) = label_re().captures(&text) else { continue };
+        let label = caps[1].to_string();
+        if Kind::from_label(&label) != Some(kind) {
+            continue;
+        }
+
+        // Drop the `{#label}` from the following text
+        let rest = text[caps.get(0).unwrap().end()..].to_string();
+        if rest.is_empty() {
+            next.detach();
+        } else {
+            set_text(next, rest);
+        }
+
+        let line = start_line(node);
+        let number = register(&label, kind, line, warnings);
+        let id = html_escape(&label);
+        let (open, close) = match kind {
+            Kind::Equation => (
+                format!("<span class=\"crossref-equation\" id=\"{}\">", id),
+                format!("<span class=\"crossref-number\">({})</span></span>", number),
+            ),
+            _ => (
+                format!("<span class=\"crossref-figure\" id=\"{}\">", id),
+                format!(
+                    "<span class=\"crossref-caption\">{} {}: {}</span></span>",
+                    html_escape(&config.figure_title),
+                    number,
+                    html_escape(&collect_text(node))
+                ),
+            ),
+        };
+        node.insert_before(new_node(arena, NodeValue::HtmlInline(open), line));
+        node.insert_after(new_node(arena, NodeValue::HtmlInline(close), line));
+    }
+
+    // Pass 2: replace references, now that forward labels are known
+    for node in root.descendants().collect::<Vec<_>>() {
+        let Some(text) = text_of(node) else { continue };
+        if !text.contains('@') || node.ancestors().any(|a| matches!(a.data.borrow().value, NodeValue::Link(_))) {
+            continue;
+        }
+
+        let line = start_line(node);
+        let mut pieces: Vec<NodeValue> = Vec::new();
+        let mut last = 0;
+        for caps in reference_re().captures_iter(&text) {
+            let whole = caps.get(0).unwrap();
+            // `name@eq:x` is an e-mail-like word, not a reference
+            if caps.get(2).is_some()
+                && text[..whole.start()].chars().next_back().is_some_and(|c| c.is_alphanumeric())
+            {
+                continue;
+            }
+
+            let refs: Vec<&str> = match caps.get(1) {
+                Some(group) => group
+                    .as_str()
+                    .split([';', ','])
+                    .map(|r| r.trim().trim_start_matches('@'))
+                    .collect(),
+                None => vec![&caps[2]],
+            };
+            let html = refs
+                .iter()
+                .map(|label| render_reference(label, &labels, config, line, warnings))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            if whole.start() > last {
+                pieces.push(NodeValue::Text(text[last..whole.start()].to_string()));
+            }
+            pieces.push(NodeValue::HtmlInline(html));
+            last = whole.end();
+        }
+
+        if pieces.is_empty() {
+            continue;
+        }
+        if last < text.len() {
+            pieces.push(NodeValue::Text(text[last..].to_string()));
+        }
+        for value in pieces {
+            node.insert_before(new_node(arena, value, line));
+        }
+        node.detach();
+    }
+}
+
+fn render_reference(
+    label: &str,
+    labels: &HashMap<String, (Kind, usize)>,
+    config: &CrossrefConfig,
+    line: usize,
+    warnings: &mut Vec<RenderWarning>,
+) -> String {
+    match labels.get(label) {
+        Some(&(kind, number)) => format!(
+            "<a href=\"#{}\" class=\"crossref\">{} {}</a>",
+            html_escape(label),
+            html_escape(config.prefix(kind)),
+            number
+        ),
+        None => {
+            warnings.push(RenderWarning {
+                kind: "unresolved-reference",
+                message: format!("Unresolved reference @{}", label),
+                line,
+            });
+            format!("<span class=\"crossref crossref-unresolved\">@{}</span>", html_escape(label))
+        }
+    }
+}
+
+/// The `tbl:` label of a table caption paragraph, if `node` is one.
+fn table_caption<'a>(node: &'a AstNode<'a>) -> Option<String> {
+    if !matches!(node.data.borrow().value, NodeValue::Paragraph) {
+        return None;
+    }
+    let first = text_of(node.first_child()?)?;
+    let last = text_of(node.last_child()?)?;
+    if !table_caption_re().is_match(&first) {
+        return None;
+    }
+    Some(table_label_re().captures(&last)?[1].to_string())
+}
+
+/// Rewrite a caption paragraph to "Table N: Caption" with the label as its id.
+fn label_table_caption<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    paragraph: &'a AstNode<'a>,
+    label: &str,
+    number: usize,
+    config: &CrossrefConfig,
+) {
+    let line = start_line(paragraph);
+    if let Some(last) = paragraph.last_child() {
+        let text = text_of(last).unwrap_or_default();
+        set_text(last, table_label_re().replace(&text, "").into_owned());
+    }
+    if let Some(first) = paragraph.first_child() {
+        let text = text_of(first).unwrap_or_default();
+        set_text(first, table_caption_re().replace(&text, "").into_owned());
+    }
+
+    let open = format!(
+        "<span class=\"crossref-caption\" id=\"{}\">{} {}: ",
+        html_escape(label),
+        html_escape(&config.table_title),
+        number
+    );
+    paragraph.prepend(new_node(arena, NodeValue::HtmlInline(open), line));
+    paragraph.append(new_node(arena, NodeValue::HtmlInline("</span>".to_string()), line));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comrak::{format_html, parse_document, ComrakOptions};
+
+    fn render_with(markdown: &str, config: &CrossrefConfig) -> (String, Vec<RenderWarning>) {
+        let mut options = ComrakOptions::default();
+        options.extension.math_dollars = true;
+        options.extension.table = true;
+        options.render.unsafe_ = true;
+        let arena = Arena::new();
+        let root = parse_document(&arena, markdown, &options);
+        merge_adjacent_text(root);
+        let mut warnings = Vec::new();
+        process(&arena, root, config, &mut warnings);
+        let mut html = Vec::new();
+        format_html(root, &options, &mut html).unwrap();
+        (String::from_utf8(html).unwrap(), warnings)
+    }
+
+    fn render(markdown: &str) -> (String, Vec<RenderWarning>) {
+        render_with(markdown, &CrossrefConfig::default())
+    }
+
+    #[test]
+    fn test_equation_numbering_and_reference() {
+        let (html, warnings) = render("$$ E=mc^2 $$ {#eq:energy}\n\n$$ a^2 $$ {#eq:pyth}\n\nSee @eq:pyth and [@eq:energy].\n");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(html.contains("id=\"eq:energy\""), "{}", html);
+        assert!(html.contains("<span class=\"crossref-number\">(1)</span>"), "{}", html);
+        assert!(html.contains("<span class=\"crossref-number\">(2)</span>"), "{}", html);
+        assert!(html.contains("<a href=\"#eq:pyth\" class=\"crossref\">Eq. 2</a>"), "{}", html);
+        assert!(html.contains("<a href=\"#eq:energy\" class=\"crossref\">Eq. 1</a>."), "{}", html);
+        assert!(!html.contains("{#eq:"), "{}", html);
+    }
+
+    #[test]
+    fn test_figure_caption_and_forward_reference() {
+        let (html, warnings) = render("As shown in [@fig:arch]:\n\n![System overview](arch.png){#fig:arch}\n");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(html.contains("<span class=\"crossref-figure\" id=\"fig:arch\">"), "{}", html);
+        assert!(html.contains("Figure 1: System overview"), "{}", html);
+        assert!(html.contains("<a href=\"#fig:arch\" class=\"crossref\">Figure 1</a>:"), "{}", html);
+    }
+
+    #[test]
+    fn test_table_caption() {
+        let md = "| a | b |\n|---|---|\n| 1 | 2 |\n\nTable: Results {#tbl:results}\n\nSee @tbl:results.\n";
+        let (html, warnings) = render(md);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(html.contains("<span class=\"crossref-caption\" id=\"tbl:results\">Table 1: Results</span>"), "{}", html);
+        assert!(html.contains("class=\"crossref\">Table 1</a>"), "{}", html);
+    }
+
+    #[test]
+    fn test_multiple_references_in_brackets() {
+        let (html, _) = render("$$x$$ {#eq:a}\n\n$$y$$ {#eq:b}\n\n[@eq:a; @eq:b]\n");
+        assert!(html.contains("Eq. 1</a>, <a href=\"#eq:b\" class=\"crossref\">Eq. 2</a>"), "{}", html);
+    }
+
+    #[test]
+    fn test_unresolved_and_duplicate_warnings() {
+        let (html, warnings) = render("$$x$$ {#eq:a}\n\n$$y$$ {#eq:a}\n\nSee @fig:missing.\n");
+        assert!(html.contains("crossref-unresolved"), "{}", html);
+        let kinds: Vec<_> = warnings.iter().map(|w| w.kind).collect();
+        assert_eq!(kinds, vec!["duplicate-label", "unresolved-reference"]);
+        assert_eq!(warnings[1].line, 5);
+    }
+
+    #[test]
+    fn test_frontmatter_prefixes() {
+        let yaml: serde_yaml::Value =
+            serde_yaml::from_str("eqnPrefix: Equation\nfigPrefix: [Fig., Figs.]\nfigureTitle: Abbildung\n").unwrap();
+        let config = CrossrefConfig::from_frontmatter(&yaml);
+        assert_eq!(config.eq_prefix, "Equation");
+        assert_eq!(config.fig_prefix, "Fig.");
+        assert_eq!(config.tbl_prefix, "Table");
+
+        let (html, _) = render_with("![Bau](a.png){#fig:a}\n\n@fig:a\n", &config);
+        assert!(html.contains("Abbildung 1: Bau"), "{}", html);
+        assert!(html.contains(">Fig. 1</a>"), "{}", html);
+    }
+
+    #[test]
+    fn test_ignores_emails_code_and_citations() {
+        let (html, warnings) = render("mail me@eq:x and `@eq:y` and [@smith2020]\n");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(!html.contains("crossref"), "{}", html);
+    }
+}
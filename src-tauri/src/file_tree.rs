@@ -0,0 +1,628 @@
+//! File tree for the sidebar explorer
+//!
+//! `list_file_tree` walks a folder into typed entries, honouring `.gitignore`
+//! and `.ignore` like the quick-open file list, down to an optional depth.
+//! `watch_directory` keeps the tree current: notify events are batched,
+//! filtered by the same rules and emitted as `directory-changed` with
+//! add/remove/rename/modify changes, renames paired from their two halves
+//! where the platform reports them separately.
+
+use crate::workspace::{is_markdown, relative_path};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, State};
+
+/// Quiet period that ends a batch of file events
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "ico", "avif", "tif", "tiff"];
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileEntry {
+    pub name: String,
+    pub path: String,
+    pub relative_path: String,
+    pub kind: EntryKind,
+    /// Bytes; 0 for directories
+    pub size: u64,
+    /// Milliseconds since the epoch
+    pub modified: Option<u64>,
+    pub is_markdown: bool,
+    pub is_image: bool,
+    /// Listed children of a directory; `None` for files and for directories
+    /// below the requested depth
+    pub children: Option<Vec<FileEntry>>,
+}
+
+impl FileEntry {
+    /// Entry for `path`, without children. Symlinks report their target's size.
+    pub fn read(root: &Path, path: &Path) -> Option<Self> {
+        let metadata = fs::symlink_metadata(path).ok()?;
+        let kind = if metadata.file_type().is_symlink() {
+            EntryKind::Symlink
+        } else if metadata.is_dir() {
+            EntryKind::Directory
+        } else {
+            EntryKind::File
+        };
+        let target = if kind == EntryKind::Symlink { fs::metadata(path).ok() } else { Some(metadata) };
+        let is_file = target.as_ref().is_some_and(|m| m.is_file());
+        Some(Self {
+            name: path.file_name()?.to_string_lossy().to_string(),
+            path: path.to_string_lossy().to_string(),
+            relative_path: relative_path(root, path),
+            kind,
+            size: target.as_ref().filter(|m| m.is_file()).map_or(0, |m| m.len()),
+            modified: target
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64),
+            is_markdown: is_file && is_markdown(path),
+            is_image: is_file && is_image(path),
+            children: None,
+        })
+    }
+
+    fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Directory
+    }
+}
+
+/// Which files to list; folders are always listed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryFilter {
+    #[default]
+    All,
+    Markdown,
+    Images,
+    /// Markdown and images
+    Notes,
+}
+
+impl EntryFilter {
+    fn accepts(self, entry: &FileEntry) -> bool {
+        match self {
+            Self::All => true,
+            _ if entry.is_dir() => true,
+            Self::Markdown => entry.is_markdown,
+            Self::Images => entry.is_image,
+            Self::Notes => entry.is_markdown || entry.is_image,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Name,
+    /// Newest first
+    Modified,
+    /// Largest first
+    Size,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TreeOptions {
+    /// Levels to list; 1 lists only the folder's own entries. Unlimited if unset.
+    pub depth: Option<usize>,
+    /// Include dotfiles and dot-folders (`.git` is never listed)
+    pub show_hidden: bool,
+    /// Honour `.gitignore`, `.ignore` and git excludes
+    pub respect_ignore: bool,
+    /// With a filter other than `all`, fully listed folders without any
+    /// matching file are left out
+    pub filter: EntryFilter,
+    /// Folders always come first
+    pub sort: SortOrder,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        Self {
+            depth: None,
+            show_hidden: false,
+            respect_ignore: true,
+            filter: EntryFilter::All,
+            sort: SortOrder::Name,
+        }
+    }
+}
+
+fn compare(sort: SortOrder, a: &FileEntry, b: &FileEntry) -> Ordering {
+    let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase()).then_with(|| a.name.cmp(&b.name));
+    b.is_dir().cmp(&a.is_dir()).then_with(|| match sort {
+        SortOrder::Name => by_name(),
+        SortOrder::Modified => b.modified.cmp(&a.modified).then_with(by_name),
+        SortOrder::Size => b.size.cmp(&a.size).then_with(by_name),
+    })
+}
+
+fn walker(root: &Path, options: &TreeOptions) -> ignore::Walk {
+    ignore::WalkBuilder::new(root)
+        .require_git(false)
+        .hidden(!options.show_hidden)
+        .ignore(options.respect_ignore)
+        .git_ignore(options.respect_ignore)
+        .git_exclude(options.respect_ignore)
+        .git_global(options.respect_ignore)
+        .parents(options.respect_ignore)
+        .max_depth(options.depth)
+        .filter_entry(|entry| entry.depth() == 0 || entry.file_name() != ".git")
+        .build()
+}
+
+/// Entries of `root` as a tree, sorted and filtered per `options`.
+pub fn list(root: &Path, options: &TreeOptions) -> Result<Vec<FileEntry>, String> {
+    if !root.is_dir() {
+        return Err("Not a directory".to_string());
+    }
+    let mut children: HashMap<PathBuf, Vec<FileEntry>> = HashMap::new();
+    let mut listed = HashSet::new();
+    for entry in walker(root, options).flatten() {
+        if entry.depth() == 0 {
+            continue;
+        }
+        if entry.file_type().is_some_and(|t| t.is_dir()) && options.depth.is_none_or(|depth| entry.depth() < depth) {
+            listed.insert(entry.path().to_path_buf());
+        }
+        let Some(file) = FileEntry::read(root, entry.path()) else { continue };
+        if let Some(parent) = entry.path().parent() {
+            children.entry(parent.to_path_buf()).or_default().push(file);
+        }
+    }
+    Ok(assemble(root, &mut children, &listed, options))
+}
+
+fn assemble(
+    dir: &Path,
+    children: &mut HashMap<PathBuf, Vec<FileEntry>>,
+    listed: &HashSet<PathBuf>,
+    options: &TreeOptions,
+) -> Vec<FileEntry> {
+    let mut entries = children.remove(dir).unwrap_or_default();
+    entries.retain(|entry| options.filter.accepts(entry));
+    for entry in &mut entries {
+        let path = PathBuf::from(&entry.path);
+        if entry.is_dir() && listed.contains(&path) {
+            entry.children = Some(assemble(&path, children, listed, options));
+        }
+    }
+    if options.filter != EntryFilter::All {
+        entries.retain(|entry| entry.children.as_ref().is_none_or(|c| !c.is_empty()));
+    }
+    entries.sort_by(|a, b| compare(options.sort, a, b));
+    entries
+}
+
+/// Ignore files of a watched folder, to filter events like the walk does.
+struct Rules {
+    /// In the walk's order of precedence: `.ignore` files, then `.gitignore`,
+    /// then `.git/info/exclude`, then the global excludes file; the deepest
+    /// folder first within each
+    ignores: Vec<Gitignore>,
+    options: TreeOptions,
+}
+
+impl Rules {
+    fn load(root: &Path, options: &TreeOptions) -> Self {
+        let mut ignores = Vec::new();
+        if options.respect_ignore {
+            let folders = TreeOptions { depth: None, filter: EntryFilter::All, ..options.clone() };
+            let mut dirs: Vec<(isize, PathBuf, bool)> = walker(root, &folders)
+                .flatten()
+                .filter(|entry| entry.file_type().is_some_and(|t| t.is_dir()))
+                .map(|entry| (entry.depth() as isize, entry.into_path(), true))
+                .collect();
+            // Folders above the root count too, git's files only up to the repository root
+            let mut in_repo = !root.join(".git").exists();
+            for (dir, up) in root.ancestors().skip(1).zip(1..) {
+                dirs.push((-up, dir.to_path_buf(), in_repo));
+                in_repo = in_repo && !dir.join(".git").exists();
+            }
+            for (depth, dir, git) in dirs {
+                let file = dir.join(".ignore");
+                if file.is_file() {
+                    ignores.push((0, depth, Gitignore::new(&file).0));
+                }
+                if !git {
+                    continue;
+                }
+                let file = dir.join(".gitignore");
+                if file.is_file() {
+                    ignores.push((1, depth, Gitignore::new(&file).0));
+                }
+                let file = dir.join(".git/info/exclude");
+                if file.is_file() {
+                    let mut builder = GitignoreBuilder::new(&dir);
+                    builder.add(file);
+                    if let Ok(exclude) = builder.build() {
+                        ignores.push((2, depth, exclude));
+                    }
+                }
+            }
+            ignores.push((3, 0, GitignoreBuilder::new(root).build_global().0));
+        }
+        ignores.sort_by_key(|(kind, depth, _)| (*kind, std::cmp::Reverse(*depth)));
+        Self { ignores: ignores.into_iter().map(|(_, _, gitignore)| gitignore).collect(), options: options.clone() }
+    }
+
+    /// Whether a change at `path` shows in the tree. `entry` is `None` once
+    /// the path is gone.
+    fn shows(&self, root: &Path, path: &Path, entry: Option<&FileEntry>) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else { return false };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+        let names: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        if names.iter().any(|name| name == ".git") || (!self.options.show_hidden && names.iter().any(|name| name.starts_with('.'))) {
+            return false;
+        }
+        if let Some(depth) = self.options.depth {
+            if names.len() > depth {
+                return false;
+            }
+        }
+        let is_dir = entry.is_some_and(|e| e.is_dir());
+        for gitignore in &self.ignores {
+            if !path.starts_with(gitignore.path()) {
+                continue;
+            }
+            match gitignore.matched_path_or_any_parents(path, is_dir) {
+                Match::None => continue,
+                Match::Ignore(_) => return false,
+                Match::Whitelist(_) => break,
+            }
+        }
+        // A deleted file is reported whatever its type; the tree may show it
+        entry.is_none_or(|entry| self.options.filter.accepts(entry))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Add,
+    Remove,
+    Rename,
+    Modify,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeEvent {
+    pub kind: ChangeKind,
+    pub path: String,
+    pub relative_path: String,
+    /// Previous path of a renamed entry
+    pub old_path: Option<String>,
+    /// The entry as it is now; `None` for removals
+    pub entry: Option<FileEntry>,
+}
+
+/// Tree changes from a batch of notify events, in order and without repeats.
+fn changes(root: &Path, rules: &Rules, events: &[notify::Event]) -> Vec<TreeEvent> {
+    let mut found = Vec::new();
+    let mut change = |kind: ChangeKind, path: &Path, old: Option<&Path>| {
+        let entry = if kind == ChangeKind::Remove { None } else { FileEntry::read(root, path) };
+        if kind != ChangeKind::Remove && entry.is_none() {
+            // Gone again before the batch ended
+            return;
+        }
+        let shown = rules.shows(root, path, entry.as_ref());
+        match (old.filter(|old| rules.shows(root, old, entry.as_ref())), shown) {
+            (Some(old), true) => found.push((kind, path.to_path_buf(), Some(old.to_path_buf()), entry)),
+            // Renamed out of view
+            (Some(old), false) => found.push((ChangeKind::Remove, old.to_path_buf(), None, None)),
+            // Renamed into view reads as an addition
+            (None, true) if kind == ChangeKind::Rename => found.push((ChangeKind::Add, path.to_path_buf(), None, entry)),
+            (None, true) => found.push((kind, path.to_path_buf(), None, entry)),
+            (None, false) => {}
+        }
+    };
+
+    let mut pending_from: Option<&notify::Event> = None;
+    for event in events {
+        let Some(path) = event.paths.first() else { continue };
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                change(ChangeKind::Rename, &event.paths[1], Some(path));
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                if let Some(from) = pending_from.replace(event) {
+                    change(ChangeKind::Remove, &from.paths[0], None);
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => match pending_from.take() {
+                Some(from) if from.tracker() == event.tracker() => change(ChangeKind::Rename, path, Some(&from.paths[0])),
+                from => {
+                    if let Some(from) = from {
+                        change(ChangeKind::Remove, &from.paths[0], None);
+                    }
+                    change(ChangeKind::Add, path, None);
+                }
+            },
+            // Renames reported one path at a time, as on macOS
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                let kind = if fs::symlink_metadata(path).is_ok() { ChangeKind::Add } else { ChangeKind::Remove };
+                change(kind, path, None);
+            }
+            EventKind::Create(_) => change(ChangeKind::Add, path, None),
+            EventKind::Remove(_) => change(ChangeKind::Remove, path, None),
+            EventKind::Modify(_) => change(ChangeKind::Modify, path, None),
+            _ => {}
+        }
+    }
+    if let Some(from) = pending_from {
+        change(ChangeKind::Remove, &from.paths[0], None);
+    }
+
+    let mut seen = HashSet::new();
+    found
+        .into_iter()
+        .filter(|(kind, path, old, _)| seen.insert((*kind, path.clone(), old.clone())))
+        .map(|(kind, path, old, entry)| TreeEvent {
+            kind,
+            relative_path: relative_path(root, &path),
+            path: path.to_string_lossy().to_string(),
+            old_path: old.map(|old| old.to_string_lossy().to_string()),
+            entry,
+        })
+        .collect()
+}
+
+/// A recursive watcher reporting tree changes below a folder.
+pub struct DirectoryWatcher {
+    /// Dropping the watcher stops the batching thread
+    _watcher: RecommendedWatcher,
+}
+
+impl DirectoryWatcher {
+    pub fn start(
+        root: PathBuf,
+        options: TreeOptions,
+        on_change: impl Fn(Vec<TreeEvent>) + Send + 'static,
+    ) -> Result<Self, String> {
+        if !root.is_dir() {
+            return Err("Not a directory".to_string());
+        }
+        let (sender, receiver) = mpsc::channel::<notify::Event>();
+        let mut watcher = RecommendedWatcher::new(
+            move |res: Result<notify::Event, notify::Error>| {
+                if let Ok(event) = res {
+                    let _ = sender.send(event);
+                }
+            },
+            Config::default(),
+        )
+        .map_err(|e| e.to_string())?;
+        watcher.watch(&root, RecursiveMode::Recursive).map_err(|e| e.to_string())?;
+
+        std::thread::spawn(move || {
+            let mut rules = Rules::load(&root, &options);
+            // Ends when the watcher, and with it the sender, is dropped
+            while let Ok(first) = receiver.recv() {
+                let mut events = vec![first];
+                while let Ok(event) = receiver.recv_timeout(DEBOUNCE) {
+                    events.push(event);
+                }
+                let ignore_changed = events.iter().flat_map(|e| &e.paths).any(|path| {
+                    path.file_name().is_some_and(|name| name == ".gitignore" || name == ".ignore")
+                });
+                if ignore_changed {
+                    rules = Rules::load(&root, &options);
+                }
+                let changes = changes(&root, &rules, &events);
+                if !changes.is_empty() {
+                    on_change(changes);
+                }
+            }
+        });
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+#[derive(Default)]
+pub struct TreeWatcherState {
+    watcher: Mutex<Option<DirectoryWatcher>>,
+}
+
+/// Typed entries below `path`; see `TreeOptions` for depth and filters.
+#[tauri::command]
+pub async fn list_file_tree(path: String, options: Option<TreeOptions>) -> Result<Vec<FileEntry>, String> {
+    tauri::async_runtime::spawn_blocking(move || list(Path::new(&path), &options.unwrap_or_default()))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()))
+}
+
+/// Watch `path` for the explorer, replacing any watched folder. Changes are
+/// emitted as `directory-changed` with a list of `TreeEvent`s.
+#[tauri::command]
+pub fn watch_directory(
+    handle: AppHandle,
+    state: State<'_, TreeWatcherState>,
+    path: String,
+    options: Option<TreeOptions>,
+) -> Result<(), String> {
+    let mut watcher = state.watcher.lock().unwrap();
+    *watcher = None;
+    *watcher = Some(DirectoryWatcher::start(PathBuf::from(path), options.unwrap_or_default(), move |changes| {
+        let _ = handle.emit("directory-changed", changes);
+    })?);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unwatch_directory(state: State<'_, TreeWatcherState>) {
+    *state.watcher.lock().unwrap() = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use notify::event::{CreateKind, RemoveKind};
+
+    fn fixture(name: &str) -> TempDir {
+        TempDir::with_files(
+            &format!("tree-{}", name),
+            &[
+                (".gitignore", "build/\n*.log\n"),
+                ("notes/.ignore", "draft.md\n"),
+                ("b.md", "# B\n"),
+                ("A.md", "# A\n"),
+                ("notes/deep/c.markdown", "c\n"),
+                ("notes/draft.md", "draft\n"),
+                ("img/pic.PNG", "png"),
+                ("build/out.md", "out\n"),
+                ("debug.log", "log\n"),
+                ("todo.txt", "longer text\n"),
+                (".hidden/secret.md", "s\n"),
+            ],
+        )
+    }
+
+    fn names(entries: &[FileEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .flat_map(|e| {
+                let mut found = vec![e.relative_path.clone()];
+                found.extend(e.children.as_deref().map(names).unwrap_or_default());
+                found
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_list_tree_with_ignores_depth_and_filters() {
+        let root = fixture("list");
+        let tree = list(&root, &TreeOptions::default()).unwrap();
+        assert_eq!(names(&tree), ["img", "img/pic.PNG", "notes", "notes/deep", "notes/deep/c.markdown", "A.md", "b.md", "todo.txt"]);
+        let pic = &tree[0].children.as_ref().unwrap()[0];
+        assert_eq!((pic.kind, pic.size, pic.is_image, pic.is_markdown), (EntryKind::File, 3, true, false));
+        assert!(tree[0].modified.is_some());
+
+        let shallow = list(&root, &TreeOptions { depth: Some(1), sort: SortOrder::Size, ..Default::default() }).unwrap();
+        assert_eq!(names(&shallow), ["img", "notes", "todo.txt", "A.md", "b.md"]);
+        assert!(shallow[0].children.is_none());
+
+        let markdown = list(&root, &TreeOptions { filter: EntryFilter::Markdown, ..Default::default() }).unwrap();
+        assert_eq!(names(&markdown), ["notes", "notes/deep", "notes/deep/c.markdown", "A.md", "b.md"]);
+
+        let everything = TreeOptions { show_hidden: true, respect_ignore: false, depth: Some(1), ..Default::default() };
+        let all = names(&list(&root, &everything).unwrap());
+        assert!(["build", ".hidden", "debug.log", ".gitignore"].iter().all(|name| all.contains(&name.to_string())), "{:?}", all);
+        assert!(list(&root.join("A.md"), &TreeOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_changes_pair_renames_and_skip_ignored() {
+        let root = fixture("changes");
+        let rules = Rules::load(&root, &TreeOptions::default());
+        fs::rename(root.join("b.md"), root.join("notes/b.md")).unwrap();
+        fs::write(root.join("new.md"), "new\n").unwrap();
+        fs::write(root.join("build/more.md"), "ignored\n").unwrap();
+        fs::write(root.join("notes/draft.md"), "ignored too\n").unwrap();
+        fs::remove_file(root.join("todo.txt")).unwrap();
+
+        let name = |mode| notify::Event::new(EventKind::Modify(ModifyKind::Name(mode)));
+        let events = [
+            name(RenameMode::From).add_path(root.join("b.md")).set_tracker(7),
+            name(RenameMode::To).add_path(root.join("notes/b.md")).set_tracker(7),
+            name(RenameMode::Both).add_path(root.join("b.md")).add_path(root.join("notes/b.md")).set_tracker(7),
+            notify::Event::new(EventKind::Create(CreateKind::File)).add_path(root.join("new.md")),
+            notify::Event::new(EventKind::Create(CreateKind::File)).add_path(root.join("build/more.md")),
+            notify::Event::new(EventKind::Create(CreateKind::File)).add_path(root.join("notes/draft.md")),
+            notify::Event::new(EventKind::Remove(RemoveKind::File)).add_path(root.join("todo.txt")),
+            notify::Event::new(EventKind::Create(CreateKind::File)).add_path(root.join("vanished.md")),
+        ];
+        let found: Vec<_> = changes(&root, &rules, &events)
+            .into_iter()
+            .map(|c| (c.kind, c.relative_path, c.old_path.map(|p| relative_path(&root, Path::new(&p))), c.entry.is_some()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (ChangeKind::Rename, "notes/b.md".to_string(), Some("b.md".to_string()), true),
+                (ChangeKind::Add, "new.md".to_string(), None, true),
+                (ChangeKind::Remove, "todo.txt".to_string(), None, false),
+            ]
+        );
+
+        // Moving into an ignored folder reads as a removal
+        fs::rename(root.join("new.md"), root.join("build/new.md")).unwrap();
+        let events = [name(RenameMode::Both).add_path(root.join("new.md")).add_path(root.join("build/new.md"))];
+        let found = changes(&root, &rules, &events);
+        assert_eq!((found[0].kind, found[0].relative_path.as_str()), (ChangeKind::Remove, "new.md"));
+    }
+
+    #[test]
+    fn test_rules_follow_parent_and_git_ignores() {
+        let outer = TempDir::with_files(
+            "tree-parents",
+            &[
+                (".ignore", "secret.md\n"),
+                (".git/info/exclude", "*.tmp\n"),
+                ("vault/ok.md", "ok\n"),
+                ("vault/secret.md", "s\n"),
+                ("vault/scratch.tmp", "t\n"),
+            ],
+        );
+        let root = outer.join("vault");
+        assert_eq!(names(&list(&root, &TreeOptions::default()).unwrap()), ["ok.md"]);
+
+        let rules = Rules::load(&root, &TreeOptions::default());
+        let shows = |name: &str| {
+            let path = root.join(name);
+            rules.shows(&root, &path, FileEntry::read(&root, &path).as_ref())
+        };
+        assert!(shows("ok.md"));
+        assert!(!shows("secret.md"));
+        assert!(!shows("scratch.tmp"));
+    }
+
+    #[test]
+    fn test_watcher_reports_changes() {
+        let root = fixture("watch");
+        let (sender, receiver) = mpsc::channel();
+        let watcher = DirectoryWatcher::start(root.to_path_buf(), TreeOptions::default(), move |changes| {
+            let _ = sender.send(changes);
+        })
+        .unwrap();
+        fs::write(root.join("notes/fresh.md"), "fresh\n").unwrap();
+        let changes = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(
+            changes.iter().any(|c| c.kind == ChangeKind::Add && c.relative_path == "notes/fresh.md"),
+            "{:?}",
+            changes
+        );
+        drop(watcher);
+    }
+}
@@ -0,0 +1,430 @@
+//! Markdown formatter
+//!
+//! Normalises a document by parsing it with the same options as the renderer
+//! and writing it back with comrak's CommonMark writer. Syntax comrak doesn't
+//! know (wikilinks, `==mark==`, `\(...\)` math, `^block-id`s, fenced div
+//! lines, bracketed spans, CriticMarkup) is swapped for opaque placeholders
+//! first so the writer can't escape it, and frontmatter is kept byte for
+//! byte. Emphasis markers, setext headings and padded tables are applied on
+//! top of the writer's output.
+//!
+//! Paragraphs are never rewrapped: the preview renders every line break, so
+//! a wrapped line would show as a break.
+
+use comrak::arena_tree::Node;
+use comrak::nodes::{Ast, AstNode, LineColumn, NodeValue, TableAlignment};
+use comrak::{format_commonmark, parse_document, Arena, ComrakOptions, ListStyleType};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::sync::OnceLock;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListMarker {
+    #[default]
+    Dash,
+    Star,
+    Plus,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmphasisMarker {
+    #[default]
+    Star,
+    Underscore,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeadingStyle {
+    /// `# Title`
+    #[default]
+    Atx,
+    /// `Title` underlined with `===`/`---` for levels 1 and 2
+    Setext,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FormatOptions {
+    pub list_marker: ListMarker,
+    /// Marker for `*emphasis*`
+    pub emphasis: EmphasisMarker,
+    /// Marker for `**strong**`
+    pub strong: EmphasisMarker,
+    /// Pad table cells so the column pipes line up
+    pub align_tables: bool,
+    pub heading_style: HeadingStyle,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            list_marker: ListMarker::Dash,
+            emphasis: EmphasisMarker::Star,
+            strong: EmphasisMarker::Star,
+            align_tables: true,
+            heading_style: HeadingStyle::Atx,
+        }
+    }
+}
+
+// Private-use characters aren't escaped by the writer and never break a line
+const PLACEHOLDER_OPEN: char = '\u{E000}';
+const PLACEHOLDER_CLOSE: char = '\u{E001}';
+
+fn protect_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?sm)```.*?```|^[ \t]*~~~.*?^[ \t]*~~~|`[^`\n]*`|^[ \t]*:::.*?$|\[[^\[\]\n]*\]\{[^{}\n]*\}|\{\+\+.*?\+\+\}|\{--.*?--\}|\{~~.*?~~\}|\{==.*?==\}|\{>>.*?<<\}|!?\[\[[^\]\n]+\]\]|==[^=\n]+==|\\\(.+?\\\)|\\\[.+?\\\]|[ \t]\^[A-Za-z0-9_-]+$",
+        )
+        .unwrap()
+    })
+}
+
+fn placeholder_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new("\u{E000}([0-9]+)\u{E001}").unwrap())
+}
+
+/// Swap syntax the writer would escape for placeholders; returns the originals.
+fn protect(content: &str) -> (String, Vec<String>) {
+    let mut saved = Vec::new();
+    if content.contains(PLACEHOLDER_OPEN) {
+        // Can't tell our placeholders from the document's own; leave it as is
+        return (content.to_string(), saved);
+    }
+    let protected = protect_re().replace_all(content, |caps: &Captures| {
+        let full = &caps[0];
+        if full.trim_start().starts_with(['`', '~']) {
+            return full.to_string();
+        }
+        // Keep the separating space of a block ID and the indentation of a fenced div line
+        // outside the placeholder
+        let body = full.trim_start_matches([' ', '\t']);
+        let lead = &full[..full.len() - body.len()];
+        saved.push(body.to_string());
+        format!("{}{}{}{}", lead, PLACEHOLDER_OPEN, saved.len() - 1, PLACEHOLDER_CLOSE)
+    });
+    (protected.into_owned(), saved)
+}
+
+fn restore(content: &str, saved: &[String]) -> String {
+    if saved.is_empty() {
+        return content.to_string();
+    }
+    placeholder_re()
+        .replace_all(content, |caps: &Captures| {
+            let index: usize = caps[1].parse().unwrap_or(usize::MAX);
+            saved.get(index).cloned().unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+fn text_of(node: &AstNode<'_>) -> Option<String> {
+    match &node.data.borrow().value {
+        NodeValue::Text(text) => Some(text.clone()),
+        _ => None,
+    }
+}
+
+/// Rewrite emphasis (or strong) nodes to use `marker`, written as raw inline
+/// HTML so the writer emits it verbatim. Intraword emphasis keeps `*`, since
+/// `_` can't open or close there.
+fn set_emphasis_marker<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>, strong: bool, marker: &str) {
+    for node in root.descendants().collect::<Vec<_>>() {
+        let matches = match node.data.borrow().value {
+            NodeValue::Emph => !strong,
+            NodeValue::Strong => strong,
+            _ => false,
+        };
+        if !matches {
+            continue;
+        }
+        let before = node.previous_sibling().and_then(text_of).and_then(|t| t.chars().next_back());
+        let after = node.next_sibling().and_then(text_of).and_then(|t| t.chars().next());
+        if before.is_some_and(char::is_alphanumeric) || after.is_some_and(char::is_alphanumeric) {
+            continue;
+        }
+
+        let delimiter = || {
+            let value = NodeValue::HtmlInline(marker.to_string());
+            arena.alloc(Node::new(RefCell::new(Ast::new(value, LineColumn { line: 0, column: 0 }))))
+        };
+        node.insert_before(delimiter());
+        while let Some(child) = node.first_child() {
+            node.insert_before(child);
+        }
+        node.insert_before(delimiter());
+        node.detach();
+    }
+}
+
+/// Display width of a cell, counting East Asian wide characters as two columns.
+fn display_width(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Split a table row on unescaped pipes, without the outer ones.
+fn split_row(row: &str) -> Vec<String> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = if row.ends_with('|') && !row.ends_with("\\|") { &row[..row.len() - 1] } else { row };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut escaped = false;
+    for c in row.chars() {
+        if c == '|' && !escaped {
+            cells.push(cell.trim().to_string());
+            cell.clear();
+        } else {
+            cell.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+/// Pad the rows of one table so its columns line up.
+fn align_table(lines: &mut [String], column: usize, alignments: &[TableAlignment]) {
+    let prefixes: Vec<String> = lines.iter().map(|l| l.chars().take(column).collect()).collect();
+    let rows: Vec<Vec<String>> = lines.iter().map(|l| split_row(&l.chars().skip(column).collect::<String>())).collect();
+
+    let columns = alignments.len();
+    let mut widths = vec![3; columns];
+    for (i, row) in rows.iter().enumerate() {
+        if i == 1 {
+            continue;
+        }
+        for (c, cell) in row.iter().enumerate().take(columns) {
+            widths[c] = widths[c].max(display_width(cell));
+        }
+    }
+
+    for (i, line) in lines.iter_mut().enumerate() {
+        let mut out = prefixes[i].clone();
+        out.push('|');
+        for c in 0..columns {
+            let width = widths[c];
+            if i == 1 {
+                let dashes = |n: usize| "-".repeat(n);
+                out.push_str(&match alignments[c] {
+                    TableAlignment::Left => format!(" :{} |", dashes(width - 1)),
+                    TableAlignment::Center => format!(" :{}: |", dashes(width - 2)),
+                    TableAlignment::Right => format!(" {}: |", dashes(width - 1)),
+                    TableAlignment::None => format!(" {} |", dashes(width)),
+                });
+                continue;
+            }
+            let cell = rows[i].get(c).map(String::as_str).unwrap_or("");
+            let pad = width - display_width(cell);
+            let (left, right) = match alignments[c] {
+                TableAlignment::Right => (pad, 0),
+                TableAlignment::Center => (pad / 2, pad - pad / 2),
+                _ => (0, pad),
+            };
+            out.push_str(&format!(" {}{}{} |", " ".repeat(left), cell, " ".repeat(right)));
+        }
+        *line = out;
+    }
+}
+
+/// Apply setext headings and table alignment to formatted output.
+fn post_process(formatted: &str, parse_options: &ComrakOptions, format_options: &FormatOptions) -> String {
+    let setext = format_options.heading_style == HeadingStyle::Setext;
+    if !setext && !format_options.align_tables {
+        return formatted.to_string();
+    }
+
+    let mut options = parse_options.clone();
+    options.render.sourcepos = true;
+    let arena = Arena::new();
+    let root = parse_document(&arena, formatted, &options);
+
+    let mut lines: Vec<String> = formatted.lines().map(str::to_string).collect();
+    // Underlines to add after a line, keyed by line index
+    let mut underlines: Vec<(usize, String)> = Vec::new();
+
+    for node in root.descendants() {
+        let data = node.data.borrow();
+        let start = data.sourcepos.start;
+        match &data.value {
+            NodeValue::Heading(heading) if setext && !heading.setext && heading.level <= 2 => {
+                let index = start.line - 1;
+                let Some(line) = lines.get(index) else { continue };
+                let prefix: String = line.chars().take(start.column - 1).collect();
+                let text = line.chars().skip(start.column - 1).collect::<String>();
+                let text = text.trim_start_matches('#').trim().to_string();
+                if text.is_empty() {
+                    continue;
+                }
+                // Continuation prefix: keep blockquote markers, blank out list markers
+                let indent: String = prefix.chars().map(|c| if c == '>' { '>' } else { ' ' }).collect();
+                let marker = if heading.level == 1 { "=" } else { "-" };
+                let underline = format!("{}{}", indent, marker.repeat(display_width(&text).max(3)));
+                lines[index] = format!("{}{}", prefix, text);
+                underlines.push((index, underline.trim_end().to_string()));
+            }
+            NodeValue::Table(table) if format_options.align_tables => {
+                let first = start.line - 1;
+                let last = (data.sourcepos.end.line).min(lines.len());
+                if last > first + 1 {
+                    align_table(&mut lines[first..last], start.column - 1, &table.alignments);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (index, underline) in underlines.into_iter().rev() {
+        lines.insert(index + 1, underline);
+    }
+    let mut result = lines.join("\n");
+    if formatted.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Format a markdown body (no frontmatter).
+pub fn format_body(content: &str, parse_options: &ComrakOptions, format_options: &FormatOptions) -> String {
+    let (protected, saved) = protect(content);
+
+    let arena = Arena::new();
+    let root = parse_document(&arena, &protected, parse_options);
+    if format_options.emphasis == EmphasisMarker::Underscore {
+        set_emphasis_marker(&arena, root, false, "_");
+    }
+    if format_options.strong == EmphasisMarker::Underscore {
+        set_emphasis_marker(&arena, root, true, "__");
+    }
+
+    let mut options = parse_options.clone();
+    options.render.hardbreaks = false;
+    options.render.sourcepos = false;
+    options.render.list_style = match format_options.list_marker {
+        ListMarker::Dash => ListStyleType::Dash,
+        ListMarker::Star => ListStyleType::Star,
+        ListMarker::Plus => ListStyleType::Plus,
+    };
+
+    let mut output = Vec::new();
+    format_commonmark(root, &options, &mut output).expect("writing markdown to a Vec cannot fail");
+    let formatted = String::from_utf8(output).expect("comrak emits valid UTF-8");
+
+    let formatted = post_process(&formatted, parse_options, format_options);
+    restore(&formatted, &saved)
+}
+
+/// Format a whole document, keeping its frontmatter as written.
+pub fn format_document(content: &str, format_options: &FormatOptions) -> String {
+    let mut parse_options = ComrakOptions::default();
+    crate::configure_markdown_options(&mut parse_options);
+
+    let (body, _metadata) = crate::split_frontmatter(content);
+    let frontmatter = &content[..content.len() - body.len()];
+
+    let mut result = frontmatter.to_string();
+    if !frontmatter.is_empty() && !frontmatter.ends_with('\n') {
+        result.push('\n');
+    }
+    if !frontmatter.is_empty() && body.starts_with(['\n', '\r']) {
+        result.push('\n');
+    }
+    result.push_str(&format_body(body, &parse_options, format_options));
+    result
+}
+
+/// Normalise a markdown document.
+#[tauri::command]
+pub fn format_markdown(content: String, options: Option<FormatOptions>) -> String {
+    format_document(&content, &options.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(content: &str, options: &FormatOptions) -> String {
+        let once = format_document(content, options);
+        let twice = format_document(&once, options);
+        assert_eq!(once, twice, "formatting should be idempotent");
+        once
+    }
+
+    const DOC: &str = "---\ntitle: Test\ntags: [a, b]\n---\n\nTitle\n=====\n\n* one\n* two with [[Wiki Page|alias]] and ![[embed.png]]\n\nSome ==marked== text, $x^2$ and \\(a_b\\) inline ^block-1\n\n$$\n\\sum_i x_i\n$$\n\n|a|b|\n|:-|-:|\n|long cell|1|\n";
+
+    #[test]
+    fn test_round_trips_extensions() {
+        let formatted = format(DOC, &FormatOptions::default());
+        assert!(formatted.starts_with("---\ntitle: Test\ntags: [a, b]\n---\n\n# Title\n"), "{}", formatted);
+        assert!(formatted.contains("- one\n- two with [[Wiki Page|alias]] and ![[embed.png]]\n"), "{}", formatted);
+        assert!(formatted.contains("Some ==marked== text, $x^2$ and \\(a_b\\) inline ^block-1\n"), "{}", formatted);
+        assert!(formatted.contains("$$\n\\sum_i x_i\n$$"), "{}", formatted);
+        assert!(formatted.contains("| a         |   b |\n| :-------- | --: |\n| long cell |   1 |\n"), "{}", formatted);
+    }
+
+    #[test]
+    fn test_list_marker_and_emphasis() {
+        let options = FormatOptions {
+            list_marker: ListMarker::Star,
+            emphasis: EmphasisMarker::Underscore,
+            strong: EmphasisMarker::Underscore,
+            ..Default::default()
+        };
+        let formatted = format("- *a* and **b**\n- in*tra*word\n", &options);
+        assert_eq!(formatted, "* _a_ and __b__\n* in*tra*word\n");
+    }
+
+    #[test]
+    fn test_setext_headings() {
+        let options = FormatOptions { heading_style: HeadingStyle::Setext, ..Default::default() };
+        let formatted = format("# One\n\n## Two\n\n### Three\n\n> # Quoted\n", &options);
+        assert_eq!(formatted, "One\n===\n\nTwo\n---\n\n### Three\n\n> Quoted\n> ======\n");
+    }
+
+    #[test]
+    fn test_tables_unaligned_and_cjk() {
+        let options = FormatOptions { align_tables: false, ..Default::default() };
+        assert_eq!(format("|a|b|\n|-|-|\n|c|d|\n", &options), "| a | b |\n| --- | --- |\n| c | d |\n");
+
+        let formatted = format("|名前|x|\n|-|-|\n|ab|y|\n", &FormatOptions::default());
+        assert!(formatted.contains("| 名前 | x   |\n| ---- | --- |\n| ab   | y   |\n"), "{}", formatted);
+    }
+
+    #[test]
+    fn test_round_trips_divs_spans_and_critic_markup() {
+        let doc = "::: {.note #n1}\nSome [x]{.y} text with {++added++}, {--gone--}, {~~old~>new~~}, {==hi==}{>>a <note>?<<} done.\n:::\n\n> ::: warning\n> Careful\n> :::\n";
+        assert_eq!(format(doc, &FormatOptions::default()), doc);
+    }
+
+    #[test]
+    fn test_code_is_untouched() {
+        let formatted = format("```rust\nlet a = [[1]]; // ==x==\n```\n\nUse `[[not a link]]`.\n", &FormatOptions::default());
+        assert!(formatted.contains("\nlet a = [[1]]; // ==x==\n```\n"), "{}", formatted);
+        assert!(formatted.contains("Use `[[not a link]]`.\n"), "{}", formatted);
+
+        let formatted = format("~~~ python\nx = \"`\" # [[y]]\n~~~\n\nAfter ==z==\n", &FormatOptions::default());
+        assert!(formatted.contains("x = \"`\" # [[y]]\n"), "{}", formatted);
+        assert!(formatted.contains("After ==z==\n"), "{}", formatted);
+    }
+}
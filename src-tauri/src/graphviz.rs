@@ -0,0 +1,360 @@
+//! GraphViz rendering module
+//!
+//! Renders DOT source to SVG with layout-rs (pure Rust).
+//!
+//! Per-fence options come from the code block info string, e.g.
+//! ```` ```dot debug no-opt theme=dark ````, and parse errors are reported
+//! with a line/column so the editor can point at the offending DOT line.
+
+use layout::backends::svg::SVGWriter;
+use layout::gv::parser::ast;
+use layout::gv::{DotParser, GraphBuilder, Lexer, Token};
+use serde::{Deserialize, Serialize};
+
+use crate::highlight::Theme;
+
+/// Font family emitted by layout-rs for every text class.
+const DEFAULT_FONT_FAMILY: &str = "font-family: Times, serif;";
+
+/// Rendering options for a single DOT fence.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphvizOptions {
+    /// Draw layout debugging aids (bounding boxes, connectors)
+    pub debug: bool,
+    /// Skip the rank/crossing optimiser in layout-rs
+    pub disable_optimizations: bool,
+    /// Skip node placement entirely (raw rank order)
+    pub disable_layout: bool,
+    /// Highlight theme whose colours are used for nodes, edges and text
+    pub theme: Option<String>,
+}
+
+impl GraphvizOptions {
+    /// Parse options from the rest of a fence info string.
+    ///
+    /// Accepts whitespace or comma separated flags, optionally wrapped in `{}`:
+    /// `debug`, `no-opt`, `no-layout` and `theme=<name>`. Unknown flags are ignored.
+    pub fn from_info_string(info: &str) -> Self {
+        let mut options = Self::default();
+        let info = info.trim().trim_start_matches('{').trim_end_matches('}');
+
+        for flag in info.split(|c: char| c.is_whitespace() || c == ',') {
+            let flag = flag.trim_start_matches('.');
+            let (key, value) = match flag.split_once('=') {
+                Some((k, v)) => (k, Some(v.trim_matches('"'))),
+                None => (flag, None),
+            };
+            match key {
+                "debug" => options.debug = true,
+                "no-opt" | "noopt" | "no-optimize" => options.disable_optimizations = true,
+                "no-layout" | "nolayout" => options.disable_layout = true,
+                "theme" => options.theme = value.filter(|v| !v.is_empty()).map(str::to_string),
+                _ => {}
+            }
+        }
+
+        options
+    }
+}
+
+/// A DOT parse error with a 1-based source location.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DotParseError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for DotParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DOT parse error at {}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for DotParseError {}
+
+/// Render DOT source to an SVG string.
+pub fn render(code: &str, options: &GraphvizOptions) -> Result<String, DotParseError> {
+    let mut parser = DotParser::new(code);
+    let mut graph = match parser.process() {
+        Ok(graph) => graph,
+        Err(message) => {
+            let offset = locate_parse_error(code, &message);
+            let (line, column) = line_column(code, offset);
+            return Err(DotParseError { message, line, column });
+        }
+    };
+
+    let colors = options
+        .theme
+        .as_deref()
+        .and_then(|t| t.parse::<Theme>().ok())
+        .map(|t| t.colors());
+
+    if let Some(colors) = &colors {
+        apply_theme_defaults(&mut graph, &web_color(&colors.foreground), &web_color(&colors.background));
+    }
+
+    let font_name = graph_font_name(&graph);
+
+    let mut builder = GraphBuilder::new();
+    builder.visit_graph(&graph);
+    let mut visual_graph = builder.get();
+
+    let mut svg_writer = SVGWriter::new();
+    visual_graph.do_it(
+        options.debug,
+        options.disable_optimizations,
+        options.disable_layout,
+        &mut svg_writer,
+    );
+    let mut svg = svg_writer.finalize();
+
+    // layout-rs hardcodes a serif font and black text/arrowheads
+    if let Some(font) = font_name {
+        let family = format!("font-family: \"{}\", sans-serif;", font.replace('"', ""));
+        svg = svg.replace(DEFAULT_FONT_FAMILY, &family);
+    }
+    if let Some(colors) = &colors {
+        let fg = web_color(&colors.foreground);
+        let rules = format!("<style>\ntext {{ fill: {fg}; }}\nmarker polygon {{ fill: {fg}; }}\n");
+        svg = svg.replacen("<style>\n", &rules, 1);
+    }
+
+    Ok(svg)
+}
+
+/// Prepend `node [...]` / `edge [...]` defaults so explicit attributes still win.
+fn apply_theme_defaults(graph: &mut ast::Graph, foreground: &str, background: &str) {
+    let mut node_attrs = ast::AttributeList::new();
+    node_attrs.add_attr("color", foreground);
+    node_attrs.add_attr("fillcolor", background);
+    node_attrs.add_attr("fontcolor", foreground);
+
+    let mut edge_attrs = ast::AttributeList::new();
+    edge_attrs.add_attr("color", foreground);
+    edge_attrs.add_attr("fontcolor", foreground);
+
+    let defaults = [
+        ast::Stmt::Attribute(ast::AttrStmt::new(ast::AttrStmtTarget::Node, node_attrs)),
+        ast::Stmt::Attribute(ast::AttrStmt::new(ast::AttrStmtTarget::Edge, edge_attrs)),
+    ];
+    let user_stmts = std::mem::take(&mut graph.list.list);
+    graph.list.list = defaults.into_iter().chain(user_stmts).collect();
+}
+
+/// Find a `fontname` set on the graph, or on all nodes, at the top level.
+fn graph_font_name(graph: &ast::Graph) -> Option<String> {
+    let mut font = None;
+    for stmt in &graph.list.list {
+        if let ast::Stmt::Attribute(attr) = stmt {
+            if matches!(attr.target, ast::AttrStmtTarget::Graph | ast::AttrStmtTarget::Node) {
+                for (key, value) in attr.list.iter() {
+                    if key == "fontname" {
+                        font = Some(value.clone());
+                    }
+                }
+            }
+        }
+    }
+    font
+}
+
+/// Normalise theme colours (`#RRGGBB` or `#RRGGBBAA`) to `#RRGGBB`.
+fn web_color(color: &str) -> String {
+    if color.starts_with('#') && color.len() == 9 {
+        color[..7].to_string()
+    } else {
+        color.to_string()
+    }
+}
+
+/// Find the character offset of a parse error.
+///
+/// layout-rs does not expose the parser position, so this re-parses prefixes of
+/// the source cut at token boundaries. Once a prefix contains the offending
+/// token the parser fails with the same message no matter what follows it, so
+/// the first such prefix (checked against a few different continuations to
+/// tell it apart from an early end of input) ends at the bad token.
+fn locate_parse_error(code: &str, message: &str) -> usize {
+    let chars: Vec<char> = code.chars().collect();
+    let token_spans = token_spans(&chars);
+
+    let fails_here = |end: usize| -> bool {
+        let prefix: String = chars[..end].iter().collect();
+        ["", " =", " ]", " x", " }"].iter().all(|suffix| {
+            let candidate = format!("{}{}", prefix, suffix);
+            matches!(DotParser::new(&candidate).process(), Err(m) if m == message)
+        })
+    };
+
+    // Smallest token index whose prefix already reproduces the error.
+    let (mut lo, mut hi) = (0, token_spans.len());
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if fails_here(token_spans[mid].1) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    match token_spans.get(lo) {
+        Some(&(start, _)) => start,
+        // The error is only triggered by the end of input (e.g. a missing '}').
+        None => chars.len(),
+    }
+}
+
+/// Tokenise DOT source into `(start, end)` character spans.
+///
+/// Stops after the first lexer error, which is always reported as a token.
+fn token_spans(chars: &[char]) -> Vec<(usize, usize)> {
+    let mut lexer = Lexer::new(chars.to_vec());
+    let mut spans = Vec::new();
+    let mut prev_end = 0;
+
+    loop {
+        let token = lexer.next_token();
+        // `pos` points one past the lookahead character, except at end of input.
+        let end = if lexer.ch == '\0' { chars.len() } else { lexer.pos - 1 };
+        let start = (prev_end..end)
+            .find(|&i| !chars[i].is_whitespace())
+            .unwrap_or(prev_end);
+
+        match token {
+            Token::EOF => break,
+            Token::Error(pos) => {
+                let at = pos.saturating_sub(1).min(chars.len());
+                spans.push((start.min(at), at));
+                break;
+            }
+            _ => spans.push((start, end)),
+        }
+        prev_end = end;
+    }
+
+    spans
+}
+
+/// Convert a character offset into a 1-based line and column.
+fn line_column(code: &str, offset: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut column = 1;
+    for c in code.chars().take(offset) {
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_from_info_string() {
+        let options = GraphvizOptions::from_info_string("debug no-opt theme=dark");
+        assert!(options.debug);
+        assert!(options.disable_optimizations);
+        assert!(!options.disable_layout);
+        assert_eq!(options.theme.as_deref(), Some("dark"));
+
+        let braced = GraphvizOptions::from_info_string("{.no-layout, theme=\"light\"}");
+        assert!(braced.disable_layout);
+        assert_eq!(braced.theme.as_deref(), Some("light"));
+
+        assert_eq!(GraphvizOptions::from_info_string(""), GraphvizOptions::default());
+    }
+
+    #[test]
+    fn test_rankdir_lr() {
+        let tb = render("digraph { a -> b -> c }", &GraphvizOptions::default()).unwrap();
+        let lr = render("digraph { rankdir=LR; a -> b -> c }", &GraphvizOptions::default()).unwrap();
+        assert!(lr.contains("<svg"));
+        assert_ne!(tb, lr, "rankdir=LR should change the layout");
+    }
+
+    #[test]
+    fn test_fontname_replaces_default_font() {
+        let svg = render(
+            "digraph { graph [fontname=Helvetica]; a -> b }",
+            &GraphvizOptions::default(),
+        )
+        .unwrap();
+        assert!(svg.contains("Helvetica"));
+        assert!(!svg.contains(DEFAULT_FONT_FAMILY));
+    }
+
+    #[test]
+    fn test_subgraph_clusters() {
+        let code = r#"
+digraph G {
+    subgraph cluster_0 {
+        label="Frontend";
+        ui -> api;
+    }
+    subgraph cluster_1 {
+        label="Backend";
+        db;
+    }
+    api -> db;
+}
+"#;
+        let svg = render(code, &GraphvizOptions::default()).unwrap();
+        for node in ["ui", "api", "db"] {
+            assert!(svg.contains(node), "cluster node {} should be rendered", node);
+        }
+    }
+
+    #[test]
+    fn test_debug_and_no_opt_render() {
+        let options = GraphvizOptions {
+            debug: true,
+            disable_optimizations: true,
+            ..Default::default()
+        };
+        let svg = render("digraph { a -> b; a -> c; b -> c }", &options).unwrap();
+        assert!(svg.contains("<svg"));
+    }
+
+    #[test]
+    fn test_dark_theme_colors() {
+        let options = GraphvizOptions {
+            theme: Some("dark".to_string()),
+            ..Default::default()
+        };
+        let svg = render("digraph { a -> b }", &options).unwrap();
+        assert!(svg.to_lowercase().contains("#d4d4d4"), "dark foreground should be applied");
+
+        let explicit = render("digraph { a [color=red]; a -> b }", &options).unwrap();
+        assert!(explicit.contains("#ff0000"), "explicit colours should override theme defaults");
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let code = "digraph G {\n    a -> b;\n    c -> ;\n}\n";
+        let err = render(code, &GraphvizOptions::default()).unwrap_err();
+        assert_eq!(err.line, 3, "error should point at the bad edge: {:?}", err);
+        assert!(!err.message.is_empty());
+    }
+
+    #[test]
+    fn test_parse_error_at_end_of_input() {
+        let code = "digraph G {\n    a -> b;\n";
+        let err = render(code, &GraphvizOptions::default()).unwrap_err();
+        assert_eq!(err.line, 3);
+    }
+
+    #[test]
+    fn test_lexer_error_location() {
+        let code = "digraph {\n  a -> \"unterminated\n}";
+        let err = render(code, &GraphvizOptions::default()).unwrap_err();
+        assert!(err.line >= 2, "{:?}", err);
+    }
+}
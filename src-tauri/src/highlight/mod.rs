@@ -319,6 +319,37 @@ impl TreeSitterHighlighter {
     
     /// Highlight source code and return HTML with CSS classes.
     pub fn highlight(&self, source: &str, language: &str) -> HighlightResult<String> {
+        self.with_highlights(source, language, |highlights| self.render_html(source, highlights))
+    }
+    
+    /// Highlight source code and return a standalone SVG image.
+    ///
+    /// Colours are inlined from the theme instead of CSS classes, so the SVG
+    /// renders outside the webview (e.g. when rasterised to PNG for export).
+    pub fn highlight_svg(&self, source: &str, language: &str) -> HighlightResult<String> {
+        self.with_highlights(source, language, |highlights| {
+            let mut renderer = SvgRenderer::new(source, &self.theme);
+            for event in highlights {
+                match event {
+                    Ok(HighlightEvent::Source { start, end }) => renderer.push_source(start, end),
+                    Ok(HighlightEvent::HighlightStart(highlight)) => renderer.push_highlight_start(highlight.0),
+                    Ok(HighlightEvent::HighlightEnd) => renderer.push_highlight_end(),
+                    Err(e) => {
+                        return Err(HighlightError::ParseError(format!("Highlight event error: {:?}", e)));
+                    }
+                }
+            }
+            Ok(renderer.finish())
+        })
+    }
+    
+    /// Run tree-sitter highlighting and hand the event stream to `render`.
+    fn with_highlights<T>(
+        &self,
+        source: &str,
+        language: &str,
+        render: impl FnOnce(&mut dyn Iterator<Item = Result<HighlightEvent, tree_sitter_highlight::Error>>) -> HighlightResult<T>,
+    ) -> HighlightResult<T> {
         // Get the canonical language name (resolves aliases like "csharp" -> "c-sharp")
         let canonical_name = self.registry.get_canonical_name(language);
         
@@ -335,15 +366,14 @@ impl TreeSitterHighlighter {
         let mut highlighter = Highlighter::new();
         
         // Highlight the source code
-        let highlights = highlighter.highlight(
+        let mut highlights = highlighter.highlight(
             config,
             source.as_bytes(),
             None,
             |_| None,
         ).map_err(|e| HighlightError::ParseError(format!("Highlight error: {:?}", e)))?;
         
-        // Convert highlights to HTML
-        self.render_html(source, highlights)
+        render(&mut highlights)
     }
     
     /// Render highlights to HTML with CSS classes.
@@ -453,6 +483,128 @@ impl<'a> HtmlRenderer<'a> {
     }
 }
 
+/// SVG renderer for highlighted code, one `<text>` element per line.
+struct SvgRenderer<'a> {
+    source: &'a str,
+    captured_names: Vec<String>,
+    colors: std::borrow::Cow<'static, themes::ThemeColors>,
+    /// Finished lines of (text, fill colour) runs
+    lines: Vec<Vec<(String, Option<String>)>>,
+    highlight_stack: Vec<usize>,
+    current_source_start: usize,
+}
+
+impl<'a> SvgRenderer<'a> {
+    const FONT_SIZE: f64 = 14.0;
+    const LINE_HEIGHT: f64 = 20.0;
+    /// Advance of a monospace glyph at FONT_SIZE (0.6em)
+    const CHAR_WIDTH: f64 = 8.4;
+    const PADDING: f64 = 12.0;
+    const TAB: &'static str = "    ";
+
+    fn new(source: &'a str, theme: &Theme) -> Self {
+        Self {
+            source,
+            captured_names: theme.captured_names(),
+            colors: theme.colors(),
+            lines: vec![Vec::new()],
+            highlight_stack: Vec::new(),
+            current_source_start: 0,
+        }
+    }
+    
+    fn current_color(&self) -> Option<String> {
+        self.highlight_stack
+            .iter()
+            .rev()
+            .filter_map(|&idx| self.captured_names.get(idx))
+            .find_map(|name| self.colors.color_map.get(name).cloned())
+    }
+    
+    fn push_text(&mut self, text: &str, color: Option<String>) {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.lines.push(Vec::new());
+            }
+            let part = part.trim_end_matches('\r').replace('\t', Self::TAB);
+            if !part.is_empty() {
+                self.lines.last_mut().unwrap().push((part, color.clone()));
+            }
+        }
+    }
+    
+    fn push_source(&mut self, start: usize, end: usize) {
+        if start > self.current_source_start {
+            let text = &self.source[self.current_source_start..start];
+            self.push_text(text, None);
+        }
+        self.current_source_start = end;
+        
+        if start < end {
+            let color = self.current_color();
+            self.push_text(&self.source[start..end], color);
+        }
+    }
+    
+    fn push_highlight_start(&mut self, highlight_idx: usize) {
+        self.highlight_stack.push(highlight_idx);
+    }
+    
+    fn push_highlight_end(&mut self) {
+        self.highlight_stack.pop();
+    }
+    
+    fn finish(mut self) -> String {
+        if self.current_source_start < self.source.len() {
+            let text = &self.source[self.current_source_start..];
+            self.push_text(text, None);
+        }
+        // Drop the empty line after a trailing newline
+        if self.lines.len() > 1 && self.lines.last().is_some_and(|l| l.is_empty()) {
+            self.lines.pop();
+        }
+        
+        let columns = self
+            .lines
+            .iter()
+            .map(|runs| runs.iter().map(|(t, _)| t.chars().count()).sum::<usize>())
+            .max()
+            .unwrap_or(0);
+        let width = (Self::PADDING * 2.0 + columns as f64 * Self::CHAR_WIDTH).ceil();
+        let height = (Self::PADDING * 2.0 + self.lines.len() as f64 * Self::LINE_HEIGHT).ceil();
+        
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+             <rect width=\"100%\" height=\"100%\" fill=\"{bg}\"/>\n\
+             <g font-family=\"Cascadia Code, Consolas, Menlo, DejaVu Sans Mono, monospace\" font-size=\"{fs}\" fill=\"{fg}\">\n",
+            w = width,
+            h = height,
+            bg = html_escape(&self.colors.background),
+            fs = Self::FONT_SIZE,
+            fg = html_escape(&self.colors.foreground),
+        );
+        
+        for (i, runs) in self.lines.iter().enumerate() {
+            if runs.is_empty() {
+                continue;
+            }
+            // Baseline sits ~70% down the line box
+            let y = Self::PADDING + i as f64 * Self::LINE_HEIGHT + Self::LINE_HEIGHT * 0.7;
+            svg.push_str(&format!("<text x=\"{}\" y=\"{}\" xml:space=\"preserve\">", Self::PADDING, y));
+            for (text, color) in runs {
+                match color {
+                    Some(c) => svg.push_str(&format!("<tspan fill=\"{}\">{}</tspan>", html_escape(c), html_escape(text))),
+                    None => svg.push_str(&html_escape(text)),
+                }
+            }
+            svg.push_str("</text>\n");
+        }
+        
+        svg.push_str("</g>\n</svg>");
+        svg
+    }
+}
+
 /// Escape HTML special characters.
 fn html_escape(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
@@ -553,6 +705,28 @@ mod tests {
         assert!(languages.len() > 0);
     }
     
+    #[test]
+    fn test_svg_renderer_inlines_theme_colors() {
+        let theme = Theme::DarkModern;
+        let names = theme.captured_names();
+        let keyword_idx = names.iter().position(|n| n == "keyword").expect("theme should color keywords");
+        let keyword_color = theme.colors().color_map["keyword"].clone();
+        
+        let source = "fn main() {\n\tx < 1\n}\n";
+        let mut renderer = SvgRenderer::new(source, &theme);
+        renderer.push_highlight_start(keyword_idx);
+        renderer.push_source(0, 2);
+        renderer.push_highlight_end();
+        renderer.push_source(2, source.len());
+        let svg = renderer.finish();
+        
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(&format!("<tspan fill=\"{}\">fn</tspan>", keyword_color)));
+        assert!(svg.contains(&format!("fill=\"{}\"", theme.colors().background)));
+        assert!(svg.contains("    x &lt; 1"), "tabs should be expanded and text escaped");
+        assert_eq!(svg.matches("<text ").count(), 3, "one text element per non-empty line");
+    }
+    
     #[test]
     fn test_html_escape() {
         assert_eq!(html_escape("<script>"), "&lt;script&gt;");
@@ -153,10 +153,8 @@ pub struct ThemeColors {
     /// Map from capture name to hex color
     pub color_map: HashMap<String, String>,
     /// Background color
-    #[allow(dead_code)]
     pub background: String,
     /// Foreground (default text) color
-    #[allow(dead_code)]
     pub foreground: String,
 }
 
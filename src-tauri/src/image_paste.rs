@@ -0,0 +1,364 @@
+//! Image paste pipeline
+//!
+//! Every image that enters a document (clipboard pastes, drag-and-drop and
+//! `save_image` data URLs) goes through the same steps before it lands in the
+//! document's image folder:
+//!
+//! 1. downscale to the configured max width (and halve HiDPI clipboard images
+//!    on macOS when asked to),
+//! 2. encode as PNG, JPEG or WebP, or keep the source format,
+//! 3. reuse an identical file already in the image folder (same SHA-256),
+//! 4. otherwise name it from a template such as `{note}-{date}-{n}`.
+//!
+//! Files that are already in the requested format and small enough are copied
+//! byte for byte, so JPEGs aren't recompressed on every drop. GIFs (which may
+//! be animated), SVGs and anything else the image crate can't decode are
+//! always copied unchanged. The WebP encoder is lossless; quality applies to
+//! JPEG only.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, RgbImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    /// Keep the source format; clipboard images become PNG
+    Original,
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormat {
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Original | Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ImageOptions {
+    /// Folder next to the document the image is saved to
+    pub image_directory: String,
+    pub format: ImageFormat,
+    /// JPEG quality, 1-100
+    pub quality: u8,
+    /// Wider images are scaled down to this width; `None` or 0 keeps the size
+    pub max_width: Option<u32>,
+    /// File name without extension. Placeholders: `{note}` (document name),
+    /// `{name}` (source file name, `paste` for the clipboard), `{date}`,
+    /// `{time}`, `{timestamp}` (milliseconds), `{hash}` (8 hex digits) and
+    /// `{n}` (the lowest number that gives an unused name)
+    pub name_template: String,
+    /// Reuse an identical image already in the image folder
+    pub deduplicate: bool,
+    /// Halve clipboard images on macOS, where screenshots are taken at 2x
+    pub macos_image_scaling: bool,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            image_directory: "img".to_string(),
+            format: ImageFormat::Original,
+            quality: 85,
+            max_width: None,
+            name_template: "{note}-{date}-{n}".to_string(),
+            deduplicate: true,
+            macos_image_scaling: false,
+        }
+    }
+}
+
+/// Where an image was saved, with the link to it relative to the document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedImage {
+    pub path: String,
+    /// `{image_directory}/{filename}`, for the link
+    pub relative_path: String,
+    pub filename: String,
+    /// An identical image already existed and nothing was written
+    pub reused: bool,
+}
+
+pub enum ImageSource {
+    /// An encoded image file; `name` is its file name (with extension)
+    File { name: String, bytes: Vec<u8> },
+    /// Raw RGBA pixels from the clipboard
+    Pixels(RgbaImage),
+}
+
+/// The time placeholders of a name template, taken once per save.
+pub struct Stamp {
+    pub date: String,
+    pub time: String,
+    pub timestamp: i64,
+}
+
+impl Stamp {
+    pub fn now() -> Self {
+        let now = chrono::Local::now();
+        Self {
+            date: now.format("%Y-%m-%d").to_string(),
+            time: now.format("%H%M%S").to_string(),
+            timestamp: now.timestamp_millis(),
+        }
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A file in `dir` with exactly these bytes. Only same-size files are read.
+fn find_identical(dir: &Path, bytes: &[u8], hash: &str) -> Option<PathBuf> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.metadata().is_ok_and(|m| m.is_file() && m.len() == bytes.len() as u64))
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+    entries.into_iter().find(|path| fs::read(path).is_ok_and(|existing| hash_bytes(&existing) == hash))
+}
+
+fn scale(image: DynamicImage, options: &ImageOptions, halve: bool) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    let mut target = if halve { (width / 2).max(1) } else { width };
+    if let Some(max) = options.max_width.filter(|&max| max > 0) {
+        target = target.min(max);
+    }
+    if target == width {
+        return image;
+    }
+    let target_height = ((height as u64 * target as u64) / width as u64).max(1) as u32;
+    image.resize_exact(target, target_height, FilterType::Lanczos3)
+}
+
+/// JPEG has no alpha channel, so transparency is composited onto white.
+fn flatten(image: &DynamicImage) -> RgbImage {
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u16 * a as u16 + 255 * (255 - a as u16)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat, quality: u8) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    match format {
+        ImageFormat::Original | ImageFormat::Png => {
+            let encoder = PngEncoder::new_with_quality(&mut out, CompressionType::Best, PngFilter::Adaptive);
+            DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(encoder)
+        }
+        ImageFormat::Jpeg => {
+            let encoder = JpegEncoder::new_with_quality(&mut out, quality.clamp(1, 100));
+            DynamicImage::ImageRgb8(flatten(image)).write_with_encoder(encoder)
+        }
+        ImageFormat::Webp => DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(WebPEncoder::new_lossless(&mut out)),
+    }
+    .map_err(|e| format!("Cannot encode image: {}", e))?;
+    Ok(out)
+}
+
+/// The bytes to write and their extension.
+fn process(source: ImageSource, options: &ImageOptions) -> Result<(Vec<u8>, String), String> {
+    match source {
+        ImageSource::Pixels(pixels) => {
+            let halve = cfg!(target_os = "macos") && options.macos_image_scaling;
+            let image = scale(DynamicImage::ImageRgba8(pixels), options, halve);
+            let format = if options.format == ImageFormat::Original { ImageFormat::Png } else { options.format };
+            Ok((encode(&image, format, options.quality)?, format.extension().to_string()))
+        }
+        ImageSource::File { name, bytes } => {
+            let extension = Path::new(&name).extension().and_then(|e| e.to_str()).unwrap_or("").to_string();
+            let Some(source_format) = ImageFormat::from_extension(&extension) else {
+                return Ok((bytes, extension.to_ascii_lowercase()));
+            };
+            let format = if options.format == ImageFormat::Original { source_format } else { options.format };
+            let image = image::load_from_memory(&bytes).map_err(|e| format!("Cannot decode {}: {}", name, e))?;
+            let width = image.width();
+            let image = scale(image, options, false);
+            if format == source_format && image.width() == width {
+                return Ok((bytes, extension.to_ascii_lowercase()));
+            }
+            Ok((encode(&image, format, options.quality)?, format.extension().to_string()))
+        }
+    }
+}
+
+/// Replace characters that aren't allowed in file names on some platform.
+fn sanitize(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '-' } else { c })
+        .collect();
+    let name = name.trim().trim_matches('.');
+    if name.is_empty() { "image".to_string() } else { name.to_string() }
+}
+
+/// Fill in a name template and pick the first file name not taken in `dir`.
+fn unique_name(dir: &Path, template: &str, values: &[(&str, &str)], extension: &str) -> String {
+    let mut base = template.to_string();
+    for (key, value) in values {
+        base = base.replace(&format!("{{{}}}", key), value);
+    }
+    let filename = |stem: &str| if extension.is_empty() { sanitize(stem) } else { format!("{}.{}", sanitize(stem), extension) };
+    let counted = base.contains("{n}");
+    for n in 1.. {
+        let stem = match (counted, n) {
+            (true, _) => base.replace("{n}", &n.to_string()),
+            (false, 1) => base.clone(),
+            (false, _) => format!("{}-{}", base, n),
+        };
+        let name = filename(&stem);
+        if !dir.join(&name).exists() {
+            return name;
+        }
+    }
+    unreachable!()
+}
+
+/// Run an image through the pipeline into the image folder of `document`.
+pub fn save(document: &Path, source: ImageSource, options: &ImageOptions, stamp: &Stamp) -> Result<SavedImage, String> {
+    let parent = document.parent().ok_or_else(|| format!("{} has no parent folder", document.display()))?;
+    let dir = parent.join(&options.image_directory);
+    let name = match &source {
+        ImageSource::File { name, .. } => Path::new(name).file_stem().and_then(|s| s.to_str()).unwrap_or("image").to_string(),
+        ImageSource::Pixels(_) => "paste".to_string(),
+    };
+    let (bytes, extension) = process(source, options)?;
+    let hash = hash_bytes(&bytes);
+    let saved = |filename: String, reused: bool| SavedImage {
+        path: dir.join(&filename).to_string_lossy().to_string(),
+        relative_path: format!("{}/{}", options.image_directory, filename),
+        filename,
+        reused,
+    };
+
+    if options.deduplicate {
+        if let Some(existing) = find_identical(&dir, &bytes, &hash) {
+            let filename = existing.file_name().unwrap_or_default().to_string_lossy().to_string();
+            return Ok(saved(filename, true));
+        }
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let note = document.file_stem().and_then(|s| s.to_str()).unwrap_or("note");
+    let timestamp = stamp.timestamp.to_string();
+    let values = [
+        ("note", note),
+        ("name", name.as_str()),
+        ("date", stamp.date.as_str()),
+        ("time", stamp.time.as_str()),
+        ("timestamp", timestamp.as_str()),
+        ("hash", &hash[..8]),
+    ];
+    let filename = unique_name(&dir, &options.name_template, &values, &extension);
+    let path = dir.join(&filename);
+    fs::write(&path, &bytes).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(saved(filename, false))
+}
+
+/// Decode the base64 payload of a data URL or plain base64 string.
+pub fn decode_base64(data: &str) -> Result<Vec<u8>, String> {
+    use base64::{engine::general_purpose, Engine as _};
+    let b64 = data.find("base64,").map_or(data, |pos| &data[pos + 7..]);
+    general_purpose::STANDARD.decode(b64.trim()).map_err(|e| e.to_string())
+}
+
+/// Error of `clipboard_pixels` when the clipboard holds no image, so a paste
+/// can fall back to text.
+pub const NO_CLIPBOARD_IMAGE: &str = "No image on the clipboard";
+
+/// Read an image from the clipboard as RGBA pixels.
+pub fn clipboard_pixels() -> Result<RgbaImage, String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    let image = clipboard.get_image().map_err(|e| match e {
+        arboard::Error::ContentNotAvailable => NO_CLIPBOARD_IMAGE.to_string(),
+        e => e.to_string(),
+    })?;
+    RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.into_owned())
+        .ok_or_else(|| "Clipboard image has an unexpected size".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn stamp() -> Stamp {
+        Stamp { date: "2024-05-06".to_string(), time: "070809".to_string(), timestamp: 1714979289000 }
+    }
+
+    fn pixels(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| image::Rgba([(x * 7) as u8, (y * 13) as u8, 90, if x < 2 { 0 } else { 255 }]))
+    }
+
+    #[test]
+    fn test_clipboard_image_is_resized_encoded_and_named() {
+        let root = TempDir::new("paste-clipboard");
+        let document = root.join("My Note.md");
+        let options = ImageOptions { format: ImageFormat::Jpeg, max_width: Some(20), ..Default::default() };
+        let saved = save(&document, ImageSource::Pixels(pixels(40, 10)), &options, &stamp()).unwrap();
+        assert_eq!(saved.relative_path, "img/My Note-2024-05-06-1.jpg");
+        assert!(!saved.reused);
+        let image = image::open(&saved.path).unwrap();
+        assert_eq!(image.dimensions(), (20, 5));
+
+        // Same pixels again: the existing file is reused
+        let again = save(&document, ImageSource::Pixels(pixels(40, 10)), &options, &stamp()).unwrap();
+        assert_eq!((again.filename.as_str(), again.reused), ("My Note-2024-05-06-1.jpg", true));
+
+        // Different pixels take the next number
+        let other = save(&document, ImageSource::Pixels(pixels(40, 12)), &options, &stamp()).unwrap();
+        assert_eq!(other.filename, "My Note-2024-05-06-2.jpg");
+    }
+
+    #[test]
+    fn test_files_keep_their_bytes_unless_converted() {
+        let root = TempDir::new("paste-files");
+        let document = root.join("note.md");
+        let png = encode(&DynamicImage::ImageRgba8(pixels(8, 8)), ImageFormat::Png, 85).unwrap();
+        let file = |name: &str, bytes: &[u8]| ImageSource::File { name: name.to_string(), bytes: bytes.to_vec() };
+        let options = ImageOptions { name_template: "{name}".to_string(), ..Default::default() };
+
+        let kept = save(&document, file("shot.png", &png), &options, &stamp()).unwrap();
+        assert_eq!(fs::read(&kept.path).unwrap(), png);
+        let svg = save(&document, file("a:b.svg", b"<svg/>"), &options, &stamp()).unwrap();
+        assert_eq!(svg.filename, "a-b.svg");
+        let named = save(&document, file("a:b.svg", b"<svg></svg>"), &options, &stamp()).unwrap();
+        assert_eq!(named.filename, "a-b-2.svg");
+
+        let webp = ImageOptions { format: ImageFormat::Webp, name_template: "{hash}".to_string(), ..Default::default() };
+        let converted = save(&document, file("shot.png", &png), &webp, &stamp()).unwrap();
+        assert!(converted.filename.ends_with(".webp") && converted.filename.len() == 13);
+        assert_eq!(image::open(&converted.path).unwrap().dimensions(), (8, 8));
+
+        let no_dedup = ImageOptions { deduplicate: false, ..options };
+        let copy = save(&document, file("shot.png", &png), &no_dedup, &stamp()).unwrap();
+        assert_eq!((copy.filename.as_str(), copy.reused), ("shot-2.png", false));
+    }
+}
@@ -0,0 +1,281 @@
+//! Kroki client module
+//!
+//! Renders diagrams through a Kroki server (https://kroki.io or a self-hosted
+//! container). Output goes through the shared render cache, keyed by diagram
+//! type and source, so unchanged diagrams re-render instantly and work offline.
+
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::render_cache::RenderCache;
+
+pub const DEFAULT_BASE_URL: &str = "https://kroki.io";
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+/// Cache version for Kroki output; bump to invalidate cached server renders.
+const CACHE_VERSION: &str = "1";
+
+/// Result of a Kroki render.
+#[derive(Debug, Clone, Serialize)]
+pub struct KrokiResponse {
+    pub svg: String,
+    /// Whether the SVG came from the on-disk cache
+    pub cached: bool,
+}
+
+/// Kroki failure, serialised as `{ "kind": "...", "message": "...", ... }`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KrokiError {
+    /// The diagram type is not a valid Kroki path segment
+    InvalidType { message: String },
+    /// Offline mode was requested and the diagram is not cached
+    Offline { message: String },
+    /// The server did not answer within the timeout
+    Timeout { message: String },
+    /// Connection or transport failure
+    Network { message: String },
+    /// The server answered with an error (usually a diagram syntax error)
+    Http { status: u16, message: String },
+}
+
+impl std::fmt::Display for KrokiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KrokiError::Http { status, message } => write!(f, "Kroki error {}: {}", status, message),
+            KrokiError::InvalidType { message }
+            | KrokiError::Offline { message }
+            | KrokiError::Timeout { message }
+            | KrokiError::Network { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for KrokiError {}
+
+impl From<reqwest::Error> for KrokiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            KrokiError::Timeout { message: format!("Kroki request timed out: {}", e) }
+        } else {
+            KrokiError::Network { message: format!("Kroki request failed: {}", e) }
+        }
+    }
+}
+
+/// Map frontend aliases to Kroki diagram types and reject anything that isn't a plain path segment.
+pub fn normalize_type(diagram_type: &str) -> Result<String, KrokiError> {
+    let diagram_type = diagram_type.trim().to_ascii_lowercase();
+    let diagram_type = match diagram_type.as_str() {
+        "dot" => "graphviz".to_string(),
+        _ => diagram_type,
+    };
+    if diagram_type.is_empty() || !diagram_type.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(KrokiError::InvalidType {
+            message: format!("Invalid Kroki diagram type: {:?}", diagram_type),
+        });
+    }
+    Ok(diagram_type)
+}
+
+/// Fetch SVG from the Kroki server with `POST <base>/<type>/svg`.
+async fn fetch_svg(
+    base_url: &str,
+    diagram_type: &str,
+    source: &str,
+    timeout: Duration,
+) -> Result<String, KrokiError> {
+    let url = format!("{}/{}/svg", base_url.trim_end_matches('/'), diagram_type);
+    let client = reqwest::Client::builder().timeout(timeout).build()?;
+    let response = client
+        .post(&url)
+        .header(reqwest::header::CONTENT_TYPE, "text/plain")
+        .body(source.to_string())
+        .send()
+        .await?;
+
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        return Err(KrokiError::Http {
+            status: status.as_u16(),
+            message: body.trim().to_string(),
+        });
+    }
+    Ok(body)
+}
+
+/// Cache key for a diagram; the server is part of it since servers (and versions) render differently.
+fn cache_key(base_url: &str, diagram_type: &str, source: &str) -> String {
+    let renderer = format!("kroki/{}@{}", diagram_type, base_url.trim_end_matches('/'));
+    RenderCache::key(&renderer, CACHE_VERSION, source)
+}
+
+/// Render a diagram, serving it from `cache` when possible.
+pub async fn render(
+    cache: Option<&RenderCache>,
+    base_url: &str,
+    diagram_type: &str,
+    source: &str,
+    timeout: Duration,
+    offline: bool,
+) -> Result<KrokiResponse, KrokiError> {
+    let diagram_type = normalize_type(diagram_type)?;
+    let key = cache_key(base_url, &diagram_type, source);
+
+    if let Some(svg) = cache.and_then(|c| c.get(&key)) {
+        return Ok(KrokiResponse { svg, cached: true });
+    }
+    if offline {
+        return Err(KrokiError::Offline {
+            message: format!("{} diagram is not cached and offline mode is on", diagram_type),
+        });
+    }
+
+    let svg = fetch_svg(base_url, &diagram_type, source, timeout).await?;
+    if let Some(cache) = cache {
+        cache.put(&key, &svg);
+    }
+    Ok(KrokiResponse { svg, cached: false })
+}
+
+/// Render a diagram via Kroki.
+///
+/// `base_url` defaults to https://kroki.io; `timeout_ms` defaults to 10s.
+/// With `offline` set, only cached diagrams are returned.
+#[tauri::command]
+pub async fn render_kroki(
+    diagram_type: String,
+    source: String,
+    base_url: Option<String>,
+    timeout_ms: Option<u64>,
+    offline: Option<bool>,
+) -> Result<KrokiResponse, KrokiError> {
+    let base_url = base_url.filter(|u| !u.trim().is_empty());
+    render(
+        crate::render_cache::global(),
+        base_url.as_deref().unwrap_or(DEFAULT_BASE_URL),
+        &diagram_type,
+        &source,
+        Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS)),
+        offline.unwrap_or(false),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn test_cache(name: &str) -> (RenderCache, TempDir) {
+        let dir = TempDir::new(&format!("kroki-{}", name));
+        (RenderCache::open(dir.to_path_buf(), 1024 * 1024), dir)
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tauri::async_runtime::block_on(future)
+    }
+
+    #[test]
+    fn test_normalize_type() {
+        assert_eq!(normalize_type("dot").unwrap(), "graphviz");
+        assert_eq!(normalize_type("PlantUML").unwrap(), "plantuml");
+        assert!(matches!(normalize_type("../etc"), Err(KrokiError::InvalidType { .. })));
+        assert!(normalize_type("").is_err());
+    }
+
+    #[test]
+    fn test_offline_serves_cache() {
+        let (cache, _dir) = test_cache("offline");
+        cache.put(&cache_key(DEFAULT_BASE_URL, "graphviz", "digraph { a }"), "<svg/>");
+
+        let hit = block_on(render(Some(&cache), DEFAULT_BASE_URL, "dot", "digraph { a }", Duration::from_secs(1), true)).unwrap();
+        assert_eq!(hit.svg, "<svg/>");
+        assert!(hit.cached);
+
+        let miss = block_on(render(Some(&cache), DEFAULT_BASE_URL, "dot", "digraph { b }", Duration::from_secs(1), true));
+        assert!(matches!(miss, Err(KrokiError::Offline { .. })));
+
+        // Another server's renders aren't served
+        let other = block_on(render(Some(&cache), "http://localhost:8000", "dot", "digraph { a }", Duration::from_secs(1), true));
+        assert!(matches!(other, Err(KrokiError::Offline { .. })));
+    }
+
+    /// Minimal local stand-in for a Kroki server: answers each connection with
+    /// `status` and `body`, returning the request paths it saw.
+    fn serve(status: &'static str, body: &'static str, requests: usize) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut paths = Vec::new();
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                paths.push(request.split_whitespace().nth(1).unwrap_or("").to_string());
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            paths
+        });
+        (base_url, handle)
+    }
+
+    #[test]
+    fn test_fetch_and_cache() {
+        let (cache, _dir) = test_cache("fetch");
+        let (base_url, server) = serve("200 OK", "<svg>ok</svg>", 1);
+
+        let first = block_on(render(Some(&cache), &format!("{}/", base_url), "dot", "digraph { a }", Duration::from_secs(5), false)).unwrap();
+        assert_eq!(first.svg, "<svg>ok</svg>");
+        assert!(!first.cached);
+        assert_eq!(server.join().unwrap(), vec!["/graphviz/svg".to_string()]);
+
+        // The server is gone now; the second render must come from disk
+        let second = block_on(render(Some(&cache), &base_url, "graphviz", "digraph { a }", Duration::from_secs(5), false)).unwrap();
+        assert!(second.cached);
+        assert_eq!(second.svg, first.svg);
+    }
+
+    #[test]
+    fn test_http_error_is_not_cached() {
+        let (cache, _dir) = test_cache("http-error");
+        let (base_url, server) = serve("400 Bad Request", "Syntax error in line 1", 1);
+
+        let result = block_on(render(Some(&cache), &base_url, "plantuml", "@startuml\n?", Duration::from_secs(5), false));
+        assert_eq!(
+            result.unwrap_err(),
+            KrokiError::Http { status: 400, message: "Syntax error in line 1".to_string() }
+        );
+        server.join().unwrap();
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_unreachable_server_is_network_error() {
+        // Port 9 (discard) on localhost is closed on any sane test machine
+        let result = block_on(render(None, "http://127.0.0.1:9", "graphviz", "digraph { a }", Duration::from_secs(2), false));
+        assert!(
+            matches!(result, Err(KrokiError::Network { .. }) | Err(KrokiError::Timeout { .. })),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_error_serialization() {
+        let err = KrokiError::Http { status: 400, message: "Syntax error".to_string() };
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["kind"], "http");
+        assert_eq!(json["status"], 400);
+        assert_eq!(json["message"], "Syntax error");
+    }
+}
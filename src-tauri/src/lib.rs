@@ -1,4 +1,4 @@
-use comrak::{markdown_to_html, ComrakOptions};
+use comrak::{format_html, parse_document, Arena, ComrakOptions};
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use regex::{Captures, Regex};
 use std::borrow::Cow;
@@ -6,16 +6,35 @@ use std::fs;
 use std::path::Path;
 use std::sync::Mutex;
 use tauri::{AppHandle, Emitter, Manager, State};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::sync::OnceLock;
 
-// layout-rs for GraphViz DOT rendering
-use layout::backends::svg::SVGWriter;
-use layout::gv::{DotParser, GraphBuilder};
-
 mod highlight;
 mod setup;
 mod pdf;
+mod pikchr;
+mod graphviz;
+mod raster;
+mod kroki;
+mod render_cache;
+mod math;
+mod crossref;
+mod citations;
+mod attributes;
+mod assets;
+mod critic;
+mod file_tree;
+mod formatter;
+mod image_paste;
+mod lint;
+mod links;
+mod spell;
+mod stats;
+mod tags;
+mod tasks;
+mod workspace;
+#[cfg(test)]
+mod test_util;
 
 use highlight::{TreeSitterHighlighter, Theme};
 
@@ -26,6 +45,16 @@ use highlight::debug_queries_dir;
 struct MarkdownResponse {
     html: String,
     metadata: String,
+    /// Problems found by render passes, e.g. unresolved cross-references
+    warnings: Vec<RenderWarning>,
+}
+
+/// A non-fatal problem found while rendering, with its 1-based source line.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct RenderWarning {
+    kind: &'static str,
+    message: String,
+    line: usize,
 }
 
 struct WatcherState {
@@ -325,19 +354,43 @@ fn process_latex_delimiters(content: &str) -> String {
     result
 }
 
+/// Optional server-side passes applied when rendering markdown.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct RenderOptions {
+    /// Convert math to MathML instead of leaving it for KaTeX in the webview
+    mathml: bool,
+    /// Directory that relative `bibliography:` paths resolve against
+    base_dir: Option<String>,
+}
+
 #[tauri::command]
 fn convert_markdown(content: &str) -> String {
-    let after_embeds = process_internal_embeds(content);
-    let after_wikilinks = process_wikilinks(&after_embeds);
-    let processed = process_latex_delimiters(&after_wikilinks);
+    render_body(content, &serde_yaml::Value::Null, &RenderOptions::default()).0
+}
 
-    // Debug: log if LaTeX delimiters are found
-    if content.contains(r#"\["#) || content.contains(r#"\("#) {
-        eprintln!("[latex] Input contains \\[ or \\(");
-        eprintln!("[latex] After processing: {}", if processed.contains("$$") { "contains $$" } else { "NO $$" });
+/// Split off frontmatter and render the body.
+///
+/// Warning lines are reported against the whole file, frontmatter included.
+fn render_document(content: &str, render_options: &RenderOptions) -> MarkdownResponse {
+    let (body, metadata) = split_frontmatter(content);
+    let frontmatter = serde_yaml::from_str(&metadata).unwrap_or(serde_yaml::Value::Null);
+    let (html, mut warnings) = render_body(body, &frontmatter, render_options);
+
+    let line_offset = content[..content.len() - body.len()].matches('\n').count();
+    for warning in &mut warnings {
+        warning.line += line_offset;
     }
 
-    let mut options = ComrakOptions::default();
+    MarkdownResponse {
+        html,
+        metadata,
+        warnings,
+    }
+}
+
+/// Comrak options shared by the renderer and the formatter.
+fn configure_markdown_options(options: &mut ComrakOptions) {
     options.extension.strikethrough = true;
     options.extension.table = true;
     options.extension.autolink = true;
@@ -351,18 +404,63 @@ fn convert_markdown(content: &str) -> String {
     options.render.unsafe_ = true;
     options.render.hardbreaks = true;
     options.render.sourcepos = true;
+    options.render.full_info_string = true;
+}
 
-    markdown_to_html(&processed, &options)
+fn render_body(
+    content: &str,
+    frontmatter: &serde_yaml::Value,
+    render_options: &RenderOptions,
+) -> (String, Vec<RenderWarning>) {
+    // CriticMarkup first, before ==highlight== and ~~strikethrough~~ claim its delimiters
+    let after_critic = critic::render(content);
+    let after_embeds = process_internal_embeds(&after_critic);
+    let after_wikilinks = process_wikilinks(&after_embeds);
+    let processed = process_latex_delimiters(&after_wikilinks);
+
+    // Debug: log if LaTeX delimiters are found
+    if content.contains(r#"\["#) || content.contains(r#"\("#) {
+        eprintln!("[latex] Input contains \\[ or \\(");
+        eprintln!("[latex] After processing: {}", if processed.contains("$$") { "contains $$" } else { "NO $$" });
+    }
+
+    let mut options = ComrakOptions::default();
+    configure_markdown_options(&mut options);
+
+    let (processed, line_map) = attributes::preprocess_fenced_divs(&processed);
+
+    let arena = Arena::new();
+    let root = parse_document(&arena, &processed, &options);
+    attributes::remap_sourcepos(root, &line_map);
+    let mut warnings = Vec::new();
+
+    crossref::merge_adjacent_text(root);
+    attributes::process_spans(&arena, root);
+
+    crossref::process(&arena, root, &crossref::CrossrefConfig::from_frontmatter(frontmatter), &mut warnings);
+    if let Some(config) =
+        citations::CitationConfig::from_frontmatter(frontmatter, render_options.base_dir.as_deref().map(Path::new))
+    {
+        citations::process(&arena, root, &config, &mut warnings);
+    }
+    tags::process(&arena, root);
+    if render_options.mathml {
+        math::render_mathml(root);
+    }
+
+    let mut html = Vec::new();
+    format_html(root, &options, &mut html).expect("writing HTML to a Vec cannot fail");
+    (String::from_utf8(html).expect("comrak emits valid UTF-8"), warnings)
 }
 
 #[tauri::command]
-fn open_markdown(path: String) -> Result<MarkdownResponse, String> {
-    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
-    let (body, metadata) = split_frontmatter(&content);
-    Ok(MarkdownResponse {
-        html: convert_markdown(body),
-        metadata,
-    })
+fn open_markdown(path: String, options: Option<RenderOptions>) -> Result<MarkdownResponse, String> {
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let mut options = options.unwrap_or_default();
+    if options.base_dir.is_none() {
+        options.base_dir = Path::new(&path).parent().map(|p| p.to_string_lossy().to_string());
+    }
+    Ok(render_document(&content, &options))
 }
 
 #[tauri::command]
@@ -393,12 +491,8 @@ async fn open_markdown_preview(path: String, max_bytes: usize) -> Result<(String
 }
 
 #[tauri::command]
-fn render_markdown(content: String) -> MarkdownResponse {
-    let (body, metadata) = split_frontmatter(&content);
-    MarkdownResponse {
-        html: convert_markdown(body),
-        metadata,
-    }
+fn render_markdown(content: String, options: Option<RenderOptions>) -> MarkdownResponse {
+    render_document(&content, &options.unwrap_or_default())
 }
 
 #[tauri::command]
@@ -452,6 +546,26 @@ fn highlight_code(code: String, language: String, theme: String) -> Result<Strin
         .map_err(|e| e.to_string())
 }
 
+/// Highlight code using tree-sitter and return a standalone SVG.
+/// 
+/// Theme colours are inlined, so the result can be rasterised with
+/// `rasterize_svg` or pasted into apps that don't load our stylesheet.
+#[tauri::command]
+fn highlight_code_svg(code: String, language: String, theme: String) -> Result<String, String> {
+    let parsed_theme: Theme = theme.parse()
+        .unwrap_or(Theme::DarkModern);
+    
+    let highlighter = get_highlighter();
+    let mut highlighter = highlighter.lock().map_err(|e| e.to_string())?;
+    
+    if *highlighter.theme() != parsed_theme {
+        highlighter.set_theme(parsed_theme);
+    }
+    
+    highlighter.highlight_svg(&code, &language)
+        .map_err(|e| e.to_string())
+}
+
 /// Check if a language is supported by tree-sitter.
 #[tauri::command]
 fn is_language_supported(language: String) -> bool {
@@ -476,23 +590,23 @@ fn get_supported_languages() -> Vec<String> {
 
 /// Render GraphViz DOT diagram using pure Rust (layout-rs).
 /// 
-/// Returns SVG string on success, error message on failure.
+/// `info` is the rest of the fence info string (e.g. `debug no-opt theme=dark`);
+/// `theme` is the current highlight theme and is overridden by `theme=` in `info`.
+/// Returns SVG string on success, a parse error with line/column on failure.
 #[tauri::command]
-fn render_graphviz_rust(code: String) -> Result<String, String> {
-	// Parse DOT code into AST
-	let mut parser = DotParser::new(&code);
-	let graph = parser.process().map_err(|e| format!("DOT parse error: {}", e))?;
-	
-	// Build VisualGraph from AST
-	let mut builder = GraphBuilder::new();
-	builder.visit_graph(&graph);
-	let mut visual_graph = builder.get();
-	
-	// Render to SVG
-	let mut svg_writer = SVGWriter::new();
-	visual_graph.do_it(false, false, false, &mut svg_writer);
-	
-	Ok(svg_writer.finalize())
+fn render_graphviz_rust(
+	code: String,
+	info: Option<String>,
+	theme: Option<String>,
+) -> Result<String, graphviz::DotParseError> {
+	let mut options = graphviz::GraphvizOptions::from_info_string(info.as_deref().unwrap_or(""));
+	if options.theme.is_none() {
+		options.theme = theme;
+	}
+	let cache_source = format!("{:?}\n{}", options, code);
+	render_cache::get_or_render("graphviz", render_cache::BUILTIN_RENDERER_VERSION, &cache_source, || {
+		graphviz::render(&code, &options)
+	})
 }
 
 /// Render Svgbob ASCII diagram using pure Rust (svgbob).
@@ -500,9 +614,29 @@ fn render_graphviz_rust(code: String) -> Result<String, String> {
 /// Returns SVG string on success, error message on failure.
 #[tauri::command]
 fn render_svgbob_rust(code: String) -> Result<String, String> {
-	let svg = svgbob::to_svg(&code);
-	Ok(svg)
+	render_cache::get_or_render("svgbob", render_cache::BUILTIN_RENDERER_VERSION, &code, || {
+		Ok(svgbob::to_svg(&code))
+	})
 }
+
+/// Render Pikchr diagram using the bundled Pikchr C library.
+/// 
+/// Returns SVG string on success, error message on failure.
+#[tauri::command]
+fn render_pikchr_rust(code: String, dark_mode: Option<bool>) -> Result<String, String> {
+	let dark_mode = dark_mode.unwrap_or(false);
+	let renderer = if dark_mode { "pikchr-dark" } else { "pikchr" };
+	render_cache::get_or_render(renderer, render_cache::BUILTIN_RENDERER_VERSION, &code, || {
+		pikchr::render(&code, dark_mode)
+	})
+}
+
+/// Whether Pikchr can be rendered offline; release builds without pikchr.c fall back to Kroki.
+#[tauri::command]
+fn pikchr_available() -> bool {
+	pikchr::AVAILABLE
+}
+
 #[tauri::command]
 fn watch_file(
     handle: AppHandle,
@@ -661,145 +795,58 @@ fn clipboard_read_text() -> Result<String, String> {
     clipboard.get_text().map_err(|e| e.to_string())
 }
 
+/// Save the clipboard image to the document's image folder through the paste pipeline.
 #[tauri::command]
-fn clipboard_read_image(_macos_image_scaling: bool) -> Result<String, String> {
-    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
-    let image = clipboard.get_image().map_err(|e| e.to_string())?;
-
-    // encode as png
-    let mut png_data = Vec::new();
-    {
-        let encoder = image::codecs::png::PngEncoder::new(&mut png_data);
-        use image::ImageEncoder;
-
-        // Check if running on macOS and scale image if needed
-        #[cfg(target_os = "macos")]
-        {
-            if macos_image_scaling {
-                // Use image crate for high-quality scaling
-                use image::{DynamicImage, ImageBuffer, Rgba};
-
-                // Convert arboard Image to ImageBuffer
-                let mut img_buffer = ImageBuffer::new(image.width as u32, image.height as u32);
-                for (x, y, pixel) in img_buffer.enumerate_pixels_mut() {
-                    let idx = (y * image.width as u32 + x) as usize * 4;
-                    if idx + 3 < image.bytes.len() {
-                        *pixel = Rgba([
-                            image.bytes[idx],
-                            image.bytes[idx + 1],
-                            image.bytes[idx + 2],
-                            image.bytes[idx + 3]
-                        ]);
-                    }
-                }
-
-                // Create DynamicImage
-                let dynamic_image = DynamicImage::ImageRgba8(img_buffer);
-
-                // Resize with high-quality Lanczos3 filter
-                let resized = dynamic_image.resize(
-                    (image.width / 2) as u32,
-                    (image.height / 2) as u32,
-                    image::imageops::FilterType::Lanczos3
-                );
-
-                // Write the resized image
-                let resized_rgba = resized.to_rgba8();
-                encoder
-                    .write_image(
-                        resized_rgba.as_raw(),
-                        (image.width / 2) as u32,
-                        (image.height / 2) as u32,
-                        image::ExtendedColorType::Rgba8,
-                    )
-                    .map_err(|e| e.to_string())?;
-            } else {
-                // Use original image if scaling is disabled
-                encoder
-                    .write_image(
-                        image.bytes.as_ref(),
-                        image.width as u32,
-                        image.height as u32,
-                        image::ExtendedColorType::Rgba8,
-                    )
-                    .map_err(|e| e.to_string())?;
-            }
-        }
-
-        #[cfg(not(target_os = "macos"))]
-        {
-            // For other platforms, use the original image
-            encoder
-                .write_image(
-                    image.bytes.as_ref(),
-                    image.width as u32,
-                    image.height as u32,
-                    image::ExtendedColorType::Rgba8,
-                )
-                .map_err(|e| e.to_string())?;
-        }
-    }
-
-    use base64::{engine::general_purpose, Engine as _};
-    Ok(general_purpose::STANDARD.encode(&png_data))
+async fn paste_clipboard_image(document: String, options: Option<image_paste::ImageOptions>) -> Result<image_paste::SavedImage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let pixels = image_paste::clipboard_pixels()?;
+        let options = options.unwrap_or_default();
+        image_paste::save(Path::new(&document), image_paste::ImageSource::Pixels(pixels), &options, &image_paste::Stamp::now())
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()))
 }
 
+/// Save a base64 image (or data URL) to the document's image folder; `filename` fills `{name}`.
 #[tauri::command]
-fn save_image(parent_dir: String, filename: String, base64_data: String, image_directory: String) -> Result<String, String> {
-    let img_dir = Path::new(&parent_dir).join(&image_directory);
-    if !img_dir.exists() {
-        fs::create_dir_all(&img_dir).map_err(|e| e.to_string())?;
-    }
-
-    let file_path = img_dir.join(&filename);
-
-    // remove potential data:image/png;base64, prefix
-    let b64 = if let Some(pos) = base64_data.find("base64,") {
-        &base64_data[pos + 7..]
-    } else {
-        &base64_data
-    };
-
-    use base64::{engine::general_purpose, Engine as _};
-    let bytes = general_purpose::STANDARD
-        .decode(b64)
-        .map_err(|e: base64::DecodeError| e.to_string())?;
-
-    fs::write(&file_path, bytes).map_err(|e| e.to_string())?;
-
-    Ok(format!("{}/{}", image_directory, filename))
+async fn save_image(
+    document: String,
+    filename: String,
+    base64_data: String,
+    options: Option<image_paste::ImageOptions>,
+) -> Result<image_paste::SavedImage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let bytes = image_paste::decode_base64(&base64_data)?;
+        let source = image_paste::ImageSource::File { name: filename, bytes };
+        image_paste::save(Path::new(&document), source, &options.unwrap_or_default(), &image_paste::Stamp::now())
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()))
 }
 
+/// Copy a dropped image file to the document's image folder through the paste pipeline.
 #[tauri::command]
-fn copy_file_to_img(src_path: String, parent_dir: String, image_directory: String) -> Result<String, String> {
-    let img_dir = Path::new(&parent_dir).join(&image_directory);
-    if !img_dir.exists() {
-        fs::create_dir_all(&img_dir).map_err(|e| e.to_string())?;
-    }
-
-    let src = Path::new(&src_path);
-    if !src.exists() {
-        return Err("Source file does not exist".to_string());
-    }
-
-    let file_name = src
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or_else(|| "Invalid source filename".to_string())?;
-
-    // Handle name conflicts by appending timestamp if exists
-    let mut dest_name = file_name.to_string();
-    let dest_path = img_dir.join(&dest_name);
-    if dest_path.exists() {
-        let stem = src.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
-        let ext = src.extension().and_then(|e| e.to_str()).unwrap_or("");
-        dest_name = format!("{}_{}.{}", stem, chrono::Local::now().timestamp(), ext);
-    }
-
-    let final_dest = img_dir.join(&dest_name);
-    fs::copy(src, &final_dest).map_err(|e| e.to_string())?;
-
-    Ok(format!("{}/{}", image_directory, dest_name))
+async fn copy_file_to_img(
+    src_path: String,
+    document: String,
+    options: Option<image_paste::ImageOptions>,
+) -> Result<image_paste::SavedImage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let src = Path::new(&src_path);
+        if !src.exists() {
+            return Err("Source file does not exist".to_string());
+        }
+        let name = src
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| "Invalid source filename".to_string())?
+            .to_string();
+        let bytes = fs::read(src).map_err(|e| e.to_string())?;
+        let source = image_paste::ImageSource::File { name, bytes };
+        image_paste::save(Path::new(&document), source, &options.unwrap_or_default(), &image_paste::Stamp::now())
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()))
 }
 
 #[tauri::command]
@@ -1016,6 +1063,8 @@ pub fn run() {
         .manage(WatcherState {
             watcher: Mutex::new(None),
         })
+        .manage(workspace::WorkspaceState::default())
+        .manage(file_tree::TreeWatcherState::default())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init());
 
@@ -1118,6 +1167,8 @@ pub fn run() {
 
             let _window = window_builder.build()?;
 
+            render_cache::init(app.path().app_data_dir()?.join("render-cache"));
+
             let config_dir = app.path().app_config_dir()?;
             let theme_path = config_dir.join("theme.txt");
             let theme_pref =
@@ -1186,18 +1237,70 @@ pub fn run() {
             get_os_type,
             // Tree-sitter highlighting
             highlight_code,
+            highlight_code_svg,
             is_language_supported,
             get_supported_languages,
             // Diagram rendering (Rust)
             render_graphviz_rust,
             render_svgbob_rust,
+            render_pikchr_rust,
+            pikchr_available,
+            // Kroki (cached HTTP client)
+            kroki::render_kroki,
+            // Diagram render cache
+            render_cache::get_render_cache_stats,
+            render_cache::clear_render_cache,
+            // CriticMarkup review
+            critic::critic_list_changes,
+            critic::critic_accept_all,
+            critic::critic_reject_all,
+            critic::critic_apply_change,
+            // Markdown formatter
+            formatter::format_markdown,
+            // Markdown lint
+            lint::lint_markdown,
+            // Link checker
+            links::check_links,
+            // Spell check
+            spell::spell_check,
+            spell::spell_list_languages,
+            spell::spell_add_word,
+            // Document statistics
+            stats::document_stats,
+            // Tasks
+            tasks::toggle_task,
+            // Workspace
+            workspace::workspace_open,
+            workspace::workspace_close,
+            workspace::workspace_search,
+            workspace::workspace_backlinks,
+            workspace::workspace_unlinked_mentions,
+            workspace::workspace_graph,
+            workspace::workspace_tags,
+            workspace::workspace_notes_for_tag,
+            workspace::workspace_tasks,
+            workspace::workspace_query,
+            workspace::workspace_quick_open,
+            workspace::workspace_rename_preview,
+            workspace::workspace_rename,
+            workspace::workspace_undo_rename,
+            // File tree
+            file_tree::list_file_tree,
+            file_tree::watch_directory,
+            file_tree::unwatch_directory,
+            // Image assets
+            assets::scan_assets,
+            assets::clean_assets,
+            // SVG rasterisation
+            raster::rasterize_svg,
+            raster::copy_svg_as_image,
             // PDF generation
             pdf::prepare_pdf_pages,
             pdf::merge_pdf_files,
             // Clipboard (upstream)
             clipboard_write_text,
             clipboard_read_text,
-            clipboard_read_image,
+            paste_clipboard_image,
             // File operations (upstream)
             save_image,
             copy_file_to_img,
@@ -1247,13 +1350,27 @@ digraph G {
 	B -> C;
 }
 "#;
-		let result = render_graphviz_rust(dot_code.to_string());
+		let result = render_graphviz_rust(dot_code.to_string(), None, None);
 		assert!(result.is_ok(), "GraphViz Rust rendering failed: {:?}", result.err());
 		let svg = result.unwrap();
 		assert!(svg.contains("<svg"), "Result should contain SVG element");
 		println!("Generated SVG length: {} bytes", svg.len());
 	}
 
+	#[test]
+	fn test_graphviz_rust_parse_error() {
+		let result = render_graphviz_rust("digraph G {\n  a -> ;\n}".to_string(), None, None);
+		let err = result.expect_err("invalid DOT should fail");
+		assert_eq!(err.line, 2, "error should point at the bad line: {:?}", err);
+	}
+
+	#[test]
+	fn test_convert_markdown_keeps_fence_meta() {
+		let html = convert_markdown("```dot debug theme=dark\ndigraph { a -> b }\n```\n");
+		assert!(html.contains("language-dot"));
+		assert!(html.contains("data-meta=\"debug theme=dark\""), "info string options should reach the frontend: {}", html);
+	}
+
 	#[test]
 	fn test_svgbob_rust_render() {
 		let code = r#"
@@ -1273,6 +1390,20 @@ digraph G {
 		println!("Generated SVG length: {} bytes", svg.len());
 	}
 
+	#[test]
+	fn test_pikchr_rust_render() {
+		let code = r#"
+box "A"; arrow; box "B"
+"#;
+		let result = render_pikchr_rust(code.to_string(), None);
+		if cfg!(pikchr) {
+			assert!(result.is_ok(), "Pikchr rendering failed: {:?}", result.err());
+			assert!(result.unwrap().contains("<svg"), "Result should contain SVG element");
+		} else {
+			assert!(result.is_err(), "Pikchr should report that it was not compiled in");
+		}
+	}
+
 	#[test]
 	fn test_process_latex_delimiters() {
 		// Test \[...\] -> $$...$$
@@ -1579,7 +1710,7 @@ digraph G {
 		// MarkdownResponse.html is a String field. The frontend must access `.html`
 		// rather than stringify the whole response object — see MarkdownViewer.svelte
 		// loadMarkdown (the >50KB "[object Object]" regression).
-		let resp = render_markdown("# 标题\n\n正文".to_string());
+		let resp = render_markdown("# 标题\n\n正文".to_string(), None);
 		assert!(resp.html.contains("<h1"), "html field should render the heading");
 		assert!(resp.html.contains("标题"));
 		assert!(resp.html.contains("正文"));
@@ -1600,7 +1731,7 @@ digraph G {
 			"test input should exceed the 50KB preview threshold"
 		);
 
-		let resp = render_markdown(content);
+		let resp = render_markdown(content, None);
 		assert!(resp.html.contains("第 199 节"), "html should contain the last heading");
 		assert!(
 			!resp.html.contains("[object Object]"),
@@ -1609,5 +1740,65 @@ digraph G {
 		let h2_count = resp.html.matches("<h2").count();
 		assert_eq!(h2_count, 200, "all 200 h2 headings should be rendered");
 	}
-}
 
+	#[test]
+	fn test_render_markdown_mathml_option() {
+		let content = "Inline \\(x^2\\) and\n\n\\[\\sqrt{y}\\]\n".to_string();
+
+		let default = render_markdown(content.clone(), None);
+		assert!(!default.html.contains("<math"), "MathML pass should be opt-in");
+
+		let options = RenderOptions { mathml: true, ..Default::default() };
+		let resp = render_markdown(content, Some(options));
+		assert!(resp.html.contains("<msup>"), "{}", resp.html);
+		assert!(resp.html.contains("<msqrt>"), "{}", resp.html);
+	}
+
+	#[test]
+	fn test_render_markdown_crossref_warnings_use_file_lines() {
+		let content = "---\neqnPrefix: Equation\n---\n$$x$$ {#eq:x}\n\nSee @eq:x and @eq:nope.\n".to_string();
+		let resp = render_markdown(content, None);
+		assert!(resp.html.contains(">Equation 1</a>"), "{}", resp.html);
+		assert_eq!(resp.warnings.len(), 1);
+		assert_eq!(resp.warnings[0].kind, "unresolved-reference");
+		assert_eq!(resp.warnings[0].line, 6, "line should count the frontmatter");
+	}
+
+	#[test]
+	fn test_convert_markdown_fenced_divs_keep_source_lines() {
+		let html = convert_markdown("Intro\n\n::: {.callout #tip}\nA [styled]{.smallcaps} tip.\n:::\n\nOutro\n");
+		assert!(html.contains("<div id=\"tip\" class=\"callout\" data-sourcepos=\"3:1-5:3\">"), "{}", html);
+		assert!(html.contains("<span class=\"smallcaps\">styled</span>"), "{}", html);
+		assert!(html.contains("<p data-sourcepos=\"4:1-4:27\">"), "{}", html);
+		assert!(html.contains("<p data-sourcepos=\"7:1-7:5\">Outro</p>"), "{}", html);
+	}
+
+	#[test]
+	fn test_convert_markdown_critic_markup() {
+		let html = convert_markdown("Keep {==this==}{>>why?<<} and {~~old~>new~~} text {--gone--}.\n");
+		assert!(html.contains("<mark class=\"critic critic-highlight\">this</mark>"), "{}", html);
+		assert!(html.contains("title=\"why?\""), "{}", html);
+		assert!(html.contains("<ins class=\"critic critic-substitution\">new</ins>"), "{}", html);
+		assert!(html.contains("<del class=\"critic critic-deletion\">gone</del>"), "{}", html);
+		assert!(!html.contains("{=="), "{}", html);
+	}
+
+	#[test]
+	fn test_open_markdown_resolves_bibliography_next_to_file() {
+		let dir = crate::test_util::TempDir::with_files(
+			"bib",
+			&[
+				("refs.bib", "@book{knuth1984, author = {Knuth, Donald E.}, title = {The TeXbook}, year = {1984}}\n"),
+				("note.md", "---\nbibliography: refs.bib\ncsl: ieee\n---\nSee [@knuth1984] and [@missing].\n"),
+			],
+		);
+		let note = dir.join("note.md");
+
+		let resp = open_markdown(note.to_string_lossy().to_string(), None).unwrap();
+		assert!(resp.html.contains("data-cites=\"knuth1984\""), "{}", resp.html);
+		assert!(resp.html.contains("id=\"ref-knuth1984\""), "{}", resp.html);
+		assert_eq!(resp.warnings.len(), 1);
+		assert_eq!(resp.warnings[0].kind, "missing-citation");
+		assert_eq!(resp.warnings[0].line, 5);
+	}
+}
@@ -0,0 +1,713 @@
+//! Broken link and missing asset checker
+//!
+//! Walks a document, or every markdown file under a folder, and reports links
+//! that point at nothing: relative file links, images (falling back to the
+//! `image_directory` pasted images are saved to), heading anchors in the
+//! target file, `[[wikilinks]]` and `![[embeds]]`. External links are only
+//! requested when asked for, directly or through a link-check endpoint.
+//!
+//! Positions are 1-based lines and UTF-16 columns, like lint diagnostics.
+
+use crate::workspace::{is_markdown, markdown_files};
+use comrak::nodes::{AstNode, NodeHtmlBlock, NodeValue};
+use comrak::{parse_document, Anchorizer, Arena, ComrakOptions};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT_MS: u64 = 5_000;
+/// External requests in flight at once
+const EXTERNAL_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LinkCheckOptions {
+    /// Folder pasted images are saved to, relative to the document (see `save_image`)
+    pub image_directory: String,
+    /// Request http(s) links too
+    pub check_external: bool,
+    /// Link-check service queried as `{endpoint}?url=<link>` instead of the link itself
+    pub external_endpoint: Option<String>,
+    pub timeout_ms: u64,
+}
+
+impl Default for LinkCheckOptions {
+    fn default() -> Self {
+        Self {
+            image_directory: "img".to_string(),
+            check_external: false,
+            external_endpoint: None,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// A linked file doesn't exist
+    MissingFile,
+    /// An image or embedded file doesn't exist
+    MissingImage,
+    /// The file exists but has no heading or element with that id
+    MissingAnchor,
+    /// No note matches a `[[wikilink]]`
+    MissingNote,
+    /// An external link failed or returned an error status
+    External,
+    /// The file itself couldn't be read
+    Unreadable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkIssue {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    /// The link as written
+    pub target: String,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkReport {
+    pub files_checked: usize,
+    pub links_checked: usize,
+    pub issues: Vec<LinkIssue>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    Link,
+    Image,
+    WikiLink,
+    Embed,
+}
+
+/// A link as written in a document, at a 1-based line and UTF-16 column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FoundLink {
+    pub kind: LinkKind,
+    pub target: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+pub(crate) fn has_scheme(target: &str) -> bool {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^(?:[a-zA-Z][a-zA-Z0-9+.-]*:|//)").unwrap()).is_match(target)
+}
+
+fn wikilink_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?sm)```.*?```|^[ \t]*~~~.*?^[ \t]*~~~|`[^`\n]*`|(!?)\[\[([^\]\n]+)\]\]").unwrap())
+}
+
+fn img_src_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"<img\b[^>]*?\bsrc\s*=\s*["']([^"']+)["']"#).unwrap())
+}
+
+pub(crate) fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(byte) = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// UTF-16 column of a 1-based byte column in `line`.
+fn utf16_column(line: &str, byte_column: usize) -> usize {
+    let end = byte_column.saturating_sub(1).min(line.len());
+    let end = (0..=end).rev().find(|i| line.is_char_boundary(*i)).unwrap_or(0);
+    line[..end].encode_utf16().count() + 1
+}
+
+/// Links, images (`<img src>` included), wikilinks and embeds in a document,
+/// with file positions.
+pub(crate) fn extract_links(content: &str) -> Vec<FoundLink> {
+    let (body, _metadata) = crate::split_frontmatter(content);
+    let body_start = content.len() - body.len();
+    let line_offset = content[..body_start].matches('\n').count();
+    let lines: Vec<&str> = content.lines().collect();
+    let column = |line: usize, byte_column: usize| utf16_column(lines.get(line - 1).copied().unwrap_or(""), byte_column);
+
+    let mut links = Vec::new();
+    let mut options = ComrakOptions::default();
+    crate::configure_markdown_options(&mut options);
+    let arena = Arena::new();
+    let root = parse_document(&arena, body, &options);
+    for node in root.descendants() {
+        // Inline nodes may lack positions; fall back to the enclosing block
+        let start = node
+            .ancestors()
+            .map(|n| n.data.borrow().sourcepos.start)
+            .find(|pos| pos.line > 0)
+            .unwrap_or_default();
+        let (kind, url) = match &node.data.borrow().value {
+            NodeValue::Link(link) => (LinkKind::Link, link.url.clone()),
+            NodeValue::Image(link) => (LinkKind::Image, link.url.clone()),
+            NodeValue::HtmlBlock(NodeHtmlBlock { literal, .. }) | NodeValue::HtmlInline(literal) => {
+                for caps in img_src_re().captures_iter(literal) {
+                    let src = caps.get(1).unwrap();
+                    let before = &literal[..src.start()];
+                    let line = start.line + line_offset + before.matches('\n').count();
+                    // An HTML block starts at its line; inline HTML after the node's column
+                    let byte_column = match before.rfind('\n') {
+                        Some(newline) => src.start() - newline,
+                        None => start.column + src.start(),
+                    };
+                    links.push(FoundLink { kind: LinkKind::Image, target: src.as_str().to_string(), line, column: column(line, byte_column) });
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let line = start.line + line_offset;
+        links.push(FoundLink { kind, target: url, line, column: column(line, start.column) });
+    }
+
+    for caps in wikilink_re().captures_iter(body) {
+        let Some(inner) = caps.get(2) else { continue };
+        let whole = caps.get(0).unwrap();
+        let offset = body_start + whole.start();
+        let line = content[..offset].matches('\n').count() + 1;
+        let line_start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let kind = if caps[1].is_empty() { LinkKind::WikiLink } else { LinkKind::Embed };
+        // Drop the alias (links) or size (embeds)
+        let target = inner.as_str().split('|').next().unwrap_or("").trim().to_string();
+        links.push(FoundLink { kind, target, line, column: column(line, offset - line_start + 1) });
+    }
+
+    links.sort_by_key(|l| (l.line, l.column));
+    links
+}
+
+/// Ids a link fragment can point at: heading slugs (as the renderer assigns
+/// them), `^block-ids`, `{#attribute}` ids and raw HTML `id`s.
+fn collect_anchors(content: &str) -> HashSet<String> {
+    static BLOCK_ID: OnceLock<Regex> = OnceLock::new();
+    static ATTR_ID: OnceLock<Regex> = OnceLock::new();
+    static HTML_ID: OnceLock<Regex> = OnceLock::new();
+
+    let (body, _metadata) = crate::split_frontmatter(content);
+    let mut anchors = HashSet::new();
+
+    let mut options = ComrakOptions::default();
+    crate::configure_markdown_options(&mut options);
+    let arena = Arena::new();
+    let root = parse_document(&arena, body, &options);
+    let mut anchorizer = Anchorizer::new();
+    for node in root.descendants() {
+        if matches!(node.data.borrow().value, NodeValue::Heading(_)) {
+            anchors.insert(anchorizer.anchorize(heading_text(node)));
+        }
+    }
+
+    let block_id = BLOCK_ID.get_or_init(|| Regex::new(r"(?m)\s\^([A-Za-z0-9_-]+)$").unwrap());
+    for caps in block_id.captures_iter(body) {
+        anchors.insert(caps[1].to_string());
+        anchors.insert(format!("^{}", &caps[1]));
+    }
+    let attr_id = ATTR_ID.get_or_init(|| Regex::new(r"\{[^}\n]*#([\w:.-]+)[^}\n]*\}").unwrap());
+    let html_id = HTML_ID.get_or_init(|| Regex::new(r#"\bid\s*=\s*["']([^"']+)["']"#).unwrap());
+    for re in [attr_id, html_id] {
+        for caps in re.captures_iter(body) {
+            anchors.insert(caps[1].to_string());
+        }
+    }
+    anchors
+}
+
+/// Heading text as comrak collects it for `header_ids`.
+pub(crate) fn heading_text<'a>(node: &'a AstNode<'a>) -> String {
+    fn collect<'a>(node: &'a AstNode<'a>, out: &mut String) {
+        match &node.data.borrow().value {
+            NodeValue::Text(t) | NodeValue::Code(comrak::nodes::NodeCode { literal: t, .. }) => out.push_str(t),
+            NodeValue::Math(math) => out.push_str(&math.literal),
+            NodeValue::LineBreak | NodeValue::SoftBreak => out.push(' '),
+            _ => {
+                for child in node.children() {
+                    collect(child, out);
+                }
+            }
+        }
+    }
+    let mut text = String::new();
+    collect(node, &mut text);
+    text
+}
+
+/// Resolve `.` and `..` without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+struct Checker<'o> {
+    options: &'o LinkCheckOptions,
+    /// Folder wikilinks are resolved in
+    root: PathBuf,
+    /// Lower-cased file names and markdown stems under `root`
+    names: Option<HashMap<String, Vec<PathBuf>>>,
+    anchors: HashMap<PathBuf, HashSet<String>>,
+    /// External links by URL, with where they were found
+    external: HashMap<String, Vec<LinkIssue>>,
+    report: LinkReport,
+}
+
+impl Checker<'_> {
+    fn names(&mut self) -> &HashMap<String, Vec<PathBuf>> {
+        let root = self.root.clone();
+        self.names.get_or_insert_with(|| {
+            let mut names: HashMap<String, Vec<PathBuf>> = HashMap::new();
+            let mut stack = vec![root];
+            while let Some(dir) = stack.pop() {
+                let Ok(entries) = fs::read_dir(&dir) else { continue };
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let name = entry.file_name().to_string_lossy().to_lowercase();
+                    if path.is_dir() {
+                        if !name.starts_with('.') && name != "node_modules" {
+                            stack.push(path);
+                        }
+                        continue;
+                    }
+                    if is_markdown(&path) {
+                        if let Some(stem) = path.file_stem() {
+                            names.entry(stem.to_string_lossy().to_lowercase()).or_default().push(path.clone());
+                        }
+                    }
+                    names.entry(name).or_default().push(path);
+                }
+            }
+            names
+        })
+    }
+
+    fn anchors(&mut self, path: &Path) -> &HashSet<String> {
+        self.anchors
+            .entry(path.to_path_buf())
+            .or_insert_with(|| fs::read_to_string(path).map(|c| collect_anchors(&c)).unwrap_or_default())
+    }
+
+    fn has_anchor(&mut self, path: &Path, fragment: &str) -> bool {
+        let fragment = percent_decode(fragment);
+        let slug = Anchorizer::new().anchorize(fragment.clone());
+        // `[[#Some Heading]]` is rendered as a lower-cased, dashed link
+        let dashed = fragment.to_lowercase().replace(' ', "-");
+        let anchors = self.anchors(path);
+        anchors.contains(&fragment) || anchors.contains(&slug) || anchors.contains(&dashed)
+    }
+
+    /// Find a note or file by name the way wikilinks do: relative to the
+    /// document first, then anywhere under the root.
+    fn resolve_by_name(&mut self, doc_dir: &Path, name: &str, extra_dirs: &[PathBuf]) -> Option<PathBuf> {
+        let name = name.trim();
+        let mut candidates = vec![doc_dir.join(name)];
+        candidates.extend(extra_dirs.iter().map(|d| d.join(name)));
+        if Path::new(name).extension().is_none() {
+            candidates.push(doc_dir.join(format!("{}.md", name)));
+        }
+        if let Some(found) = candidates.into_iter().find(|p| p.is_file()) {
+            return Some(found);
+        }
+        let key = name.rsplit('/').next().unwrap_or(name).to_lowercase();
+        self.names().get(&key).and_then(|paths| paths.first().cloned())
+    }
+
+    fn issue(file: &Path, link: &FoundLink, kind: IssueKind, message: String) -> LinkIssue {
+        LinkIssue {
+            file: file.to_string_lossy().to_string(),
+            line: link.line,
+            column: link.column,
+            target: link.target.clone(),
+            kind,
+            message,
+        }
+    }
+
+    fn check_link(&mut self, file: &Path, link: &FoundLink) -> Option<LinkIssue> {
+        let doc_dir = file.parent().unwrap_or(Path::new(""));
+        let target = link.target.trim();
+        if target.is_empty() {
+            return None;
+        }
+
+        match link.kind {
+            LinkKind::Link | LinkKind::Image => {
+                if target.starts_with("http://") || target.starts_with("https://") {
+                    if self.options.check_external {
+                        let issue = Self::issue(file, link, IssueKind::External, String::new());
+                        self.external.entry(target.to_string()).or_default().push(issue);
+                    }
+                    return None;
+                }
+                if has_scheme(target) {
+                    return None;
+                }
+
+                let (path_part, fragment) = match target.split_once('#') {
+                    Some((path, fragment)) => (path, Some(fragment)),
+                    None => (target, None),
+                };
+                let path_part = path_part.split('?').next().unwrap_or("");
+                let resolved = if path_part.is_empty() {
+                    file.to_path_buf()
+                } else {
+                    let decoded = percent_decode(path_part);
+                    let base = if decoded.starts_with('/') { self.root.clone() } else { doc_dir.to_path_buf() };
+                    let path = normalize(&base.join(decoded.trim_start_matches('/')));
+                    let image_dir = doc_dir.join(&self.options.image_directory);
+                    let fallbacks = [
+                        Some(path.clone()),
+                        (link.kind == LinkKind::Image && !decoded.contains('/')).then(|| image_dir.join(&decoded)),
+                        (link.kind == LinkKind::Link && path.extension().is_none()).then(|| path.with_extension("md")),
+                    ];
+                    match fallbacks.into_iter().flatten().find(|p| p.exists()) {
+                        Some(found) => found,
+                        None => {
+                            let kind = if link.kind == LinkKind::Image { IssueKind::MissingImage } else { IssueKind::MissingFile };
+                            let message = format!("{} not found", path.display());
+                            return Some(Self::issue(file, link, kind, message));
+                        }
+                    }
+                };
+
+                match fragment {
+                    Some(fragment) if !fragment.is_empty() && is_markdown(&resolved) => {
+                        (!self.has_anchor(&resolved, fragment)).then(|| {
+                            let message = format!("No heading or anchor #{} in {}", fragment, resolved.display());
+                            Self::issue(file, link, IssueKind::MissingAnchor, message)
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            LinkKind::WikiLink | LinkKind::Embed => {
+                let (name, fragment) = match target.split_once('#') {
+                    Some((name, fragment)) => (name, Some(fragment)),
+                    None => (target, None),
+                };
+                let resolved = if name.is_empty() {
+                    file.to_path_buf()
+                } else {
+                    let image_dir = [doc_dir.join(&self.options.image_directory)];
+                    let extra: &[PathBuf] = if link.kind == LinkKind::Embed { &image_dir } else { &[] };
+                    match self.resolve_by_name(doc_dir, name, extra) {
+                        Some(found) => found,
+                        None => {
+                            let (kind, what) = if link.kind == LinkKind::Embed {
+                                (IssueKind::MissingImage, "embedded file")
+                            } else {
+                                (IssueKind::MissingNote, "note")
+                            };
+                            return Some(Self::issue(file, link, kind, format!("No {} named \"{}\"", what, name)));
+                        }
+                    }
+                };
+                match fragment {
+                    Some(fragment) if !fragment.is_empty() && is_markdown(&resolved) => {
+                        (!self.has_anchor(&resolved, fragment)).then(|| {
+                            let message = format!("No heading or block #{} in {}", fragment, resolved.display());
+                            Self::issue(file, link, IssueKind::MissingAnchor, message)
+                        })
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Check one file; a file that can't be read is reported as an issue rather than ending the scan.
+    fn check_file(&mut self, file: &Path) {
+        self.report.files_checked += 1;
+        let content = match fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(e) => {
+                self.report.issues.push(LinkIssue {
+                    file: file.to_string_lossy().to_string(),
+                    line: 1,
+                    column: 1,
+                    target: String::new(),
+                    kind: IssueKind::Unreadable,
+                    message: format!("Cannot read {}: {}", file.display(), e),
+                });
+                return;
+            }
+        };
+        for link in extract_links(&content) {
+            self.report.links_checked += 1;
+            if let Some(issue) = self.check_link(file, &link) {
+                self.report.issues.push(issue);
+            }
+        }
+    }
+}
+
+/// Request one external URL; `Err` describes why it counts as broken.
+async fn fetch_status(client: &reqwest::Client, url: &str, endpoint: Option<&str>) -> Result<(), String> {
+    let status = match endpoint {
+        Some(endpoint) => {
+            let separator = if endpoint.contains('?') { '&' } else { '?' };
+            let check_url = format!("{}{}url={}", endpoint, separator, percent_encode(url));
+            client.get(&check_url).send().await.map_err(|e| e.to_string())?.status()
+        }
+        None => {
+            let head = client.head(url).send().await.map_err(|e| e.to_string())?.status();
+            // Some servers don't implement HEAD
+            if matches!(head.as_u16(), 403 | 405 | 501) {
+                client.get(url).send().await.map_err(|e| e.to_string())?.status()
+            } else {
+                head
+            }
+        }
+    };
+    if status.is_success() || status.is_redirection() {
+        Ok(())
+    } else {
+        Err(format!("HTTP {}", status.as_u16()))
+    }
+}
+
+async fn check_external(external: HashMap<String, Vec<LinkIssue>>, options: &LinkCheckOptions) -> Vec<LinkIssue> {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_millis(options.timeout_ms))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            log::warn!("[links] Cannot build HTTP client: {}", e);
+            return Vec::new();
+        }
+    };
+    let endpoint = options.external_endpoint.clone().filter(|e| !e.trim().is_empty());
+
+    let mut urls: Vec<(String, Vec<LinkIssue>)> = external.into_iter().collect();
+    urls.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut issues = Vec::new();
+    for batch in urls.chunks(EXTERNAL_CONCURRENCY) {
+        let handles: Vec<_> = batch
+            .iter()
+            .map(|(url, _)| {
+                let (client, url, endpoint) = (client.clone(), url.clone(), endpoint.clone());
+                tauri::async_runtime::spawn(async move { fetch_status(&client, &url, endpoint.as_deref()).await })
+            })
+            .collect();
+        for ((_, found), handle) in batch.iter().zip(handles) {
+            let result = handle.await.unwrap_or_else(|e| Err(e.to_string()));
+            if let Err(message) = result {
+                issues.extend(found.iter().cloned().map(|mut issue| {
+                    issue.message = message.clone();
+                    issue
+                }));
+            }
+        }
+    }
+    issues
+}
+
+/// Check one markdown file, or every markdown file under a folder.
+pub async fn check(path: &Path, options: &LinkCheckOptions) -> Result<LinkReport, String> {
+    let (root, files) = if path.is_dir() {
+        (path.to_path_buf(), markdown_files(path))
+    } else if path.is_file() {
+        (path.parent().unwrap_or(Path::new("")).to_path_buf(), vec![path.to_path_buf()])
+    } else {
+        return Err(format!("{} does not exist", path.display()));
+    };
+
+    let mut checker = Checker {
+        options,
+        root,
+        names: None,
+        anchors: HashMap::new(),
+        external: HashMap::new(),
+        report: LinkReport::default(),
+    };
+    for file in &files {
+        checker.check_file(file);
+    }
+
+    let mut report = checker.report;
+    if !checker.external.is_empty() {
+        report.issues.extend(check_external(checker.external, options).await);
+    }
+    report.issues.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+    Ok(report)
+}
+
+/// Report broken links in a markdown file or folder.
+#[tauri::command]
+pub async fn check_links(path: String, options: Option<LinkCheckOptions>) -> Result<LinkReport, String> {
+    check(Path::new(&path), &options.unwrap_or_default()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tauri::async_runtime::block_on(future)
+    }
+
+    fn workspace(name: &str, files: &[(&str, &str)]) -> TempDir {
+        TempDir::with_files(&format!("links-{}", name), files)
+    }
+
+    fn kinds(report: &LinkReport) -> Vec<(IssueKind, String)> {
+        report.issues.iter().map(|i| (i.kind, i.target.clone())).collect()
+    }
+
+    #[test]
+    fn test_extract_links_positions() {
+        let links = extract_links(
+            "---\nt: 1\n---\nSee [a](b.md) and [[Note|alias]]\n\n`[[code]]`\n\n![[pic.png|100]]\n\n<p>\n  <img src=\"html.png\">\n</p>\n\n~~~\n[[tilde fenced]]\n~~~\n",
+        );
+        let found: Vec<(LinkKind, &str, usize)> = links.iter().map(|l| (l.kind, l.target.as_str(), l.line)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (LinkKind::Link, "b.md", 4),
+                (LinkKind::WikiLink, "Note", 4),
+                (LinkKind::Embed, "pic.png", 8),
+                (LinkKind::Image, "html.png", 11),
+            ]
+        );
+        assert_eq!(links[1].column, 19);
+        assert_eq!(links[3].column, 13);
+    }
+
+    #[test]
+    fn test_relative_links_images_and_anchors() {
+        let root = workspace(
+            "relative",
+            &[
+                ("docs/index.md", "# Index\n\n![](img/diagram.png) ![](missing.png) ![](pasted.png)\n\n[ok](../other.md#setup) [bad](../other.md#nope) [gone](nothing.md)\n\n[self](#index) [spaces](my%20file.md)\n"),
+                ("docs/img/pasted.png", ""),
+                ("docs/my file.md", ""),
+                ("other.md", "# Intro\n\n## Setup\n"),
+            ],
+        );
+        let report = block_on(check(&root.join("docs/index.md"), &LinkCheckOptions::default())).unwrap();
+        assert_eq!(report.files_checked, 1);
+        assert_eq!(
+            kinds(&report),
+            vec![
+                (IssueKind::MissingImage, "img/diagram.png".to_string()),
+                (IssueKind::MissingImage, "missing.png".to_string()),
+                (IssueKind::MissingAnchor, "../other.md#nope".to_string()),
+                (IssueKind::MissingFile, "nothing.md".to_string()),
+            ]
+        );
+        assert_eq!(report.issues[0].line, 3);
+    }
+
+    #[test]
+    fn test_wikilinks_across_folder() {
+        let root = workspace(
+            "wiki",
+            &[
+                ("a.md", "[[Deep Note]] [[Deep Note#Part Two]] [[Deep Note#Nope]] [[Missing Note]] [[#Local]] ![[shot.png]] [[Deep Note#^blk]]\n\n# Local\n"),
+                ("sub/Deep Note.md", "## Part Two\n\nText ^blk\n"),
+                ("sub/b.md", "[[a]]\n"),
+                ("img/shot.png", ""),
+            ],
+        );
+        // A note that isn't UTF-8 is reported without ending the scan
+        root.write("bad.md", [0xff, 0xfe, b'\n']);
+        let report = block_on(check(&root, &LinkCheckOptions::default())).unwrap();
+        assert_eq!(report.files_checked, 4);
+        assert_eq!(
+            kinds(&report),
+            vec![
+                (IssueKind::MissingAnchor, "Deep Note#Nope".to_string()),
+                (IssueKind::MissingNote, "Missing Note".to_string()),
+                (IssueKind::Unreadable, String::new()),
+            ]
+        );
+    }
+
+    /// Local stand-in HTTP server answering `/ok` with 200 and anything else with 404.
+    fn serve(requests: usize) -> String {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("").to_string();
+                let status = if path == "/ok" || path.contains("url=http%3A%2F%2Fexample.com") { "200 OK" } else { "404 Not Found" };
+                let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        base
+    }
+
+    #[test]
+    fn test_external_links_are_optional() {
+        let base = serve(2);
+        let doc = format!("[ok]({base}/ok) [bad]({base}/missing) [again]({base}/missing)\n");
+        let root = workspace("external", &[("doc.md", &doc)]);
+
+        let offline = block_on(check(&root.join("doc.md"), &LinkCheckOptions::default())).unwrap();
+        assert!(offline.issues.is_empty());
+
+        let options = LinkCheckOptions { check_external: true, ..Default::default() };
+        let report = block_on(check(&root.join("doc.md"), &options)).unwrap();
+        assert_eq!(report.issues.len(), 2, "{:?}", report.issues);
+        assert!(report.issues.iter().all(|i| i.kind == IssueKind::External && i.message == "HTTP 404"));
+
+        let endpoint = serve(1);
+        let root2 = workspace("endpoint", &[("doc.md", "[x](http://example.com)\n")]);
+        let options = LinkCheckOptions {
+            check_external: true,
+            external_endpoint: Some(format!("{}/check", endpoint)),
+            ..Default::default()
+        };
+        let report = block_on(check(&root2.join("doc.md"), &options)).unwrap();
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+    }
+}
@@ -0,0 +1,545 @@
+//! Markdown linting
+//!
+//! markdownlint-style rules run over the comrak AST and the raw text:
+//!
+//! | id    | name                     |
+//! |-------|--------------------------|
+//! | MD001 | heading-increment        |
+//! | MD004 | ul-style                 |
+//! | MD009 | no-trailing-spaces       |
+//! | MD024 | no-duplicate-heading     |
+//! | MD034 | no-bare-urls             |
+//! | MD040 | fenced-code-language     |
+//! | MD045 | no-alt-text              |
+//!
+//! Rules are configured with the nearest `.markdownlint.json` (or `.yaml`)
+//! found walking up from the document's folder, in markdownlint's format:
+//! `{ "default": true, "MD009": false, "ul-style": { "style": "dash", "severity": "error" } }`.
+//!
+//! Ranges are 1-based with an exclusive end column, counted in UTF-16 code
+//! units, so they can be handed to Monaco markers unchanged.
+
+use comrak::nodes::{AstNode, ListType, NodeValue};
+use comrak::{parse_document, Arena, ComrakOptions};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+pub const CONFIG_FILES: &[&str] = &[".markdownlint.json", ".markdownlint.yaml", ".markdownlint.yml"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "error" => Some(Severity::Error),
+            "warning" => Some(Severity::Warning),
+            "info" => Some(Severity::Info),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LintRange {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+/// Replace `range` with `text`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintFix {
+    pub range: LintRange,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LintDiagnostic {
+    /// markdownlint id, e.g. `MD001`
+    pub rule: &'static str,
+    /// markdownlint alias, e.g. `heading-increment`
+    pub name: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub range: LintRange,
+    pub fix: Option<LintFix>,
+}
+
+/// Rule ids and aliases, in id order.
+const RULES: &[(&str, &str)] = &[
+    ("MD001", "heading-increment"),
+    ("MD004", "ul-style"),
+    ("MD009", "no-trailing-spaces"),
+    ("MD024", "no-duplicate-heading"),
+    ("MD034", "no-bare-urls"),
+    ("MD040", "fenced-code-language"),
+    ("MD045", "no-alt-text"),
+];
+
+/// Parsed markdownlint configuration.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintConfig {
+    /// Whether rules not mentioned in `rules` are enabled
+    pub default: Option<bool>,
+    /// Settings by rule id or alias: `false`, `true` or an options object
+    pub rules: HashMap<String, serde_json::Value>,
+}
+
+impl LintConfig {
+    pub fn from_value(value: serde_json::Value) -> Self {
+        let mut config = LintConfig::default();
+        if let serde_json::Value::Object(map) = value {
+            for (key, value) in map {
+                if key == "default" {
+                    config.default = value.as_bool();
+                } else {
+                    config.rules.insert(key, value);
+                }
+            }
+        }
+        config
+    }
+
+    /// Load the nearest config file at or above `dir`.
+    pub fn find(dir: &Path) -> Result<Self, String> {
+        for dir in dir.ancestors() {
+            for name in CONFIG_FILES {
+                let path = dir.join(name);
+                if !path.is_file() {
+                    continue;
+                }
+                let text = fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+                let value: serde_json::Value = if name.ends_with(".json") {
+                    serde_json::from_str(&text).map_err(|e| format!("Invalid {}: {}", path.display(), e))?
+                } else {
+                    serde_yaml::from_str(&text).map_err(|e| format!("Invalid {}: {}", path.display(), e))?
+                };
+                return Ok(Self::from_value(value));
+            }
+        }
+        Ok(Self::default())
+    }
+
+    fn setting(&self, id: &str, name: &str) -> Option<&serde_json::Value> {
+        self.rules.get(id).or_else(|| self.rules.get(name))
+    }
+
+    fn enabled(&self, id: &str, name: &str) -> bool {
+        match self.setting(id, name) {
+            Some(serde_json::Value::Bool(enabled)) => *enabled,
+            Some(serde_json::Value::Object(_)) => true,
+            _ => self.default.unwrap_or(true),
+        }
+    }
+
+    fn option<'c>(&'c self, id: &str, name: &str, key: &str) -> Option<&'c serde_json::Value> {
+        self.setting(id, name)?.get(key)
+    }
+
+    fn severity(&self, id: &str, name: &str) -> Severity {
+        self.option(id, name, "severity")
+            .and_then(|s| s.as_str())
+            .and_then(Severity::parse)
+            .unwrap_or(Severity::Warning)
+    }
+}
+
+/// Raw lines of the document, for column conversion and text rules.
+struct Source<'s> {
+    lines: Vec<&'s str>,
+    /// Lines taken by frontmatter, added to body line numbers
+    line_offset: usize,
+}
+
+impl Source<'_> {
+    fn line(&self, body_line: usize) -> &str {
+        self.lines.get(body_line + self.line_offset - 1).copied().unwrap_or("")
+    }
+
+    /// UTF-16 column of a 1-based byte column on a body line.
+    fn column(&self, body_line: usize, byte_column: usize) -> usize {
+        let line = self.line(body_line);
+        let end = byte_column.saturating_sub(1).min(line.len());
+        let end = (0..=end).rev().find(|i| line.is_char_boundary(*i)).unwrap_or(0);
+        line[..end].encode_utf16().count() + 1
+    }
+
+    /// Range from byte columns on body lines; `end_column` is exclusive.
+    fn range(&self, start_line: usize, start_column: usize, end_line: usize, end_column: usize) -> LintRange {
+        LintRange {
+            start_line: start_line + self.line_offset,
+            start_column: self.column(start_line, start_column),
+            end_line: end_line + self.line_offset,
+            end_column: self.column(end_line, end_column),
+        }
+    }
+
+    fn whole_line(&self, body_line: usize) -> LintRange {
+        self.range(body_line, 1, body_line, self.line(body_line).len() + 1)
+    }
+}
+
+struct Linter<'c, 's> {
+    config: &'c LintConfig,
+    source: Source<'s>,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl Linter<'_, '_> {
+    fn enabled(&self, id: &str) -> bool {
+        let name = RULES.iter().find(|(rule, _)| *rule == id).map_or("", |(_, name)| *name);
+        self.config.enabled(id, name)
+    }
+
+    fn report(&mut self, id: &'static str, message: String, range: LintRange, fix: Option<LintFix>) {
+        let name = RULES.iter().find(|(rule, _)| *rule == id).map_or("", |(_, name)| *name);
+        self.diagnostics.push(LintDiagnostic {
+            rule: id,
+            name,
+            severity: self.config.severity(id, name),
+            message,
+            range,
+            fix,
+        });
+    }
+}
+
+fn plain_text<'a>(node: &'a AstNode<'a>) -> String {
+    node.descendants()
+        .filter_map(|n| match &n.data.borrow().value {
+            NodeValue::Text(t) | NodeValue::Code(comrak::nodes::NodeCode { literal: t, .. }) => Some(t.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Start line and byte column of a node, falling back to its nearest
+/// positioned ancestor for inline nodes without positions.
+fn position<'a>(node: &'a AstNode<'a>) -> (usize, usize, usize, usize) {
+    for n in node.ancestors() {
+        let pos = n.data.borrow().sourcepos;
+        if pos.start.line > 0 {
+            return (pos.start.line, pos.start.column, pos.end.line, pos.end.column + 1);
+        }
+    }
+    (1, 1, 1, 1)
+}
+
+fn lint_headings<'a>(linter: &mut Linter, root: &'a AstNode<'a>) {
+    let mut previous_level = 0u8;
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for node in root.descendants() {
+        let (level, setext) = match &node.data.borrow().value {
+            NodeValue::Heading(h) => (h.level, h.setext),
+            _ => continue,
+        };
+        let (line, column, end_line, end_column) = position(node);
+
+        // A position outside the source (or inside a character) is skipped
+        let hashes = linter
+            .source
+            .line(line)
+            .get(column.saturating_sub(1)..)
+            .map(|text| text.chars().take_while(|c| *c == '#').count());
+        if let Some(hashes) = hashes.filter(|_| linter.enabled("MD001") && previous_level > 0 && level > previous_level + 1) {
+            let expected = previous_level + 1;
+            let fix = (!setext).then(|| LintFix {
+                range: linter.source.range(line, column, line, column + hashes),
+                text: "#".repeat(expected as usize),
+            });
+            let range = linter.source.range(line, column, end_line, end_column);
+            linter.report(
+                "MD001",
+                format!("Heading levels should only increment by one: expected h{}, found h{}", expected, level),
+                range,
+                fix,
+            );
+        }
+        previous_level = level;
+
+        if linter.enabled("MD024") {
+            let text = plain_text(node).trim().to_string();
+            if let Some(first) = seen.get(&text) {
+                let range = linter.source.range(line, column, end_line, end_column);
+                let message = format!("Duplicate heading \"{}\" (first on line {})", text, first);
+                linter.report("MD024", message, range, None);
+            } else {
+                seen.insert(text, line + linter.source.line_offset);
+            }
+        }
+    }
+}
+
+fn lint_lists<'a>(linter: &mut Linter, root: &'a AstNode<'a>) {
+    if !linter.enabled("MD004") {
+        return;
+    }
+    let style = linter
+        .config
+        .option("MD004", "ul-style", "style")
+        .and_then(|s| s.as_str())
+        .unwrap_or("consistent");
+    let mut expected = match style {
+        "dash" => Some(b'-'),
+        "asterisk" => Some(b'*'),
+        "plus" => Some(b'+'),
+        _ => None,
+    };
+
+    for node in root.descendants() {
+        let bullet = match &node.data.borrow().value {
+            NodeValue::Item(list) if list.list_type == ListType::Bullet => list.bullet_char,
+            _ => continue,
+        };
+        let expected_bullet = *expected.get_or_insert(bullet);
+        if bullet == expected_bullet {
+            continue;
+        }
+        let (line, column, _, _) = position(node);
+        // The item starts at its marker
+        let Some(text) = linter.source.line(line).get(column.saturating_sub(1)..) else { continue };
+        let marker_column = column + text.find(bullet as char).unwrap_or(0);
+        let range = linter.source.range(line, marker_column, line, marker_column + 1);
+        linter.report(
+            "MD004",
+            format!("Unordered list style: expected \"{}\", found \"{}\"", expected_bullet as char, bullet as char),
+            range,
+            Some(LintFix { range, text: (expected_bullet as char).to_string() }),
+        );
+    }
+}
+
+fn lint_blocks<'a>(linter: &mut Linter, root: &'a AstNode<'a>) {
+    let check_fences = linter.enabled("MD040");
+    let check_alt = linter.enabled("MD045");
+
+    for node in root.descendants() {
+        let (unlabelled_fence, image) = match &node.data.borrow().value {
+            NodeValue::CodeBlock(block) => (block.fenced && block.info.trim().is_empty(), false),
+            NodeValue::Image(_) => (false, true),
+            _ => continue,
+        };
+        if unlabelled_fence && check_fences {
+            let range = linter.source.whole_line(node.data.borrow().sourcepos.start.line);
+            linter.report("MD040", "Fenced code blocks should have a language".to_string(), range, None);
+        }
+        if image && check_alt && plain_text(node).trim().is_empty() {
+            let (line, column, end_line, end_column) = position(node);
+            let range = linter.source.range(line, column, end_line, end_column);
+            linter.report("MD045", "Images should have alternate text".to_string(), range, None);
+        }
+    }
+}
+
+/// Body lines inside code and HTML blocks, where text rules don't apply.
+fn literal_lines<'a>(root: &'a AstNode<'a>) -> Vec<(usize, usize)> {
+    root.descendants()
+        .filter_map(|node| {
+            let data = node.data.borrow();
+            matches!(data.value, NodeValue::CodeBlock(_) | NodeValue::HtmlBlock(_) | NodeValue::Math(_))
+                .then(|| (data.sourcepos.start.line, data.sourcepos.end.line))
+        })
+        .collect()
+}
+
+fn bare_url_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r#"`[^`]*`|<[^>\n]*>|\[[^\]\n]*\]\([^)\n]*\)|\[[^\]\n]*\]:.*|"[^"\n]*"|'[^'\n]*'|https?://[^\s<>()\[\]]+"#)
+            .unwrap()
+    })
+}
+
+fn lint_text(linter: &mut Linter, literal: &[(usize, usize)], body_lines: usize) {
+    let check_spaces = linter.enabled("MD009");
+    let check_urls = linter.enabled("MD034");
+    // markdownlint allows exactly two trailing spaces as a hard line break;
+    // whitespace-only lines are still reported, as there is no text to break
+    let br_spaces = linter
+        .config
+        .option("MD009", "no-trailing-spaces", "br_spaces")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(2) as usize;
+
+    for line_number in 1..=body_lines {
+        if literal.iter().any(|(start, end)| (*start..=*end).contains(&line_number)) {
+            continue;
+        }
+        let line = linter.source.line(line_number).to_string();
+
+        if check_spaces {
+            let trimmed = line.trim_end_matches([' ', '\t']);
+            let trailing = &line[trimmed.len()..];
+            let hard_break =
+                !trimmed.is_empty() && br_spaces >= 2 && trailing.len() == br_spaces && !trailing.contains('\t');
+            if !trailing.is_empty() && !hard_break {
+                let range = linter.source.range(line_number, trimmed.len() + 1, line_number, line.len() + 1);
+                let fix = LintFix { range, text: String::new() };
+                linter.report("MD009", "Trailing spaces".to_string(), range, Some(fix));
+            }
+        }
+
+        if check_urls && line.contains("://") {
+            for m in bare_url_re().find_iter(&line) {
+                if !m.as_str().starts_with("http") {
+                    continue;
+                }
+                let url = m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']);
+                let start = m.start() + 1;
+                let range = linter.source.range(line_number, start, line_number, start + url.len());
+                let fix = LintFix { range, text: format!("<{}>", url) };
+                linter.report("MD034", format!("Bare URL used: {}", url), range, Some(fix));
+            }
+        }
+    }
+}
+
+/// Lint a document (frontmatter included) with `config`.
+pub fn lint(content: &str, config: &LintConfig) -> Vec<LintDiagnostic> {
+    let (body, _metadata) = crate::split_frontmatter(content);
+    let line_offset = content[..content.len() - body.len()].matches('\n').count();
+
+    let mut options = ComrakOptions::default();
+    crate::configure_markdown_options(&mut options);
+    let arena = Arena::new();
+    let root = parse_document(&arena, body, &options);
+
+    let mut linter = Linter {
+        config,
+        source: Source { lines: content.lines().collect(), line_offset },
+        diagnostics: Vec::new(),
+    };
+    lint_headings(&mut linter, root);
+    lint_lists(&mut linter, root);
+    lint_blocks(&mut linter, root);
+    lint_text(&mut linter, &literal_lines(root), body.lines().count());
+
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|d| (d.range.start_line, d.range.start_column, d.rule));
+    diagnostics
+}
+
+/// Lint markdown, using the `.markdownlint.*` config nearest to `path`.
+#[tauri::command]
+pub fn lint_markdown(content: String, path: Option<String>) -> Result<Vec<LintDiagnostic>, String> {
+    let config = match path.as_deref().map(Path::new).and_then(Path::parent) {
+        Some(dir) => LintConfig::find(dir)?,
+        None => LintConfig::default(),
+    };
+    Ok(lint(&content, &config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn rules(diagnostics: &[LintDiagnostic]) -> Vec<&'static str> {
+        diagnostics.iter().map(|d| d.rule).collect()
+    }
+
+    #[test]
+    fn test_heading_rules() {
+        let diagnostics = lint("# A\n\n### B\n\n## C\n\n## C\n", &LintConfig::default());
+        assert_eq!(rules(&diagnostics), vec!["MD001", "MD024"]);
+
+        let increment = &diagnostics[0];
+        assert_eq!(increment.range.start_line, 3);
+        let fix = increment.fix.as_ref().unwrap();
+        assert_eq!(fix.text, "##");
+        assert_eq!((fix.range.start_column, fix.range.end_column), (1, 4));
+
+        assert_eq!(diagnostics[1].range.start_line, 7);
+        assert!(diagnostics[1].message.contains("line 5"));
+    }
+
+    #[test]
+    fn test_frontmatter_offsets_lines() {
+        let diagnostics = lint("---\ntitle: x\n---\n# A\n### B\n", &LintConfig::default());
+        assert_eq!(diagnostics[0].rule, "MD001");
+        assert_eq!(diagnostics[0].range.start_line, 5);
+    }
+
+    #[test]
+    fn test_trailing_spaces_and_bare_urls() {
+        let content = "Hard break  \nTrailing   \nSee https://example.com. and <https://ok.example> or [x](https://ok.example)\n\n```text\ncode https://x.example   \n```\n";
+        let diagnostics = lint(content, &LintConfig::default());
+        assert_eq!(rules(&diagnostics), vec!["MD009", "MD034"]);
+
+        let spaces = &diagnostics[0];
+        assert_eq!(spaces.range.start_line, 2);
+        assert_eq!((spaces.range.start_column, spaces.range.end_column), (9, 12));
+
+        let url = &diagnostics[1];
+        assert_eq!((url.range.start_column, url.range.end_column), (5, 24));
+        assert_eq!(url.fix.as_ref().unwrap().text, "<https://example.com>");
+
+        // Whitespace-only lines are reported even with exactly br_spaces spaces
+        let diagnostics = lint("One\n  \n\t\nTwo\n", &LintConfig::default());
+        assert_eq!(rules(&diagnostics), vec!["MD009", "MD009"]);
+        assert_eq!(diagnostics[0].range.start_line, 2);
+        assert_eq!((diagnostics[0].range.start_column, diagnostics[0].range.end_column), (1, 3));
+    }
+
+    #[test]
+    fn test_list_markers_images_and_fences() {
+        let content = "- a\n* b\n\n![](x.png) ![ok](y.png)\n\n```\nplain\n```\n";
+        let diagnostics = lint(content, &LintConfig::default());
+        assert_eq!(rules(&diagnostics), vec!["MD004", "MD045", "MD040"]);
+        assert_eq!(diagnostics[0].fix.as_ref().unwrap().text, "-");
+        assert_eq!(diagnostics[0].range.start_line, 2);
+        assert_eq!(diagnostics[1].range.start_line, 4);
+        assert_eq!(diagnostics[2].range.start_line, 6);
+    }
+
+    #[test]
+    fn test_utf16_columns() {
+        let diagnostics = lint("中文 https://example.com\n", &LintConfig::default());
+        assert_eq!(diagnostics[0].range.start_column, 4);
+    }
+
+    #[test]
+    fn test_config() {
+        let config = LintConfig::from_value(serde_json::json!({
+            "MD009": false,
+            "ul-style": { "style": "asterisk", "severity": "error" },
+        }));
+        let diagnostics = lint("- a\n- b  \t\n", &config);
+        assert_eq!(rules(&diagnostics), vec!["MD004", "MD004"]);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+
+        let config = LintConfig::from_value(serde_json::json!({ "default": false, "MD040": true }));
+        assert_eq!(rules(&lint("# A\n### B\n\n```\nx\n```\n", &config)), vec!["MD040"]);
+    }
+
+    #[test]
+    fn test_find_config_walks_up() {
+        let root = TempDir::new("lint");
+        let nested = root.join("notes/sub");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(".markdownlint.yaml"), "default: true\nMD001: false\n").unwrap();
+
+        let config = LintConfig::find(&nested).unwrap();
+        assert!(!config.enabled("MD001", "heading-increment"));
+        assert!(config.enabled("MD009", "no-trailing-spaces"));
+
+        fs::write(nested.join(".markdownlint.json"), "{\"no-bare-urls\": false}").unwrap();
+        let config = LintConfig::find(&nested).unwrap();
+        assert!(config.enabled("MD001", "heading-increment"), "nearest file wins");
+        assert!(!config.enabled("MD034", "no-bare-urls"));
+    }
+}
@@ -0,0 +1,123 @@
+//! Server-side math rendering
+//!
+//! Converts comrak math nodes (`$...$`, `$$...$$`, `` $`...`$ `` and ```` ```math ````
+//! fences) to MathML with latex2mathml (pure Rust), so HTML exported or rendered
+//! without KaTeX in the webview still shows formulas.
+//!
+//! Formulas the converter can't handle are left as comrak renders them (the raw
+//! LaTeX inside a `data-math-style` span), which KaTeX can still pick up.
+
+use comrak::nodes::{AstNode, NodeHtmlBlock, NodeValue};
+use latex2mathml::{latex_to_mathml, DisplayStyle};
+
+/// Macros that define or load other macros; a stateless converter can't honour them.
+const UNSUPPORTED_MACROS: &[&str] = &["\\newcommand", "\\renewcommand", "\\def", "\\require", "\\let"];
+
+/// Convert a LaTeX formula to MathML, or `None` if it isn't supported.
+pub fn to_mathml(latex: &str, display: bool) -> Option<String> {
+    let latex = latex.trim();
+    if latex.is_empty() || UNSUPPORTED_MACROS.iter().any(|m| latex.contains(m)) {
+        return None;
+    }
+    let style = if display { DisplayStyle::Block } else { DisplayStyle::Inline };
+    latex_to_mathml(latex, style).ok()
+}
+
+/// Wrap MathML so it keeps the same hooks as comrak's math spans.
+fn wrap(mathml: &str, display: bool) -> String {
+    let style = if display { "display" } else { "inline" };
+    format!("<span data-math-style=\"{}\" class=\"math-mathml\">{}</span>", style, mathml)
+}
+
+/// Replace math nodes under `root` with MathML.
+///
+/// Returns the number of formulas that fell back to raw LaTeX.
+pub fn render_mathml<'a>(root: &'a AstNode<'a>) -> usize {
+    let mut fallbacks = 0;
+
+    for node in root.descendants() {
+        let replacement = {
+            let data = node.data.borrow();
+            match &data.value {
+                NodeValue::Math(math) => match to_mathml(&math.literal, math.display_math) {
+                    Some(mathml) => Some(NodeValue::HtmlInline(wrap(&mathml, math.display_math))),
+                    None => {
+                        fallbacks += 1;
+                        None
+                    }
+                },
+                NodeValue::CodeBlock(block) if block.info.split_whitespace().next() == Some("math") => {
+                    match to_mathml(&block.literal, true) {
+                        Some(mathml) => Some(NodeValue::HtmlBlock(NodeHtmlBlock {
+                            block_type: 6,
+                            literal: format!("<div class=\"math-block\">{}</div>\n", wrap(&mathml, true)),
+                        })),
+                        None => {
+                            fallbacks += 1;
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            }
+        };
+
+        if let Some(value) = replacement {
+            node.data.borrow_mut().value = value;
+        }
+    }
+
+    fallbacks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comrak::{format_html, parse_document, Arena, ComrakOptions};
+
+    fn render(markdown: &str) -> String {
+        let mut options = ComrakOptions::default();
+        options.extension.math_dollars = true;
+        options.extension.math_code = true;
+        let arena = Arena::new();
+        let root = parse_document(&arena, markdown, &options);
+        render_mathml(root);
+        let mut html = Vec::new();
+        format_html(root, &options, &mut html).unwrap();
+        String::from_utf8(html).unwrap()
+    }
+
+    #[test]
+    fn test_inline_and_display_math() {
+        let html = render("Euler: $e^{i\\pi} + 1 = 0$\n\n$$\\frac{a}{b}$$\n");
+        assert!(html.contains("<math"), "{}", html);
+        assert!(html.contains("display=\"block\""), "{}", html);
+        assert!(html.contains("<mfrac>"), "{}", html);
+        assert!(!html.contains("$"), "{}", html);
+    }
+
+    #[test]
+    fn test_math_code_block() {
+        let html = render("```math\nx^2\n```\n");
+        assert!(html.contains("class=\"math-block\""), "{}", html);
+        assert!(html.contains("<msup>"), "{}", html);
+        assert!(!html.contains("<pre>"), "{}", html);
+    }
+
+    #[test]
+    fn test_unsupported_falls_back_to_source() {
+        assert!(to_mathml("\\frac{a}{", false).is_none());
+        assert!(to_mathml("\\newcommand{\\R}{\\mathbb{R}} \\R", false).is_none());
+
+        let html = render("$\\frac{a}{$ stays\n");
+        assert!(html.contains("data-math-style=\"inline\""), "{}", html);
+        assert!(html.contains("\\frac{a}{"), "{}", html);
+        assert!(!html.contains("<math"), "{}", html);
+    }
+
+    #[test]
+    fn test_code_is_untouched() {
+        let html = render("`$x$`\n\n```rust\nlet x = 1;\n```\n");
+        assert!(!html.contains("<math"), "{}", html);
+    }
+}
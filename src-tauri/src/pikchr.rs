@@ -0,0 +1,105 @@
+//! Pikchr diagram rendering module
+//!
+//! Wraps the single-file Pikchr C library (https://pikchr.org), which turns
+//! PIC-like diagram text into SVG with no external dependencies.
+//!
+//! pikchr.c is downloaded by `scripts/download_pikchr.py` and compiled by
+//! build.rs with cc, the same way as the tree-sitter grammars. When it is not
+//! present the `pikchr` cfg is unset and rendering returns an error instead.
+
+/// Report errors as plain text rather than HTML
+#[cfg(pikchr)]
+const PIKCHR_PLAINTEXT_ERRORS: u32 = 0x0001;
+/// Render with colours suitable for a dark background
+#[cfg(pikchr)]
+const PIKCHR_DARK_MODE: u32 = 0x0002;
+
+#[cfg(pikchr)]
+mod ffi {
+    use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+    extern "C" {
+        pub fn pikchr(
+            z_text: *const c_char,
+            z_class: *const c_char,
+            m_flags: c_uint,
+            pn_width: *mut c_int,
+            pn_height: *mut c_int,
+        ) -> *mut c_char;
+
+        pub fn free(ptr: *mut c_void);
+    }
+}
+
+/// Render Pikchr source to an SVG string.
+///
+/// Returns the plain-text Pikchr error message on failure.
+#[cfg(pikchr)]
+pub fn render(source: &str, dark_mode: bool) -> Result<String, String> {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_int;
+
+    let text = CString::new(source).map_err(|_| "Pikchr source contains a NUL byte".to_string())?;
+    let class = CString::new("pikchr").unwrap();
+
+    let mut flags = PIKCHR_PLAINTEXT_ERRORS;
+    if dark_mode {
+        flags |= PIKCHR_DARK_MODE;
+    }
+
+    let mut width: c_int = 0;
+    let mut height: c_int = 0;
+
+    // SAFETY: both strings are valid NUL-terminated buffers for the duration of
+    // the call, and the returned buffer is allocated by pikchr with malloc.
+    unsafe {
+        let out = ffi::pikchr(text.as_ptr(), class.as_ptr(), flags, &mut width, &mut height);
+        if out.is_null() {
+            return Err("Pikchr ran out of memory".to_string());
+        }
+        let result = CStr::from_ptr(out).to_string_lossy().into_owned();
+        ffi::free(out as *mut _);
+
+        // pikchr signals an error by setting the width to a negative value
+        if width < 0 {
+            Err(result.trim().to_string())
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+/// Whether pikchr.c was compiled in (see build.rs)
+pub const AVAILABLE: bool = cfg!(pikchr);
+
+#[cfg(not(pikchr))]
+pub fn render(_source: &str, _dark_mode: bool) -> Result<String, String> {
+    Err("Pikchr support was not compiled in; run scripts/download_pikchr.py and rebuild".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(pikchr)]
+    fn test_render_simple_diagram() {
+        let svg = render("arrow; box \"Hello\"; arrow", false).expect("pikchr should render");
+        assert!(svg.contains("<svg"), "Result should contain SVG element");
+        assert!(svg.contains("Hello"));
+    }
+
+    #[test]
+    #[cfg(pikchr)]
+    fn test_render_reports_errors() {
+        let err = render("box \"unterminated", false).unwrap_err();
+        assert!(!err.is_empty());
+        assert!(!err.contains("<svg"), "Errors should not be returned as SVG");
+    }
+
+    #[test]
+    #[cfg(not(pikchr))]
+    fn test_render_without_library() {
+        assert!(render("box", false).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+//! SVG rasterisation module
+//!
+//! Converts SVG produced by the diagram renderers (layout-rs, svgbob, Pikchr)
+//! or by `highlight_code_svg` into PNG, for targets that cannot take SVG such as
+//! DOCX/e-mail export and clipboard pastes.
+//!
+//! Rendering uses resvg (pure Rust); PNG encoding uses the image crate.
+
+use resvg::tiny_skia::{self, Pixmap, Transform};
+use resvg::usvg::{self, fontdb};
+use std::sync::{Arc, OnceLock};
+
+/// Upper bound on either output dimension, to keep huge scales from exhausting memory.
+const MAX_DIMENSION: u32 = 16_384;
+
+/// System font database, loaded once (scanning fonts is slow).
+static FONT_DB: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+
+fn font_db() -> Arc<fontdb::Database> {
+    FONT_DB
+        .get_or_init(|| {
+            let mut db = fontdb::Database::new();
+            db.load_system_fonts();
+            Arc::new(db)
+        })
+        .clone()
+}
+
+/// Parse a CSS-style colour: `#rgb`, `#rrggbb`, `#rrggbbaa`, `white`, `black` or `transparent`.
+fn parse_color(color: &str) -> Result<tiny_skia::Color, String> {
+    let color = color.trim();
+    match color.to_ascii_lowercase().as_str() {
+        "" | "transparent" | "none" => return Ok(tiny_skia::Color::TRANSPARENT),
+        "white" => return Ok(tiny_skia::Color::WHITE),
+        "black" => return Ok(tiny_skia::Color::BLACK),
+        _ => {}
+    }
+
+    let hex = color
+        .strip_prefix('#')
+        .ok_or_else(|| format!("Unsupported colour: {}", color))?;
+    let digits: Vec<u8> = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("Invalid hex colour: {}", color))?;
+
+    let (r, g, b, a) = match digits.len() {
+        3 => (digits[0] * 17, digits[1] * 17, digits[2] * 17, 255),
+        6 => (
+            digits[0] * 16 + digits[1],
+            digits[2] * 16 + digits[3],
+            digits[4] * 16 + digits[5],
+            255,
+        ),
+        8 => (
+            digits[0] * 16 + digits[1],
+            digits[2] * 16 + digits[3],
+            digits[4] * 16 + digits[5],
+            digits[6] * 16 + digits[7],
+        ),
+        _ => return Err(format!("Invalid hex colour: {}", color)),
+    };
+    Ok(tiny_skia::Color::from_rgba8(r, g, b, a))
+}
+
+/// Rasterise an SVG document to an RGBA image.
+///
+/// `scale` multiplies the SVG's intrinsic size; `background` fills the canvas
+/// first (transparent when `None`).
+pub fn rasterize(svg: &str, scale: f32, background: Option<&str>) -> Result<image::RgbaImage, String> {
+    if !(scale.is_finite() && scale > 0.0) {
+        return Err(format!("Invalid scale factor: {}", scale));
+    }
+
+    let mut options = usvg::Options::default();
+    options.fontdb = font_db();
+    let tree = usvg::Tree::from_str(svg, &options).map_err(|e| format!("SVG parse error: {}", e))?;
+
+    let size = tree.size();
+    let width = (size.width() * scale).ceil() as u32;
+    let height = (size.height() * scale).ceil() as u32;
+    if width == 0 || height == 0 {
+        return Err("SVG has an empty size".to_string());
+    }
+    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(format!("Rasterised image too large: {}x{}", width, height));
+    }
+
+    let mut pixmap = Pixmap::new(width, height).ok_or("Failed to allocate pixmap")?;
+    if let Some(color) = background {
+        pixmap.fill(parse_color(color)?);
+    }
+    resvg::render(&tree, Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+    // tiny-skia stores premultiplied alpha; image expects straight RGBA
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    for pixel in pixmap.pixels() {
+        let c = pixel.demultiply();
+        rgba.extend_from_slice(&[c.red(), c.green(), c.blue(), c.alpha()]);
+    }
+    image::RgbaImage::from_raw(width, height, rgba).ok_or_else(|| "Invalid pixel buffer".to_string())
+}
+
+/// Encode an RGBA image as PNG bytes.
+pub fn encode_png(image: &image::RgbaImage) -> Result<Vec<u8>, String> {
+    use image::ImageEncoder;
+
+    let mut png_data = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png_data)
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image::ExtendedColorType::Rgba8,
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(png_data)
+}
+
+/// Rasterise SVG to a base64-encoded PNG.
+#[tauri::command]
+pub async fn rasterize_svg(
+    svg: String,
+    scale: Option<f32>,
+    background: Option<String>,
+) -> Result<String, String> {
+    use base64::{engine::general_purpose, Engine as _};
+    tauri::async_runtime::spawn_blocking(move || {
+        let image = rasterize(&svg, scale.unwrap_or(1.0), background.as_deref())?;
+        Ok(general_purpose::STANDARD.encode(encode_png(&image)?))
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()))
+}
+
+/// Rasterise SVG and place the result on the clipboard as an image.
+#[tauri::command]
+pub async fn copy_svg_as_image(
+    svg: String,
+    scale: Option<f32>,
+    background: Option<String>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let image = rasterize(&svg, scale.unwrap_or(1.0), background.as_deref())?;
+        let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+        clipboard
+            .set_image(arboard::ImageData {
+                width: image.width() as usize,
+                height: image.height() as usize,
+                bytes: image.into_raw().into(),
+            })
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="20" viewBox="0 0 10 20">
+<rect x="0" y="0" width="10" height="10" fill="#ff0000"/>
+</svg>"##;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#fff").unwrap(), tiny_skia::Color::WHITE);
+        assert_eq!(parse_color("#000000").unwrap(), tiny_skia::Color::BLACK);
+        assert_eq!(parse_color("transparent").unwrap(), tiny_skia::Color::TRANSPARENT);
+        assert_eq!(parse_color("#00000000").unwrap(), tiny_skia::Color::TRANSPARENT);
+        assert!(parse_color("#12").is_err());
+        assert!(parse_color("rebeccapurple").is_err());
+    }
+
+    #[test]
+    fn test_rasterize_scale_and_background() {
+        let image = rasterize(SQUARE, 2.0, Some("#0000ff")).unwrap();
+        assert_eq!((image.width(), image.height()), (20, 40));
+        // Top half is the red rect, bottom half the blue background
+        assert_eq!(image.get_pixel(5, 5).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(5, 35).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_rasterize_transparent_background() {
+        let image = rasterize(SQUARE, 1.0, None).unwrap();
+        assert_eq!(image.get_pixel(5, 15).0[3], 0);
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let image = rasterize(SQUARE, 1.0, Some("white")).unwrap();
+        let png = encode_png(&image).unwrap();
+        assert!(png.starts_with(&[0x89, b'P', b'N', b'G']));
+    }
+
+    #[test]
+    fn test_rasterize_rejects_bad_input() {
+        assert!(rasterize("not svg", 1.0, None).is_err());
+        assert!(rasterize(SQUARE, 0.0, None).is_err());
+        assert!(rasterize(SQUARE, 10_000.0, None).is_err());
+    }
+
+    #[test]
+    fn test_rasterize_svgbob_output() {
+        let svg = svgbob::to_svg("+---+\n| A |\n+---+");
+        let image = rasterize(&svg, 1.5, Some("#ffffff")).unwrap();
+        assert!(image.width() > 0 && image.height() > 0);
+    }
+}
@@ -0,0 +1,432 @@
+//! Persistent diagram render cache
+//!
+//! Rendered SVG from the Rust renderers (Graphviz, Svgbob, Pikchr, Kroki) is
+//! stored in the app data directory, keyed by renderer id, renderer version and
+//! a hash of the source, so reopening a note or a `file-changed` reload doesn't
+//! re-render unchanged diagrams.
+//!
+//! The cache is bounded by total size and evicts least recently used entries.
+//! Recency survives restarts through file modification times.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Default size bound for the cache directory.
+pub const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Version for renderers compiled into the app: their output only changes
+/// when the app (and with it the renderer crates) is updated.
+pub const BUILTIN_RENDERER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const ENTRY_EXTENSION: &str = "svg";
+
+/// Global cache instance, set up in `run()` once the app data dir is known.
+static RENDER_CACHE: OnceLock<RenderCache> = OnceLock::new();
+
+/// Initialise the global cache. Later calls are ignored.
+pub fn init(dir: PathBuf) {
+    let _ = RENDER_CACHE.set(RenderCache::open(dir, DEFAULT_MAX_BYTES));
+}
+
+/// The global cache, if it has been initialised.
+pub fn global() -> Option<&'static RenderCache> {
+    RENDER_CACHE.get()
+}
+
+/// Return a cached render or run `render` and cache its successful output.
+///
+/// Renders straight through when the global cache isn't initialised (tests).
+pub fn get_or_render<E>(
+    renderer: &str,
+    version: &str,
+    source: &str,
+    render: impl FnOnce() -> Result<String, E>,
+) -> Result<String, E> {
+    let Some(cache) = global() else {
+        return render();
+    };
+
+    let key = RenderCache::key(renderer, version, source);
+    if let Some(svg) = cache.get(&key) {
+        return Ok(svg);
+    }
+    let svg = render()?;
+    cache.put(&key, &svg);
+    Ok(svg)
+}
+
+/// Cache statistics.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderCacheStats {
+    pub entries: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    /// Hits and misses since the app started
+    pub hits: u64,
+    pub misses: u64,
+    pub directory: String,
+}
+
+struct Entry {
+    size: u64,
+    /// Logical clock value of the last access
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, Entry>,
+    total_bytes: u64,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl CacheState {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_bytes -= entry.size;
+        }
+    }
+}
+
+/// Size-bounded LRU cache of rendered SVG on disk.
+pub struct RenderCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    state: Mutex<CacheState>,
+}
+
+impl RenderCache {
+    /// Open a cache directory, indexing any entries left from earlier sessions.
+    pub fn open(dir: PathBuf, max_bytes: u64) -> Self {
+        let mut found: Vec<(String, u64, SystemTime)> = Vec::new();
+        if let Ok(read_dir) = fs::read_dir(&dir) {
+            for entry in read_dir.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some(ENTRY_EXTENSION) {
+                    // Leftover temp files from an interrupted write
+                    if path.extension().and_then(|e| e.to_str()) == Some("tmp") {
+                        let _ = fs::remove_file(&path);
+                    }
+                    continue;
+                }
+                let (Some(key), Ok(meta)) = (path.file_stem().and_then(|s| s.to_str()), entry.metadata()) else {
+                    continue;
+                };
+                let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                found.push((key.to_string(), meta.len(), modified));
+            }
+        }
+
+        // Oldest first, so the clock reproduces the previous access order
+        found.sort_by_key(|(_, _, modified)| *modified);
+        let mut state = CacheState::default();
+        for (key, size, _) in found {
+            let last_used = state.tick();
+            state.total_bytes += size;
+            state.entries.insert(key, Entry { size, last_used });
+        }
+
+        let cache = Self { dir, max_bytes, state: Mutex::new(state) };
+        let evicted = cache.evict(&mut cache.state.lock().unwrap());
+        cache.remove_files(evicted);
+        cache
+    }
+
+    /// Cache key for a render: hex SHA-256 over renderer id, version and source.
+    pub fn key(renderer: &str, version: &str, source: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [renderer, version, source] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, ENTRY_EXTENSION))
+    }
+
+    /// Look up an entry, marking it as recently used.
+    ///
+    /// File I/O happens outside the lock so concurrent renders don't queue behind each other's disk access.
+    pub fn get(&self, key: &str) -> Option<String> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if !state.entries.contains_key(key) {
+                state.misses += 1;
+                return None;
+            }
+        }
+
+        let path = self.entry_path(key);
+        let read = fs::read_to_string(&path);
+        if read.is_ok() {
+            touch(&path);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        match read {
+            Ok(svg) => {
+                let now = state.tick();
+                if let Some(entry) = state.entries.get_mut(key) {
+                    entry.last_used = now;
+                }
+                state.hits += 1;
+                Some(svg)
+            }
+            Err(_) => {
+                // Deleted behind our back
+                state.remove(key);
+                state.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Store an entry, evicting old entries to stay under the size bound.
+    ///
+    /// Write failures are logged and otherwise ignored: the cache is an optimisation.
+    pub fn put(&self, key: &str, svg: &str) {
+        let size = svg.len() as u64;
+        if size > self.max_bytes {
+            return;
+        }
+
+        if let Err(e) = self.write_entry(key, svg) {
+            log::warn!("Failed to write render cache entry: {}", e);
+            return;
+        }
+
+        let evicted = {
+            let mut state = self.state.lock().unwrap();
+            state.remove(key);
+            let last_used = state.tick();
+            state.total_bytes += size;
+            state.entries.insert(key.to_string(), Entry { size, last_used });
+            self.evict(&mut state)
+        };
+        self.remove_files(evicted);
+    }
+
+    fn write_entry(&self, key: &str, svg: &str) -> std::io::Result<()> {
+        static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temp file first so a crash never leaves a truncated SVG behind.
+        // The counter keeps concurrent writes of the same key from sharing a temp file.
+        let path = self.entry_path(key);
+        let tmp = path.with_extension(format!("{}.tmp", TMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+        fs::write(&tmp, svg)?;
+        fs::rename(&tmp, &path)
+    }
+
+    /// Drop least recently used entries from the index until under the size bound.
+    /// Returns the evicted keys so their files can be deleted after unlocking.
+    fn evict(&self, state: &mut CacheState) -> Vec<String> {
+        let mut evicted = Vec::new();
+        if state.total_bytes <= self.max_bytes {
+            return evicted;
+        }
+
+        let mut by_age: Vec<(u64, String)> = state
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.last_used, key.clone()))
+            .collect();
+        by_age.sort();
+
+        for (_, key) in by_age {
+            if state.total_bytes <= self.max_bytes {
+                break;
+            }
+            state.remove(&key);
+            evicted.push(key);
+        }
+        evicted
+    }
+
+    /// Delete the files of evicted entries. If a concurrent `put` re-created one
+    /// in the meantime, `get` notices the missing file and drops the entry.
+    fn remove_files(&self, keys: Vec<String>) {
+        for key in keys {
+            let _ = fs::remove_file(self.entry_path(&key));
+        }
+    }
+
+    /// Remove every entry.
+    ///
+    /// Entries whose file can't be deleted stay in the index; the first failure is returned.
+    pub fn clear(&self) -> Result<(), String> {
+        let keys: Vec<String> = self.state.lock().unwrap().entries.keys().cloned().collect();
+
+        let mut removed = Vec::new();
+        let mut error = None;
+        for key in keys {
+            let path = self.entry_path(&key);
+            match fs::remove_file(&path) {
+                Ok(()) => removed.push(key),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => removed.push(key),
+                Err(e) => {
+                    error.get_or_insert_with(|| format!("Cannot delete {}: {}", path.display(), e));
+                }
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        for key in &removed {
+            state.remove(key);
+        }
+        error.map_or(Ok(()), Err)
+    }
+
+    pub fn stats(&self) -> RenderCacheStats {
+        let state = self.state.lock().unwrap();
+        RenderCacheStats {
+            entries: state.entries.len(),
+            total_bytes: state.total_bytes,
+            max_bytes: self.max_bytes,
+            hits: state.hits,
+            misses: state.misses,
+            directory: self.dir.to_string_lossy().to_string(),
+        }
+    }
+}
+
+/// Bump a file's modification time so recency survives restarts.
+fn touch(path: &Path) {
+    if let Ok(file) = fs::OpenOptions::new().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// Get render cache statistics.
+#[tauri::command]
+pub fn get_render_cache_stats() -> Result<RenderCacheStats, String> {
+    global()
+        .map(RenderCache::stats)
+        .ok_or_else(|| "Render cache is not initialised".to_string())
+}
+
+/// Delete all cached renders.
+#[tauri::command]
+pub fn clear_render_cache() -> Result<(), String> {
+    global()
+        .ok_or_else(|| "Render cache is not initialised".to_string())?
+        .clear()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn test_dir(name: &str) -> TempDir {
+        TempDir::new(&format!("render-cache-{}", name))
+    }
+
+    #[test]
+    fn test_key_separates_renderer_version_and_source() {
+        let key = RenderCache::key("graphviz", "1.0", "digraph { a }");
+        assert_eq!(key, RenderCache::key("graphviz", "1.0", "digraph { a }"));
+        assert_ne!(key, RenderCache::key("svgbob", "1.0", "digraph { a }"));
+        assert_ne!(key, RenderCache::key("graphviz", "1.1", "digraph { a }"));
+        assert_ne!(key, RenderCache::key("graphviz", "1.0", "digraph { b }"));
+        // Length prefixes keep field boundaries unambiguous
+        assert_ne!(RenderCache::key("ab", "c", ""), RenderCache::key("a", "bc", ""));
+    }
+
+    #[test]
+    fn test_get_put_and_stats() {
+        let dir = test_dir("basic");
+        let cache = RenderCache::open(dir.to_path_buf(), 1024);
+        let key = RenderCache::key("svgbob", "1", "+--+");
+
+        assert_eq!(cache.get(&key), None);
+        cache.put(&key, "<svg/>");
+        assert_eq!(cache.get(&key).as_deref(), Some("<svg/>"));
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.total_bytes, 6);
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let dir = test_dir("lru");
+        let cache = RenderCache::open(dir.to_path_buf(), 25);
+        let svg = "x".repeat(10);
+
+        cache.put("a", &svg);
+        cache.put("b", &svg);
+        // Touch "a" so "b" becomes the least recently used
+        assert!(cache.get("a").is_some());
+        cache.put("c", &svg);
+
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none(), "least recently used entry should be evicted");
+        assert!(cache.get("c").is_some());
+        assert!(!cache.entry_path("b").exists());
+        assert!(cache.stats().total_bytes <= 25);
+    }
+
+    #[test]
+    fn test_persists_across_sessions() {
+        let dir = test_dir("persist");
+        {
+            let cache = RenderCache::open(dir.to_path_buf(), 1024);
+            cache.put("k", "<svg>persisted</svg>");
+        }
+        let reopened = RenderCache::open(dir.to_path_buf(), 1024);
+        assert_eq!(reopened.stats().entries, 1);
+        assert_eq!(reopened.get("k").as_deref(), Some("<svg>persisted</svg>"));
+
+        // Reopening with a smaller bound evicts down to it
+        let shrunk = RenderCache::open(dir.to_path_buf(), 4);
+        assert_eq!(shrunk.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_clear() {
+        let dir = test_dir("clear");
+        let cache = RenderCache::open(dir.to_path_buf(), 1024);
+        cache.put("a", "<svg/>");
+        cache.put("b", "<svg/>");
+        cache.clear().unwrap();
+
+        assert_eq!(cache.stats().entries, 0);
+        assert_eq!(cache.stats().total_bytes, 0);
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn test_clear_keeps_entries_it_cannot_delete() {
+        let dir = test_dir("clear-partial");
+        let cache = RenderCache::open(dir.to_path_buf(), 1024);
+        cache.put("a", "<svg/>");
+        cache.put("b", "<svg/>");
+        // A directory in place of the entry file makes remove_file fail
+        fs::remove_file(cache.entry_path("b")).unwrap();
+        fs::create_dir_all(cache.entry_path("b").join("blocker")).unwrap();
+
+        assert!(cache.clear().is_err());
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 1, "only the undeletable entry should stay indexed");
+        assert_eq!(stats.total_bytes, 6);
+        assert!(!cache.entry_path("a").exists());
+    }
+}
@@ -0,0 +1,343 @@
+//! Offline spell checking with Hunspell dictionaries
+//!
+//! Dictionaries are `{lang}.aff` / `{lang}.dic` pairs in the `dictionaries`
+//! folder of the app config directory, loaded with spellbook (a pure-Rust
+//! Hunspell engine) so checking works where the webview has no spell checker.
+//!
+//! Only prose is checked: paragraphs, headings and table cells from the comrak
+//! AST, with code spans, math, URLs, HTML, link destinations, wikilink targets,
+//! citations and tags masked out. CJK text is skipped. Words listed in the
+//! nearest `.markpad-dictionary.txt` (one per line) are always accepted.
+
+use crate::lint::LintRange;
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::{parse_document, Arena, ComrakOptions};
+use regex::Regex;
+use serde::Serialize;
+use spellbook::Dictionary;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_LANGUAGE: &str = "en_US";
+/// Per-workspace word list, found by walking up from the document
+pub const CUSTOM_DICTIONARY: &str = ".markpad-dictionary.txt";
+const MAX_SUGGESTIONS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Misspelling {
+    pub word: String,
+    pub range: LintRange,
+    pub suggestions: Vec<String>,
+}
+
+type DictionaryCache = HashMap<PathBuf, (Option<SystemTime>, Arc<Dictionary>)>;
+
+fn dictionary_cache() -> &'static Mutex<DictionaryCache> {
+    static CACHE: OnceLock<Mutex<DictionaryCache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn dictionaries_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app.path().app_config_dir().map_err(|e| e.to_string())?.join("dictionaries"))
+}
+
+/// Load `{dir}/{language}.aff` and `.dic`, reusing the parsed dictionary until
+/// the `.dic` file changes.
+pub fn load_dictionary(dir: &Path, language: &str) -> Result<Arc<Dictionary>, String> {
+    let aff_path = dir.join(format!("{}.aff", language));
+    let dic_path = dir.join(format!("{}.dic", language));
+    let modified = fs::metadata(&dic_path).and_then(|m| m.modified()).ok();
+
+    let mut cache = dictionary_cache().lock().unwrap();
+    if let Some((cached_modified, dictionary)) = cache.get(&dic_path) {
+        if *cached_modified == modified {
+            return Ok(dictionary.clone());
+        }
+    }
+
+    let aff = fs::read_to_string(&aff_path)
+        .map_err(|_| format!("No Hunspell dictionary for {} in {}", language, dir.display()))?;
+    let dic = fs::read_to_string(&dic_path).map_err(|e| format!("Cannot read {}: {}", dic_path.display(), e))?;
+    let dictionary = Arc::new(Dictionary::new(&aff, &dic).map_err(|e| format!("Invalid dictionary {}: {}", language, e))?);
+    cache.insert(dic_path, (modified, dictionary.clone()));
+    Ok(dictionary)
+}
+
+/// Nearest custom dictionary at or above `dir`.
+fn find_custom_dictionary(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().map(|d| d.join(CUSTOM_DICTIONARY)).find(|p| p.is_file())
+}
+
+/// Words from the nearest custom dictionary, ignoring blank lines and `#` comments.
+pub fn custom_words(dir: &Path) -> HashSet<String> {
+    find_custom_dictionary(dir)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|text| {
+            text.lines()
+                .map(str::trim)
+                .filter(|w| !w.is_empty() && !w.starts_with('#'))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Inline syntax that isn't prose; matched text is blanked before tokenising.
+fn mask_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?s)``.*?``|`[^`]*`",                    // code spans
+            r"|\$\$.*?\$\$|\$[^$\n]+\$",               // math
+            r"|\\\(.*?\\\)|\\\[.*?\\\]",
+            r"|<[^>\n]*>",                             // HTML tags and autolinks
+            r"|\]\([^)\n]*\)|\]\[[^\]\n]*\]",          // link destinations and references
+            r"|(?:https?|ftp)://\S+|www\.\S+",         // bare URLs
+            r"|[\w.+-]+@[\w-]+\.[\w.-]+",              // email addresses
+            r"|\[\[[^\]|\n]*\|?",                      // wikilink targets (aliases are prose)
+            r"|\{[^}\n]*\}",                           // attributes and CriticMarkup braces
+            r"|\[\^[^\]\n]*\]|\s\^[A-Za-z0-9_-]+",     // footnote references, block ids
+            r"|[@#][\w:./-]+",                         // citations and tags
+            r"|&\w+;",                                 // entities
+        ))
+        .unwrap()
+    })
+}
+
+fn word_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"[\p{L}\p{M}\p{N}_]+(?:['’][\p{L}\p{M}]+)*").unwrap())
+}
+
+fn cjk_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\p{Han}|\p{Hiragana}|\p{Katakana}|\p{Hangul}").unwrap())
+}
+
+/// Whether a token is worth looking up: no digits, no underscores, no CJK,
+/// more than one letter.
+fn is_checkable(word: &str) -> bool {
+    word.chars().count() > 1
+        && !word.chars().any(|c| c.is_numeric() || c == '_')
+        && !cjk_re().is_match(word)
+}
+
+/// Blocks whose inline content is prose.
+fn is_prose_block(value: &NodeValue) -> bool {
+    matches!(value, NodeValue::Paragraph | NodeValue::Heading(_) | NodeValue::TableCell)
+}
+
+/// Byte offset of a 1-based line/column in `text`, given its line starts.
+fn byte_offset(text: &str, line_starts: &[usize], line: usize, column: usize) -> Option<usize> {
+    let start = *line_starts.get(line.checked_sub(1)?)?;
+    let line_end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+    let mut offset = (start + column.saturating_sub(1)).min(line_end);
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    Some(offset)
+}
+
+/// Byte ranges of prose blocks in `body`.
+fn prose_ranges<'a>(root: &'a AstNode<'a>, body: &str) -> Vec<(usize, usize)> {
+    let line_starts: Vec<usize> = std::iter::once(0).chain(body.match_indices('\n').map(|(i, _)| i + 1)).collect();
+    let mut ranges = Vec::new();
+    for node in root.descendants() {
+        let data = node.data.borrow();
+        if !is_prose_block(&data.value) || data.sourcepos.start.line == 0 {
+            continue;
+        }
+        let (start, end) = (data.sourcepos.start, data.sourcepos.end);
+        let (Some(from), Some(to)) = (
+            byte_offset(body, &line_starts, start.line, start.column),
+            byte_offset(body, &line_starts, end.line, end.column + 1),
+        ) else {
+            continue;
+        };
+        if from < to {
+            ranges.push((from, to));
+        }
+    }
+    ranges
+}
+
+/// Misspelled words in `content`, with UTF-16 ranges and suggestions.
+pub fn check_document(content: &str, dictionary: &Dictionary, custom: &HashSet<String>) -> Vec<Misspelling> {
+    let (body, _metadata) = crate::split_frontmatter(content);
+    let body_start = content.len() - body.len();
+
+    let mut options = ComrakOptions::default();
+    crate::configure_markdown_options(&mut options);
+    let arena = Arena::new();
+    let root = parse_document(&arena, body, &options);
+
+    let line_starts: Vec<usize> = std::iter::once(0).chain(content.match_indices('\n').map(|(i, _)| i + 1)).collect();
+    let position = |offset: usize| {
+        let line = line_starts.partition_point(|&start| start <= offset);
+        let start = line_starts[line - 1];
+        (line, content[start..offset].encode_utf16().count() + 1)
+    };
+
+    let mut verdicts: HashMap<&str, Option<Vec<String>>> = HashMap::new();
+    let mut misspellings = Vec::new();
+    for (from, to) in prose_ranges(root, body) {
+        let text = &body[from..to];
+        let mut masked = text.as_bytes().to_vec();
+        for m in mask_re().find_iter(text) {
+            masked[m.range()].fill(b' ');
+        }
+        // Blanking whole matches with ASCII keeps the text valid UTF-8 and byte offsets stable
+        let masked = String::from_utf8(masked).unwrap_or_default();
+
+        for m in word_re().find_iter(&masked) {
+            let word = &text[m.range()];
+            let word = word.trim_end_matches(['\'', '’']);
+            if !is_checkable(word) || custom.contains(word) || custom.contains(&word.to_lowercase()) {
+                continue;
+            }
+            let suggestions = verdicts.entry(word).or_insert_with(|| {
+                (!dictionary.check(word)).then(|| {
+                    let mut suggestions = Vec::new();
+                    dictionary.suggest(word, &mut suggestions);
+                    suggestions.truncate(MAX_SUGGESTIONS);
+                    suggestions
+                })
+            });
+            let Some(suggestions) = suggestions else { continue };
+
+            let start = body_start + from + m.start();
+            let (start_line, start_column) = position(start);
+            let (end_line, end_column) = position(start + word.len());
+            misspellings.push(Misspelling {
+                word: word.to_string(),
+                range: LintRange { start_line, start_column, end_line, end_column },
+                suggestions: suggestions.clone(),
+            });
+        }
+    }
+    misspellings.sort_by_key(|m| (m.range.start_line, m.range.start_column));
+    misspellings
+}
+
+/// Check a document against a Hunspell dictionary from the config directory.
+#[tauri::command]
+pub async fn spell_check(
+    app: AppHandle,
+    content: String,
+    path: Option<String>,
+    language: Option<String>,
+) -> Result<Vec<Misspelling>, String> {
+    let dir = dictionaries_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let language = language.filter(|l| !l.is_empty()).unwrap_or_else(|| DEFAULT_LANGUAGE.to_string());
+        let dictionary = load_dictionary(&dir, &language)?;
+        let custom = path
+            .as_deref()
+            .and_then(|p| Path::new(p).parent())
+            .map(custom_words)
+            .unwrap_or_default();
+        Ok(check_document(&content, &dictionary, &custom))
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()))
+}
+
+/// Languages with both `.aff` and `.dic` files installed.
+#[tauri::command]
+pub fn spell_list_languages(app: AppHandle) -> Result<Vec<String>, String> {
+    let dir = dictionaries_dir(&app)?;
+    let mut languages = Vec::new();
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == "dic") && path.with_extension("aff").is_file() {
+                if let Some(name) = path.file_stem().and_then(|n| n.to_str()) {
+                    languages.push(name.to_string());
+                }
+            }
+        }
+    }
+    languages.sort();
+    Ok(languages)
+}
+
+/// Add a word to the custom dictionary nearest to `dir`, creating one in
+/// `dir` if there is none.
+#[tauri::command]
+pub fn spell_add_word(dir: String, word: String) -> Result<(), String> {
+    let word = word.trim();
+    if word.is_empty() || word.contains(char::is_whitespace) {
+        return Err(format!("Not a single word: {:?}", word));
+    }
+    let path = find_custom_dictionary(Path::new(&dir)).unwrap_or_else(|| Path::new(&dir).join(CUSTOM_DICTIONARY));
+    let mut text = fs::read_to_string(&path).unwrap_or_default();
+    if text.lines().any(|line| line.trim() == word) {
+        return Ok(());
+    }
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str(word);
+    text.push('\n');
+    fs::write(&path, text).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    const AFF: &str = "SET UTF-8\nTRY esianrtolcdugmphbyfvkwzESIANRTOLCDUGMPHBYFVKWZ'\n";
+    const DIC: &str = "8\nhello\nworld\nthe\nquick\nbrown\nfox\nlink\ntitle\n";
+
+    fn dictionary() -> Dictionary {
+        Dictionary::new(AFF, DIC).unwrap()
+    }
+
+    fn words(misspellings: &[Misspelling]) -> Vec<&str> {
+        misspellings.iter().map(|m| m.word.as_str()).collect()
+    }
+
+    #[test]
+    fn test_reports_misspellings_with_positions() {
+        let doc = "---\ntitle: Helo\n---\n# Hello wrold\n\nThe quikc brown fox.\n";
+        let found = check_document(doc, &dictionary(), &HashSet::new());
+        assert_eq!(words(&found), vec!["wrold", "quikc"]);
+        assert_eq!(found[0].range, LintRange { start_line: 4, start_column: 9, end_line: 4, end_column: 14 });
+        assert_eq!(found[1].range.start_line, 6);
+        assert!(found[0].suggestions.contains(&"world".to_string()), "{:?}", found[0].suggestions);
+    }
+
+    #[test]
+    fn test_skips_code_math_urls_and_wikilink_targets() {
+        let doc = concat!(
+            "Hello `wrold` $qx + yz$ https://exmple.com/pth <span clss=\"x\">world</span>\n\n",
+            "[[Nte Pge|the link]] [title](./fle.md) @cite2020 #tagg\n\n",
+            "```\nnotaword\n```\n\n",
+            "| the | fxo |\n|---|---|\n| brown | 你好 |\n",
+        );
+        let found = check_document(doc, &dictionary(), &HashSet::new());
+        assert_eq!(words(&found), vec!["fxo"]);
+    }
+
+    #[test]
+    fn test_custom_dictionary() {
+        let dir = TempDir::new("spell");
+        let nested = dir.join("notes");
+        fs::create_dir_all(&nested).unwrap();
+
+        spell_add_word(dir.to_string_lossy().to_string(), "Markpad".to_string()).unwrap();
+        spell_add_word(nested.to_string_lossy().to_string(), "comrak".to_string()).unwrap();
+        spell_add_word(nested.to_string_lossy().to_string(), "comrak".to_string()).unwrap();
+        assert_eq!(fs::read_to_string(dir.join(CUSTOM_DICTIONARY)).unwrap(), "Markpad\ncomrak\n");
+
+        let custom = custom_words(&nested);
+        let found = check_document("Hello Markpad and comrak\n", &dictionary(), &custom);
+        assert_eq!(words(&found), vec!["and"]);
+    }
+}
@@ -0,0 +1,347 @@
+//! Document statistics
+//!
+//! Counts come from the comrak AST, so code, math, HTML, image alt text and
+//! frontmatter are left out. CJK characters count as one word each; other
+//! scripts are split into words. Readability scores use the Flesch formulas
+//! over the non-CJK words, with an English syllable heuristic.
+
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::{parse_document, Anchorizer, Arena, ComrakOptions};
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+const WORDS_PER_MINUTE: f64 = 200.0;
+const CJK_CHARS_PER_MINUTE: f64 = 300.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextCounts {
+    /// Words, with each CJK character counted as a word
+    pub words: usize,
+    pub characters: usize,
+    pub characters_no_spaces: usize,
+    pub cjk_characters: usize,
+    /// Estimated reading time in seconds
+    pub reading_seconds: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Readability {
+    /// 0-100, higher is easier
+    pub flesch_reading_ease: f64,
+    /// US school grade
+    pub flesch_kincaid_grade: f64,
+    pub sentences: usize,
+    pub syllables: usize,
+}
+
+/// Counts for one heading and the text up to the next heading.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionStats {
+    /// Heading anchor, as in the rendered preview; empty for text before the first heading
+    pub id: String,
+    pub title: String,
+    /// 0 for text before the first heading
+    pub level: u8,
+    pub line: usize,
+    #[serde(flatten)]
+    pub counts: TextCounts,
+    /// Words including nested subsections
+    pub total_words: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentStats {
+    #[serde(flatten)]
+    pub counts: TextCounts,
+    pub headings: usize,
+    pub links: usize,
+    pub images: usize,
+    pub code_blocks: usize,
+    pub tasks: usize,
+    pub tasks_done: usize,
+    /// `None` when there are no non-CJK words to score
+    pub readability: Option<Readability>,
+    pub sections: Vec<SectionStats>,
+}
+
+fn cjk_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"[\p{Han}\p{Hiragana}\p{Katakana}\p{Hangul}]").unwrap())
+}
+
+fn word_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"[\p{L}\p{M}\p{N}]+(?:['’.-][\p{L}\p{M}\p{N}]+)*").unwrap())
+}
+
+fn wikilink_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(!?)\[\[([^\]\n]+)\]\]").unwrap())
+}
+
+/// English syllable estimate: vowel groups, less a silent final `e`.
+fn syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for c in word.chars() {
+        let vowel = matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    if count > 1 && word.ends_with('e') && !word.ends_with("le") {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+/// Running totals for a stretch of prose.
+#[derive(Debug, Default, Clone, Copy)]
+struct Tally {
+    words: usize,
+    cjk: usize,
+    characters: usize,
+    characters_no_spaces: usize,
+    sentences: usize,
+    syllables: usize,
+}
+
+impl Tally {
+    /// Add the text of one block; each block ends at least one sentence.
+    fn add(&mut self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        self.characters += text.chars().count();
+        self.characters_no_spaces += text.chars().filter(|c| !c.is_whitespace()).count();
+        self.cjk += cjk_re().find_iter(text).count();
+
+        for sentence in text.split(['.', '!', '?', '。', '！', '？']) {
+            let latin = cjk_re().replace_all(sentence, " ");
+            let mut any = false;
+            for word in word_re().find_iter(&latin) {
+                // Numbers are words but have no syllables worth scoring
+                if word.as_str().chars().any(char::is_alphabetic) {
+                    self.syllables += syllables(word.as_str());
+                }
+                self.words += 1;
+                any = true;
+            }
+            if any {
+                self.sentences += 1;
+            }
+        }
+    }
+
+    fn counts(&self) -> TextCounts {
+        let minutes = self.words as f64 / WORDS_PER_MINUTE + self.cjk as f64 / CJK_CHARS_PER_MINUTE;
+        TextCounts {
+            words: self.words + self.cjk,
+            characters: self.characters,
+            characters_no_spaces: self.characters_no_spaces,
+            cjk_characters: self.cjk,
+            reading_seconds: (minutes * 60.0).ceil() as u64,
+        }
+    }
+
+    fn readability(&self) -> Option<Readability> {
+        if self.words == 0 || self.sentences == 0 {
+            return None;
+        }
+        let words_per_sentence = self.words as f64 / self.sentences as f64;
+        let syllables_per_word = self.syllables as f64 / self.words as f64;
+        let round = |x: f64| (x * 10.0).round() / 10.0;
+        Some(Readability {
+            flesch_reading_ease: round(206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word),
+            flesch_kincaid_grade: round(0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59),
+            sentences: self.sentences,
+            syllables: self.syllables,
+        })
+    }
+}
+
+/// Prose of an inline container, with code, math, HTML and image alt text blanked.
+fn block_text<'a>(node: &'a AstNode<'a>, out: &mut String) {
+    for child in node.children() {
+        match &child.data.borrow().value {
+            NodeValue::Text(text) => out.push_str(text),
+            NodeValue::SoftBreak | NodeValue::LineBreak => out.push('\n'),
+            NodeValue::Code(_) | NodeValue::Math(_) | NodeValue::HtmlInline(_) | NodeValue::Image(_) => out.push(' '),
+            _ => block_text(child, out),
+        }
+    }
+}
+
+struct Section {
+    id: String,
+    title: String,
+    level: u8,
+    line: usize,
+    tally: Tally,
+}
+
+pub fn compute(content: &str) -> DocumentStats {
+    let (body, _metadata) = crate::split_frontmatter(content);
+    let line_offset = content[..content.len() - body.len()].matches('\n').count();
+
+    let mut options = ComrakOptions::default();
+    crate::configure_markdown_options(&mut options);
+    let arena = Arena::new();
+    let root = parse_document(&arena, body, &options);
+
+    let mut stats = DocumentStats {
+        counts: TextCounts::default(),
+        headings: 0,
+        links: 0,
+        images: 0,
+        code_blocks: 0,
+        tasks: 0,
+        tasks_done: 0,
+        readability: None,
+        sections: Vec::new(),
+    };
+    let mut total = Tally::default();
+    let mut sections = vec![Section { id: String::new(), title: String::new(), level: 0, line: 1, tally: Tally::default() }];
+    let mut anchorizer = Anchorizer::new();
+
+    for node in root.descendants() {
+        let value = node.data.borrow().value.clone();
+        match value {
+            NodeValue::Heading(heading) => {
+                stats.headings += 1;
+                let title = crate::links::heading_text(node);
+                sections.push(Section {
+                    id: anchorizer.anchorize(title.clone()),
+                    title: title.trim().to_string(),
+                    level: heading.level,
+                    line: node.data.borrow().sourcepos.start.line + line_offset,
+                    tally: Tally::default(),
+                });
+            }
+            NodeValue::Link(_) => stats.links += 1,
+            NodeValue::Image(_) => stats.images += 1,
+            NodeValue::CodeBlock(_) => stats.code_blocks += 1,
+            NodeValue::TaskItem(checked) => {
+                stats.tasks += 1;
+                if checked.is_some_and(|c| c != ' ') {
+                    stats.tasks_done += 1;
+                }
+            }
+            _ => {}
+        }
+
+        if matches!(value, NodeValue::Paragraph | NodeValue::Heading(_) | NodeValue::TableCell) {
+            let mut text = String::new();
+            block_text(node, &mut text);
+            // Wikilinks are plain text to comrak; count them and keep only the label
+            let text = wikilink_re().replace_all(&text, |caps: &regex::Captures| {
+                if caps[1].is_empty() {
+                    stats.links += 1;
+                    caps[2].rsplit('|').next().unwrap_or("").to_string()
+                } else {
+                    stats.images += 1;
+                    String::new()
+                }
+            });
+            total.add(&text);
+            sections.last_mut().unwrap().tally.add(&text);
+        }
+    }
+
+    stats.counts = total.counts();
+    stats.readability = total.readability();
+
+    // Drop the preamble when the document starts with a heading
+    if sections[0].tally.characters == 0 {
+        sections.remove(0);
+    }
+    stats.sections = sections
+        .iter()
+        .enumerate()
+        .map(|(i, section)| {
+            let nested: usize = sections[i + 1..]
+                .iter()
+                .take_while(|s| section.level > 0 && s.level > section.level)
+                .map(|s| s.tally.counts().words)
+                .sum();
+            let counts = section.tally.counts();
+            SectionStats {
+                id: section.id.clone(),
+                title: section.title.clone(),
+                level: section.level,
+                line: section.line,
+                counts,
+                total_words: counts.words + nested,
+            }
+        })
+        .collect();
+    stats
+}
+
+/// Word, character, element and readability statistics for a document.
+#[tauri::command]
+pub fn document_stats(content: String) -> DocumentStats {
+    compute(&content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_exclude_code_and_frontmatter() {
+        let doc = "---\ntitle: Lots of words here\n---\nHello `code span` world.\n\n```\nlet x = 1;\n```\n\n$$\nx^2\n$$\n\n![alt words](a.png) [link text](b.md) [[Note|the note]] ![[pic.png]]\n";
+        let stats = compute(doc);
+        assert_eq!(stats.counts.words, 6, "{:?}", stats);
+        assert_eq!(stats.links, 2);
+        assert_eq!(stats.images, 2);
+        assert_eq!(stats.code_blocks, 1);
+    }
+
+    #[test]
+    fn test_cjk_counted_by_character() {
+        let stats = compute("中文字数统计 and English\n");
+        assert_eq!(stats.counts.cjk_characters, 6);
+        assert_eq!(stats.counts.words, 8);
+        assert_eq!(stats.counts.reading_seconds, 2);
+    }
+
+    #[test]
+    fn test_tasks_and_readability() {
+        let stats = compute("- [x] done\n- [ ] open\n- [X] also done\n\nThe cat sat on the mat. The dog ran.\n");
+        assert_eq!((stats.tasks, stats.tasks_done), (3, 2));
+        let readability = stats.readability.unwrap();
+        assert_eq!(readability.sentences, 5);
+        assert!(readability.flesch_reading_ease > 90.0, "{:?}", readability);
+    }
+
+    #[test]
+    fn test_sections_follow_outline() {
+        let doc = "Intro words.\n\n# Title\n\nOne two.\n\n## Part `A`\n\nThree four five.\n\n## Part `A`\n\nSix.\n\n# Next\n";
+        let stats = compute(doc);
+        let outline: Vec<(&str, u8, usize, usize, usize)> = stats
+            .sections
+            .iter()
+            .map(|s| (s.id.as_str(), s.level, s.line, s.counts.words, s.total_words))
+            .collect();
+        assert_eq!(
+            outline,
+            vec![
+                ("", 0, 1, 2, 2),
+                ("title", 1, 3, 3, 9),
+                ("part-a", 2, 7, 4, 4),
+                ("part-a-1", 2, 11, 2, 2),
+                ("next", 1, 15, 1, 1),
+            ]
+        );
+        assert_eq!(stats.headings, 4);
+    }
+}
@@ -0,0 +1,236 @@
+//! Tags: inline `#tag` and nested `#parent/child` tags, plus frontmatter `tags`
+//!
+//! ```markdown
+//! ---
+//! tags: [reading, project/alpha]
+//! ---
+//! Notes for #project/alpha and #作业.
+//! ```
+//!
+//! A tag starts after whitespace or an opening bracket and must contain a
+//! letter, so `#1` and `a#b` are not tags. Tags are found in the text nodes of
+//! the comrak AST, which leaves out code, math, HTML and link text; headings
+//! are skipped too, so `#Title` written without a space is not picked up.
+//! Tags compare case-insensitively, and a nested tag also belongs to each of
+//! its parents.
+
+use comrak::arena_tree::Node;
+use comrak::nodes::{Ast, AstNode, LineColumn, NodeValue};
+use comrak::{parse_document, Arena, ComrakOptions};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    /// As written, without the `#`
+    pub name: String,
+    /// 1-based line in the document; `None` for frontmatter tags
+    pub line: Option<usize>,
+}
+
+fn tag_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?:^|[\s(\[])(#[\p{L}\p{M}\p{N}_/-]+)").unwrap())
+}
+
+/// `raw` without a leading `#` or trailing `/`, if it is a valid tag name.
+fn normalize(raw: &str) -> Option<String> {
+    let name = raw.trim().trim_start_matches('#').trim_end_matches('/');
+    let valid = !name.is_empty()
+        && !name.starts_with('/')
+        && !name.contains("//")
+        && !name.chars().any(char::is_whitespace)
+        && name.chars().any(char::is_alphabetic);
+    valid.then(|| name.to_string())
+}
+
+/// Lower-cased key used to compare tags.
+pub fn key(name: &str) -> String {
+    name.to_lowercase()
+}
+
+/// `name` and each of its parents: `a/b/c`, `a/b`, `a`.
+pub fn with_parents(name: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(name), |name| name.rfind('/').map(|i| &name[..i]))
+}
+
+/// Tags in a run of plain text, with the byte range of each `#tag`.
+pub fn find(text: &str) -> Vec<(Range<usize>, String)> {
+    tag_re()
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(1).unwrap();
+            let name = normalize(m.as_str())?;
+            // Keep a trailing `/` out of the link
+            Some((m.start()..m.start() + 1 + name.len(), name))
+        })
+        .collect()
+}
+
+/// Tags listed under `tags` or `tag` in frontmatter, as a list or a comma- or
+/// space-separated string.
+pub fn frontmatter_tags(metadata: &serde_yaml::Value) -> Vec<String> {
+    let mut names = Vec::new();
+    for field in ["tags", "tag"] {
+        match metadata.get(field) {
+            Some(serde_yaml::Value::Sequence(items)) => {
+                names.extend(items.iter().filter_map(|item| item.as_str()).filter_map(normalize));
+            }
+            Some(serde_yaml::Value::String(list)) => {
+                names.extend(list.split([',', ' ']).filter_map(normalize));
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+/// Whether text under `node` is prose that may carry tags.
+fn in_prose<'a>(node: &'a AstNode<'a>) -> bool {
+    !node.ancestors().any(|n| {
+        matches!(n.data.borrow().value, NodeValue::Heading(_) | NodeValue::Link(_) | NodeValue::Image(_))
+    })
+}
+
+/// Line breaks inside an inline node that is skipped.
+fn breaks_in<'a>(node: &'a AstNode<'a>) -> usize {
+    node.descendants()
+        .map(|n| match &n.data.borrow().value {
+            NodeValue::SoftBreak | NodeValue::LineBreak => 1,
+            NodeValue::HtmlInline(html) => html.matches('\n').count(),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn inline_tags<'a>(node: &'a AstNode<'a>, line: &mut usize, out: &mut Vec<Tag>) {
+    for child in node.children() {
+        match &child.data.borrow().value {
+            NodeValue::Text(text) => {
+                out.extend(find(text).into_iter().map(|(_, name)| Tag { name, line: Some(*line) }));
+            }
+            NodeValue::Link(_) | NodeValue::Image(_) | NodeValue::HtmlInline(_) => *line += breaks_in(child),
+            NodeValue::SoftBreak | NodeValue::LineBreak => *line += 1,
+            _ => inline_tags(child, line, out),
+        }
+    }
+}
+
+/// Frontmatter tags followed by inline tags, in document order.
+pub fn extract(content: &str) -> Vec<Tag> {
+    let (body, metadata) = crate::split_frontmatter(content);
+    let line_offset = content[..content.len() - body.len()].matches('\n').count();
+    let metadata: serde_yaml::Value = serde_yaml::from_str(&metadata).unwrap_or_default();
+    let mut tags: Vec<Tag> = frontmatter_tags(&metadata).into_iter().map(|name| Tag { name, line: None }).collect();
+
+    let mut options = ComrakOptions::default();
+    crate::configure_markdown_options(&mut options);
+    let arena = Arena::new();
+    let root = parse_document(&arena, body, &options);
+    for node in root.descendants() {
+        if matches!(node.data.borrow().value, NodeValue::Paragraph | NodeValue::TableCell) {
+            let mut line = node.data.borrow().sourcepos.start.line + line_offset;
+            inline_tags(node, &mut line, &mut tags);
+        }
+    }
+    tags
+}
+
+fn new_node<'a>(arena: &'a Arena<AstNode<'a>>, value: NodeValue, line: usize) -> &'a AstNode<'a> {
+    arena.alloc(Node::new(RefCell::new(Ast::new(value, LineColumn { line, column: 0 }))))
+}
+
+/// Turn inline tags under `root` into `<a class="tag">` links.
+///
+/// Expects adjacent text nodes to be merged (see `crossref::merge_adjacent_text`)
+/// and runs after cross-references, whose `{#label}` syntax it leaves alone.
+pub fn process<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let nodes: Vec<_> = root
+        .descendants()
+        .filter(|n| matches!(n.data.borrow().value, NodeValue::Text(_)) && in_prose(n))
+        .collect();
+    for node in nodes {
+        let text = match &node.data.borrow().value {
+            NodeValue::Text(text) => text.clone(),
+            _ => continue,
+        };
+        let found = find(&text);
+        if found.is_empty() {
+            continue;
+        }
+        let line = node.data.borrow().sourcepos.start.line;
+        let mut last = 0;
+        for (range, name) in found {
+            if range.start > last {
+                node.insert_before(new_node(arena, NodeValue::Text(text[last..range.start].to_string()), line));
+            }
+            let html = format!(
+                "<a class=\"tag\" href=\"#tag:{}\" data-tag=\"{}\">#{}</a>",
+                name, name, name
+            );
+            node.insert_before(new_node(arena, NodeValue::HtmlInline(html), line));
+            last = range.end;
+        }
+        if last < text.len() {
+            node.insert_before(new_node(arena, NodeValue::Text(text[last..].to_string()), line));
+        }
+        node.detach();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comrak::format_html;
+
+    #[test]
+    fn test_find_nested_tags() {
+        let names: Vec<String> =
+            find("#project/alpha, (#todo) a#b #1 #v2 #作业 #trailing/ ##x").into_iter().map(|(_, n)| n).collect();
+        assert_eq!(names, vec!["project/alpha", "todo", "v2", "作业", "trailing"]);
+        assert_eq!(with_parents("a/b/c").collect::<Vec<_>>(), vec!["a/b/c", "a/b", "a"]);
+    }
+
+    #[test]
+    fn test_extract_skips_code_headings_and_links() {
+        let doc = "---\ntags: [Reading, \"#project/beta\"]\n---\n# Title #heading\n\nSome #inline text\nand `#code` #second\n\n```\n#fenced\n```\n\n[see #link](http://x/#anchor) | x\n\n| a | b |\n|---|---|\n| #cell | 1 |\n";
+        let tags = extract(doc);
+        let found: Vec<(&str, Option<usize>)> = tags.iter().map(|t| (t.name.as_str(), t.line)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Reading", None),
+                ("project/beta", None),
+                ("inline", Some(6)),
+                ("second", Some(7)),
+                ("cell", Some(17)),
+            ]
+        );
+        let metadata: serde_yaml::Value = serde_yaml::from_str("tags: a, b/c d").unwrap();
+        assert_eq!(frontmatter_tags(&metadata), vec!["a", "b/c", "d"]);
+    }
+
+    #[test]
+    fn test_process_renders_tag_links() {
+        let mut options = ComrakOptions::default();
+        options.render.unsafe_ = true;
+        let arena = Arena::new();
+        let root = parse_document(&arena, "# No #tag\n\nSee #project/alpha and *#em*.\n", &options);
+        crate::crossref::merge_adjacent_text(root);
+        process(&arena, root);
+        let mut html = Vec::new();
+        format_html(root, &options, &mut html).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains("<h1>No #tag</h1>"), "{}", html);
+        assert!(
+            html.contains(r##"See <a class="tag" href="#tag:project/alpha" data-tag="project/alpha">#project/alpha</a> and"##),
+            "{}",
+            html
+        );
+        assert!(html.contains(r##"<em><a class="tag" href="#tag:em" data-tag="em">#em</a></em>"##), "{}", html);
+    }
+}
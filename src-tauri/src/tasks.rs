@@ -0,0 +1,222 @@
+//! Task list items: toggling from the preview and collecting them for overviews
+//!
+//! ```markdown
+//! - [ ] Draft the report 📅 2026-01-01 ⏫
+//! - [x] Book the room due:2025-12-20
+//! ```
+//!
+//! Items are found in the comrak AST, so checkboxes inside code blocks are
+//! never touched. Due dates are written `📅 YYYY-MM-DD` or `due:YYYY-MM-DD`;
+//! priorities use the emoji markers 🔺 ⏫ 🔼 🔽 ⏬, highest to lowest.
+
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena, ComrakOptions};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Highest,
+    High,
+    Medium,
+    Low,
+    Lowest,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    /// 1-based line in the document, frontmatter included
+    pub line: usize,
+    /// The character between the brackets: ` `, `x` or `X`
+    pub status: String,
+    /// Checked
+    pub done: bool,
+    /// The item's first line after the checkbox
+    pub text: String,
+    /// `YYYY-MM-DD`
+    pub due: Option<String>,
+    pub priority: Option<Priority>,
+}
+
+/// A list marker and checkbox, after any blockquote markers and indentation.
+fn checkbox_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^(?:[-*+]|\d+[.)])[ \t]+\[([^\]])\]").unwrap())
+}
+
+fn due_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?:📅|\bdue::?)\s*(\d{4}-\d{2}-\d{2})\b").unwrap())
+}
+
+fn priority(text: &str) -> Option<Priority> {
+    [
+        ('🔺', Priority::Highest),
+        ('⏫', Priority::High),
+        ('🔼', Priority::Medium),
+        ('🔽', Priority::Low),
+        ('⏬', Priority::Lowest),
+    ]
+    .into_iter()
+    .find(|(marker, _)| text.contains(*marker))
+    .map(|(_, priority)| priority)
+}
+
+/// Body lines and byte columns (both 1-based) where task items start.
+fn task_positions(body: &str) -> Vec<(usize, usize)> {
+    let mut options = ComrakOptions::default();
+    crate::configure_markdown_options(&mut options);
+    let arena = Arena::new();
+    let root = parse_document(&arena, body, &options);
+    root.descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::TaskItem(_)))
+        .map(|node| {
+            let start = node.data.borrow().sourcepos.start;
+            (start.line, start.column)
+        })
+        .collect()
+}
+
+/// All task items in `content`.
+pub fn extract(content: &str) -> Vec<Task> {
+    let (body, _metadata) = crate::split_frontmatter(content);
+    let line_offset = content[..content.len() - body.len()].matches('\n').count();
+    let lines: Vec<&str> = body.lines().collect();
+
+    task_positions(body)
+        .into_iter()
+        .filter_map(|(line, column)| {
+            let source = lines.get(line - 1)?;
+            let item = source.get(column.saturating_sub(1)..)?;
+            let caps = checkbox_re().captures(item)?;
+            let status = caps[1].to_string();
+            let text = item[caps.get(0).unwrap().end()..].trim().to_string();
+            Some(Task {
+                line: line + line_offset,
+                done: status != " ",
+                due: due_re().captures(&text).map(|c| c[1].to_string()),
+                priority: priority(&text),
+                status,
+                text,
+            })
+        })
+        .collect()
+}
+
+/// `content` with the task at body line `line` (as in `data-sourcepos`)
+/// checked or unchecked.
+///
+/// `checked` is the state the caller saw; if the file no longer matches, it
+/// changed underneath and nothing is toggled.
+pub fn toggle(content: &str, line: usize, checked: bool) -> Result<String, String> {
+    let (body, _metadata) = crate::split_frontmatter(content);
+    let body_start = content.len() - body.len();
+    let (_, column) = task_positions(body)
+        .into_iter()
+        .find(|(l, _)| *l == line)
+        .ok_or_else(|| format!("No task on line {}", line))?;
+
+    let line_start = body_start + body.split_inclusive('\n').take(line - 1).map(str::len).sum::<usize>();
+    let item_start = line_start + column - 1;
+    let caps = checkbox_re()
+        .captures(&content[item_start..])
+        .ok_or_else(|| format!("No checkbox on line {}", line))?;
+    let status = caps.get(1).unwrap();
+    if (status.as_str() != " ") != checked {
+        return Err("The task changed on disk; reload and try again".to_string());
+    }
+
+    let at = item_start + status.start();
+    let replacement = if checked { " " } else { "x" };
+    Ok(format!("{}{}{}", &content[..at], replacement, &content[at + status.len()..]))
+}
+
+/// Write `content` to `path` via a temporary file, so a failed write never
+/// leaves the note truncated.
+pub(crate) fn write_atomic(path: &Path, content: &str) -> Result<(), String> {
+    let name = path.file_name().ok_or("Invalid file path")?.to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.markpad-tmp", name));
+    fs::write(&tmp, content).map_err(|e| format!("Cannot write {}: {}", tmp.display(), e))?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        e.to_string()
+    })
+}
+
+/// Toggle the task whose list item starts at `line` (its `data-sourcepos`
+/// line) and save the file. Returns the new content. Given `content`, the
+/// unsaved buffer the preview was rendered from, the toggle applies to it
+/// instead and nothing is written.
+#[tauri::command]
+pub fn toggle_task(path: String, line: usize, checked: bool, content: Option<String>) -> Result<String, String> {
+    if let Some(content) = content {
+        return toggle(&content, line, checked);
+    }
+    let path = Path::new(&path);
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let updated = toggle(&content, line, checked)?;
+    write_atomic(path, &updated)?;
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    const DOC: &str = "---\ntitle: Tasks\n---\n- [ ] Draft 📅 2026-01-01 ⏫\n- [x] Booked due:2025-12-20\n  1. [X] Nested 🔽\n> - [ ] Quoted\n\n```\n- [ ] in code\n```\n\n- [] not a task\n";
+
+    #[test]
+    fn test_extract_tasks() {
+        let tasks = extract(DOC);
+        let found: Vec<_> = tasks
+            .iter()
+            .map(|t| (t.line, t.status.as_str(), t.done, t.text.as_str(), t.due.as_deref(), t.priority))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (4, " ", false, "Draft 📅 2026-01-01 ⏫", Some("2026-01-01"), Some(Priority::High)),
+                (5, "x", true, "Booked due:2025-12-20", Some("2025-12-20"), None),
+                (6, "X", true, "Nested 🔽", None, Some(Priority::Low)),
+                (7, " ", false, "Quoted", None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_toggle_by_sourcepos_line() {
+        let updated = toggle(DOC, 1, false).unwrap();
+        assert!(updated.contains("- [x] Draft"));
+        let updated = toggle(&updated, 2, true).unwrap();
+        assert!(updated.contains("- [ ] Booked"));
+        assert!(toggle(DOC, 4, false).unwrap().contains("> - [x] Quoted"));
+        assert!(toggle(DOC, 3, true).unwrap().contains("1. [ ] Nested"));
+
+        // Stale state, code blocks and non-tasks are refused
+        assert!(toggle(DOC, 1, true).is_err());
+        assert!(toggle(DOC, 7, false).is_err());
+        assert!(toggle(DOC, 10, false).is_err());
+        assert_eq!(toggle("- [ ] a\r\n- [ ] b\r\n", 2, false).unwrap(), "- [ ] a\r\n- [x] b\r\n");
+    }
+
+    #[test]
+    fn test_toggle_task_in_unsaved_buffer_leaves_file_alone() {
+        let dir = TempDir::new("tasks-buffer");
+        let path = dir.write("note.md", "- [ ] On disk\n");
+        let path = path.to_string_lossy().to_string();
+
+        let buffer = "# Added\n\n- [ ] Unsaved\n".to_string();
+        let updated = toggle_task(path.clone(), 3, false, Some(buffer)).unwrap();
+        assert_eq!(updated, "# Added\n\n- [x] Unsaved\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "- [ ] On disk\n");
+
+        assert_eq!(toggle_task(path.clone(), 1, false, None).unwrap(), "- [x] On disk\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "- [x] On disk\n");
+    }
+}
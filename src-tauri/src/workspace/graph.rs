@@ -0,0 +1,547 @@
+//! Link graph: backlinks, unlinked mentions and a node/edge export
+//!
+//! Links are stored unresolved in the search index and resolved here against
+//! the current set of notes, so a note created later picks up links written
+//! before it existed. Wikilinks resolve by file name anywhere in the
+//! workspace, preferring the linking note's folder; Markdown links resolve by
+//! relative path.
+
+use super::search::{phrase_count, tokenize, SearchIndex};
+use crate::links::{has_scheme, percent_decode, FoundLink, LinkKind};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Lines shown before and after a backlink
+const CONTEXT_LINES: usize = 1;
+
+/// Where a link points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Target {
+    /// Relative path of the note, or the note path it would have when missing
+    key: String,
+    exists: bool,
+    heading: Option<String>,
+}
+
+/// `a/b` joined with `../c` as a `/`-separated key.
+pub(super) fn join_key(dir: &str, target: &str) -> String {
+    let mut parts: Vec<&str> = if target.starts_with('/') { Vec::new() } else { dir.split('/').filter(|p| !p.is_empty()).collect() };
+    for part in target.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+pub(super) fn parent_key(key: &str) -> &str {
+    key.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+pub(super) fn has_markdown_extension(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    lower.ends_with(".md") || lower.ends_with(".markdown")
+}
+
+pub(super) fn stem(key: &str) -> &str {
+    let name = key.rsplit('/').next().unwrap_or(key);
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+/// `target` relative to the folder `dir`, both relative paths.
+pub(super) fn relative_key(dir: &str, target: &str) -> String {
+    let dir: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    let target: Vec<&str> = target.split('/').collect();
+    let common = dir.iter().zip(&target).take_while(|(a, b)| a == b).count().min(target.len() - 1);
+    let mut parts = vec![".."; dir.len() - common];
+    parts.extend(&target[common..]);
+    parts.join("/")
+}
+
+/// Files by path and by name, for resolving wikilinks.
+pub(crate) struct Files {
+    keys: BTreeSet<String>,
+    /// Lower-cased file names, and note stems, to relative paths
+    names: HashMap<String, Vec<String>>,
+}
+
+impl Files {
+    pub(crate) fn new(keys: BTreeSet<String>) -> Self {
+        let mut names: HashMap<String, Vec<String>> = HashMap::new();
+        for key in &keys {
+            let name = key.rsplit('/').next().unwrap_or(key).to_lowercase();
+            if has_markdown_extension(key) {
+                names.entry(stem(key).to_lowercase()).or_default().push(key.clone());
+            }
+            names.entry(name).or_default().push(key.clone());
+        }
+        Self { keys, names }
+    }
+
+    pub(super) fn contains(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// A file by name anywhere, preferring the folder `dir`, then shallow paths.
+    fn by_name(&self, dir: &str, name: &str) -> Option<&String> {
+        self.names
+            .get(&name.to_lowercase())?
+            .iter()
+            .min_by_key(|key| (parent_key(key) != dir, key.matches('/').count(), key.len()))
+    }
+}
+
+/// Whether a link target names its extension, so `.md` isn't implied.
+pub(super) fn has_extension(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| !e.is_empty() && e.len() <= 8 && !e.contains(' '))
+}
+
+/// How a wikilink name was resolved, and so how to write it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum WikiStyle {
+    /// A name found near the note or anywhere by file name
+    Bare,
+    /// A path from the workspace root
+    Root,
+    /// A path from the linking note's folder
+    Relative,
+}
+
+/// Resolve a wikilink or embed name, without `#heading`, from note `from`;
+/// embeds pass the image folder to also look in.
+pub(super) fn resolve_wiki(files: &Files, from: &str, name: &str, image_dir: Option<&str>) -> Option<(String, WikiStyle)> {
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let file = if has_extension(name) { name.to_string() } else { format!("{}.md", name) };
+    let dir = parent_key(from);
+    if name.contains('/') {
+        return [(join_key("", &file), WikiStyle::Root), (join_key(dir, &file), WikiStyle::Relative)]
+            .into_iter()
+            .find(|(key, _)| files.contains(key));
+    }
+    let mut near = vec![join_key(dir, &file)];
+    if let Some(image_dir) = image_dir {
+        near.push(join_key(dir, &format!("{}/{}", image_dir, file)));
+    }
+    near.into_iter()
+        .find(|key| files.contains(key))
+        .or_else(|| files.by_name(dir, name).cloned())
+        .map(|key| (key, WikiStyle::Bare))
+}
+
+struct Resolver {
+    /// The notes in the index
+    files: Files,
+}
+
+impl Resolver {
+    fn new(index: &SearchIndex) -> Self {
+        Self { files: Files::new(index.documents().keys().cloned().collect()) }
+    }
+
+    fn exists(&self, key: &str) -> bool {
+        self.files.contains(key)
+    }
+
+    /// A wikilink name, or the note it would be when missing: at the root for
+    /// a path, in the linking note's folder for a bare name.
+    fn resolve_name(&self, from: &str, name: &str) -> Target {
+        if let Some((key, _)) = resolve_wiki(&self.files, from, name, None) {
+            return Target { key, exists: true, heading: None };
+        }
+        let name = name.trim();
+        let file = if has_extension(name) { name.to_string() } else { format!("{}.md", name) };
+        let dir = if name.contains('/') { "" } else { parent_key(from) };
+        Target { key: join_key(dir, &file), exists: false, heading: None }
+    }
+
+    /// Resolve a link from note `from`; `None` for external links and non-note files.
+    fn resolve(&self, from: &str, link: &FoundLink) -> Option<Target> {
+        let (path, heading) = match link.target.split_once('#') {
+            Some((path, heading)) => (path, Some(heading.to_string()).filter(|h| !h.is_empty())),
+            None => (link.target.as_str(), None),
+        };
+        let mut target = match link.kind {
+            LinkKind::WikiLink | LinkKind::Embed => {
+                if path.is_empty() {
+                    Target { key: from.to_string(), exists: true, heading: None }
+                } else {
+                    // Embedded images and other files aren't notes
+                    let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("");
+                    if !extension.is_empty() && !has_markdown_extension(path) && extension.len() <= 4 {
+                        return None;
+                    }
+                    self.resolve_name(from, path)
+                }
+            }
+            LinkKind::Link | LinkKind::Image => {
+                if has_scheme(&link.target) {
+                    return None;
+                }
+                let path = path.split('?').next().unwrap_or("");
+                if path.is_empty() {
+                    Target { key: from.to_string(), exists: true, heading: None }
+                } else {
+                    let decoded = percent_decode(path);
+                    let key = join_key(parent_key(from), &decoded);
+                    let key = if has_markdown_extension(&key) {
+                        key
+                    } else if Path::new(&key).extension().is_none() {
+                        format!("{}.md", key)
+                    } else {
+                        return None;
+                    };
+                    let exists = self.exists(&key);
+                    Target { key, exists, heading: None }
+                }
+            }
+        };
+        target.heading = heading.map(|h| percent_decode(&h));
+        Some(target)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextLine {
+    pub line: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backlink {
+    pub path: String,
+    pub relative_path: String,
+    pub title: String,
+    pub line: usize,
+    pub column: usize,
+    pub kind: LinkKind,
+    /// Heading or `^block` the link points at, if any
+    pub heading: Option<String>,
+    pub context: Vec<ContextLine>,
+}
+
+fn context(lines: &[&str], line: usize) -> Vec<ContextLine> {
+    let first = line.saturating_sub(CONTEXT_LINES).max(1);
+    let last = (line + CONTEXT_LINES).min(lines.len());
+    (first..=last)
+        .map(|n| ContextLine { line: n, text: lines[n - 1].to_string() })
+        .collect()
+}
+
+/// Links from other notes to `key`, in path and line order.
+pub fn backlinks(index: &SearchIndex, root: &Path, key: &str) -> Vec<Backlink> {
+    let resolver = Resolver::new(index);
+    let mut found = Vec::new();
+    for (source, document) in index.documents() {
+        if source == key {
+            continue;
+        }
+        let links: Vec<(&FoundLink, Target)> = document
+            .links
+            .iter()
+            .filter_map(|link| resolver.resolve(source, link).map(|target| (link, target)))
+            .filter(|(_, target)| target.key == key)
+            .collect();
+        if links.is_empty() {
+            continue;
+        }
+        let path = root.join(source);
+        let text = fs::read_to_string(&path).unwrap_or_default();
+        let lines: Vec<&str> = text.lines().collect();
+        for (link, target) in links {
+            found.push(Backlink {
+                path: path.to_string_lossy().to_string(),
+                relative_path: source.clone(),
+                title: document.title.clone(),
+                line: link.line,
+                column: link.column,
+                kind: link.kind,
+                heading: target.heading,
+                context: context(&lines, link.line),
+            });
+        }
+    }
+    found
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mention {
+    pub path: String,
+    pub relative_path: String,
+    pub title: String,
+    pub line: usize,
+    /// UTF-16, 1-based
+    pub column: usize,
+    /// The mention as written
+    pub text: String,
+    pub context: String,
+}
+
+/// Links, code spans, HTML and URLs, where a name isn't an unlinked mention.
+fn linked_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"`[^`]*`|!?\[\[[^\]]*\]\]|!?\[[^\]]*\]\([^)]*\)|<[^>]*>|https?://\S+").unwrap()
+    })
+}
+
+/// Names a note goes by: its title, file name and frontmatter aliases.
+fn note_names(index: &SearchIndex, key: &str) -> Vec<String> {
+    let Some(document) = index.documents().get(key) else { return Vec::new() };
+    let mut names = vec![document.title.clone(), stem(key).to_string()];
+    for field in ["aliases", "alias"] {
+        names.extend(document.fields.get(field).into_iter().flatten().cloned());
+    }
+    let mut seen = BTreeSet::new();
+    names.retain(|name| name.chars().count() > 1 && seen.insert(name.to_lowercase()));
+    names
+}
+
+/// Plain-text occurrences of the note's names in other notes.
+pub fn unlinked_mentions(index: &SearchIndex, root: &Path, key: &str) -> Vec<Mention> {
+    let names = note_names(index, key);
+    let patterns: Vec<(Vec<String>, Regex)> = names
+        .iter()
+        .filter_map(|name| {
+            let tokens: Vec<String> = tokenize(name).collect();
+            let escaped = regex::escape(name.trim());
+            // Word boundaries only make sense around word characters
+            let start = if name.starts_with(|c: char| c.is_alphanumeric() && c.is_ascii()) { r"\b" } else { "" };
+            let end = if name.ends_with(|c: char| c.is_alphanumeric() && c.is_ascii()) { r"\b" } else { "" };
+            let re = Regex::new(&format!("(?i){}{}{}", start, escaped, end)).ok()?;
+            (!tokens.is_empty()).then_some((tokens, re))
+        })
+        .collect();
+
+    let mut mentions = Vec::new();
+    for (source, document) in index.documents() {
+        if source == key {
+            continue;
+        }
+        let candidates: Vec<&Regex> = patterns
+            .iter()
+            .filter(|(tokens, _)| phrase_count(document, tokens) > 0)
+            .map(|(_, re)| re)
+            .collect();
+        if candidates.is_empty() {
+            continue;
+        }
+        let path = root.join(source);
+        let Ok(text) = fs::read_to_string(&path) else { continue };
+        let (body, _metadata) = crate::split_frontmatter(&text);
+        let line_offset = text[..text.len() - body.len()].matches('\n').count();
+
+        let mut in_fence = false;
+        for (index, line) in body.lines().enumerate() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+                continue;
+            }
+            if in_fence {
+                continue;
+            }
+            let linked: Vec<(usize, usize)> = linked_re().find_iter(line).map(|m| (m.start(), m.end())).collect();
+            let mut taken: Vec<(usize, usize)> = Vec::new();
+            for re in &candidates {
+                for m in re.find_iter(line) {
+                    let overlaps = |(s, e): &(usize, usize)| m.start() < *e && *s < m.end();
+                    if linked.iter().any(overlaps) || taken.iter().any(overlaps) {
+                        continue;
+                    }
+                    taken.push((m.start(), m.end()));
+                    mentions.push(Mention {
+                        path: path.to_string_lossy().to_string(),
+                        relative_path: source.clone(),
+                        title: document.title.clone(),
+                        line: index + 1 + line_offset,
+                        column: line[..m.start()].encode_utf16().count() + 1,
+                        text: m.as_str().to_string(),
+                        context: line.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    mentions.sort_by(|a, b| (&a.relative_path, a.line, a.column).cmp(&(&b.relative_path, b.line, b.column)));
+    mentions
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+    /// Relative path of the note
+    pub id: String,
+    pub title: String,
+    /// False for link targets with no note yet
+    pub exists: bool,
+    pub outgoing: usize,
+    pub incoming: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    /// Links from source to target
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Every note as a node and every linked pair as an edge; self-links are left out.
+pub fn graph(index: &SearchIndex) -> Graph {
+    let resolver = Resolver::new(index);
+    let mut nodes: BTreeMap<String, GraphNode> = index
+        .documents()
+        .iter()
+        .map(|(key, document)| {
+            let node = GraphNode { id: key.clone(), title: document.title.clone(), exists: true, outgoing: 0, incoming: 0 };
+            (key.clone(), node)
+        })
+        .collect();
+    let mut edges: BTreeMap<(String, String), usize> = BTreeMap::new();
+    for (source, document) in index.documents() {
+        for link in &document.links {
+            let Some(target) = resolver.resolve(source, link) else { continue };
+            if &target.key == source {
+                continue;
+            }
+            *edges.entry((source.clone(), target.key.clone())).or_default() += 1;
+            if !target.exists {
+                nodes.entry(target.key.clone()).or_insert_with(|| GraphNode {
+                    title: stem(&target.key).to_string(),
+                    id: target.key.clone(),
+                    exists: false,
+                    outgoing: 0,
+                    incoming: 0,
+                });
+            }
+        }
+    }
+    for (source, target) in edges.keys() {
+        if let Some(node) = nodes.get_mut(source) {
+            node.outgoing += 1;
+        }
+        if let Some(node) = nodes.get_mut(target) {
+            node.incoming += 1;
+        }
+    }
+    Graph {
+        nodes: nodes.into_values().collect(),
+        edges: edges
+            .into_iter()
+            .map(|((source, target), count)| GraphEdge { source, target, count })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn workspace(name: &str, files: &[(&str, &str)]) -> (TempDir, SearchIndex) {
+        let root = TempDir::with_files(&format!("graph-{}", name), files);
+        let mut index = SearchIndex::default();
+        index.sync(&root);
+        (root, index)
+    }
+
+    const FILES: &[(&str, &str)] = &[
+        ("Target Note.md", "---\naliases: [TN]\n---\n# Target Note\n\n## Setup\n"),
+        ("a.md", "Intro\nSee [[Target Note#Setup]] here.\nOutro\n"),
+        ("sub/b.md", "[link](../Target%20Note.md) and ![[Target Note]] and [[Missing]]\n"),
+        ("sub/c.md", "Mentions target note and TN, but not `Target Note` or [[Target Note|alias]].\n"),
+        ("d.md", "![img](pic.png) [web](https://example.com) [self](#top)\n"),
+    ];
+
+    #[test]
+    fn test_shared_path_and_wikilink_helpers() {
+        assert_eq!(relative_key("a/b", "a/c/note.md"), "../c/note.md");
+        assert_eq!(relative_key("", "a/note.md"), "a/note.md");
+        assert_eq!(relative_key("a/note.md", "a/note.md"), "../note.md");
+
+        let files = Files::new(
+            ["note.md", "deep/x/note.md", "sub/note.md", "sub/img/pic.png", "sub/a.md"].into_iter().map(String::from).collect(),
+        );
+        let resolve = |from, name, image_dir| resolve_wiki(&files, from, name, image_dir);
+        assert_eq!(resolve("sub/a.md", "Note", None), Some(("sub/note.md".to_string(), WikiStyle::Bare)));
+        assert_eq!(resolve("other/a.md", "note", None), Some(("note.md".to_string(), WikiStyle::Bare)));
+        assert_eq!(resolve("sub/a.md", "deep/x/note", None), Some(("deep/x/note.md".to_string(), WikiStyle::Root)));
+        assert_eq!(resolve("sub/a.md", "img/pic.png", None), Some(("sub/img/pic.png".to_string(), WikiStyle::Relative)));
+        assert_eq!(resolve("sub/a.md", "pic.png", Some("img")), Some(("sub/img/pic.png".to_string(), WikiStyle::Bare)));
+        assert_eq!(resolve("sub/a.md", "missing", None), None);
+    }
+
+    #[test]
+    fn test_backlinks_with_context() {
+        let (root, index) = workspace("backlinks", FILES);
+        let found = backlinks(&index, &root, "Target Note.md");
+        let summary: Vec<(&str, usize, LinkKind, Option<&str>)> = found
+            .iter()
+            .map(|b| (b.relative_path.as_str(), b.line, b.kind, b.heading.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a.md", 2, LinkKind::WikiLink, Some("Setup")),
+                ("sub/b.md", 1, LinkKind::Link, None),
+                ("sub/b.md", 1, LinkKind::Embed, None),
+                ("sub/c.md", 1, LinkKind::WikiLink, None),
+            ]
+        );
+        let lines: Vec<usize> = found[0].context.iter().map(|c| c.line).collect();
+        assert_eq!(lines, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_unlinked_mentions() {
+        let (root, index) = workspace("mentions", FILES);
+        let mentions = unlinked_mentions(&index, &root, "Target Note.md");
+        let found: Vec<(&str, &str)> = mentions.iter().map(|m| (m.relative_path.as_str(), m.text.as_str())).collect();
+        assert_eq!(found, vec![("sub/c.md", "target note"), ("sub/c.md", "TN")]);
+        assert_eq!(mentions[0].column, 10);
+    }
+
+    #[test]
+    fn test_graph_export() {
+        let (_root, index) = workspace("graph", FILES);
+        let graph = graph(&index);
+        let edges: Vec<(&str, &str, usize)> = graph.edges.iter().map(|e| (e.source.as_str(), e.target.as_str(), e.count)).collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("a.md", "Target Note.md", 1),
+                ("sub/b.md", "Target Note.md", 2),
+                ("sub/b.md", "sub/Missing.md", 1),
+                ("sub/c.md", "Target Note.md", 1),
+            ]
+        );
+        let missing = graph.nodes.iter().find(|n| n.id == "sub/Missing.md").unwrap();
+        assert!(!missing.exists);
+        let target = graph.nodes.iter().find(|n| n.id == "Target Note.md").unwrap();
+        assert_eq!((target.incoming, target.outgoing), (3, 0));
+    }
+}
@@ -0,0 +1,399 @@
+//! Workspace: a folder of Markdown notes opened as a whole
+//!
+//! Opening a workspace loads its persisted search index from the app data
+//! directory, reconciles it with the files on disk and keeps it current with a
+//! recursive `notify` watcher. File events are batched, applied to the index
+//! on a background thread and announced with a `workspace-index-updated` event
+//! carrying the changed relative paths. Backlinks, the link graph, tags,
+//! tasks and metadata queries are derived from the same index, so they stay
+//! fresh with it. The watcher also maintains the file list for quick open
+//! and link-fixing renames.
+
+pub mod graph;
+pub mod query;
+pub mod quick_open;
+pub mod refactor;
+pub mod search;
+pub mod tags;
+pub mod tasks;
+
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use quick_open::{FileList, QuickOpenItem};
+use refactor::{RenameOptions, RenamePlan};
+use search::{Query, SearchHit, SearchIndex};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Quiet period that ends a batch of file events
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+pub fn is_markdown(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref(),
+        Some("md" | "markdown")
+    )
+}
+
+/// Folders never indexed or listed
+fn is_skipped_dir(name: &str) -> bool {
+    name.starts_with('.') || name == "node_modules"
+}
+
+/// Whether `path` lies in a skipped folder below `root`.
+pub fn is_hidden(root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root)
+        .map(|relative| {
+            let mut components: Vec<_> = relative.components().collect();
+            // The file itself may be a dotfile without being in a hidden folder
+            components.pop();
+            components.iter().any(|c| is_skipped_dir(&c.as_os_str().to_string_lossy()))
+        })
+        .unwrap_or(true)
+}
+
+/// Markdown files under `dir`, skipping hidden folders and `node_modules`.
+pub fn markdown_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if !is_skipped_dir(&entry.file_name().to_string_lossy()) {
+                    stack.push(path);
+                }
+            } else if is_markdown(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// `path` relative to `root`, `/`-separated on every platform.
+pub fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub struct Workspace {
+    pub root: PathBuf,
+    pub index: Arc<Mutex<SearchIndex>>,
+    /// All files, for quick open and renames
+    pub files: Arc<Mutex<FileList>>,
+    /// Applied renames, most recent last, for undo
+    pub renames: Mutex<Vec<RenamePlan>>,
+    /// Dropping the watcher stops the update thread
+    _watcher: RecommendedWatcher,
+}
+
+impl Workspace {
+    /// Open `root`, syncing the index persisted at `index_path` and watching
+    /// for changes. `on_update` receives the relative paths of each applied batch.
+    pub fn open(
+        root: PathBuf,
+        index_path: PathBuf,
+        on_update: impl Fn(Vec<String>) + Send + 'static,
+    ) -> Result<Self, String> {
+        if !root.is_dir() {
+            return Err(format!("{} is not a folder", root.display()));
+        }
+        let mut index = SearchIndex::load(&index_path);
+        if !index.sync(&root).is_empty() {
+            if let Err(e) = index.save(&index_path) {
+                log::warn!("[workspace] Cannot save search index: {}", e);
+            }
+        }
+        let index = Arc::new(Mutex::new(index));
+        let files = Arc::new(Mutex::new(FileList::scan(&root)));
+
+        let (sender, receiver) = mpsc::channel::<PathBuf>();
+        let mut watcher = RecommendedWatcher::new(
+            move |res: Result<notify::Event, notify::Error>| {
+                if let Ok(event) = res {
+                    for path in event.paths {
+                        let _ = sender.send(path);
+                    }
+                }
+            },
+            Config::default(),
+        )
+        .map_err(|e| e.to_string())?;
+        watcher.watch(&root, RecursiveMode::Recursive).map_err(|e| e.to_string())?;
+
+        let thread_root = root.clone();
+        let thread_index = index.clone();
+        let thread_files = files.clone();
+        std::thread::spawn(move || {
+            // Ends when the watcher, and with it the sender, is dropped
+            while let Ok(first) = receiver.recv() {
+                let mut paths = BTreeSet::from([first]);
+                while let Ok(path) = receiver.recv_timeout(DEBOUNCE) {
+                    paths.insert(path);
+                }
+                let changed: Vec<String> = {
+                    let mut index = thread_index.lock().unwrap();
+                    let changed: Vec<String> = paths.iter().flat_map(|path| index.refresh(&thread_root, path)).collect();
+                    if !changed.is_empty() {
+                        if let Err(e) = index.save(&index_path) {
+                            log::warn!("[workspace] Cannot save search index: {}", e);
+                        }
+                    }
+                    changed
+                };
+                {
+                    let mut files = thread_files.lock().unwrap();
+                    for path in &paths {
+                        files.update(&thread_root, path);
+                    }
+                }
+                if !changed.is_empty() {
+                    on_update(changed);
+                }
+            }
+        });
+
+        Ok(Self { root, index, files, renames: Mutex::new(Vec::new()), _watcher: watcher })
+    }
+}
+
+/// The open workspace, if any.
+#[derive(Default)]
+pub struct WorkspaceState {
+    workspace: Mutex<Option<Workspace>>,
+}
+
+impl WorkspaceState {
+    /// Run `f` against the open workspace.
+    pub fn with<T>(&self, f: impl FnOnce(&Workspace) -> Result<T, String>) -> Result<T, String> {
+        let workspace = self.workspace.lock().unwrap();
+        f(workspace.as_ref().ok_or("No workspace is open")?)
+    }
+}
+
+/// Where the index for `root` is persisted: one file per workspace, named by
+/// a hash of its path.
+fn index_path(app: &AppHandle, root: &Path) -> Result<PathBuf, String> {
+    let hash = Sha256::digest(root.to_string_lossy().as_bytes());
+    let name: String = hash.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("search-index")
+        .join(format!("{}.json", name)))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceInfo {
+    pub root: String,
+    pub documents: usize,
+}
+
+/// Open a folder as the workspace, replacing any open one.
+#[tauri::command]
+pub async fn workspace_open(app: AppHandle, state: State<'_, WorkspaceState>, root: String) -> Result<WorkspaceInfo, String> {
+    let root = PathBuf::from(&root);
+    let index_path = index_path(&app, &root)?;
+    let workspace = tauri::async_runtime::spawn_blocking(move || {
+        Workspace::open(root, index_path, move |changed| {
+            let _ = app.emit("workspace-index-updated", changed);
+        })
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()))?;
+
+    let info = WorkspaceInfo {
+        root: workspace.root.to_string_lossy().to_string(),
+        documents: workspace.index.lock().unwrap().len(),
+    };
+    *state.workspace.lock().unwrap() = Some(workspace);
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn workspace_close(state: State<'_, WorkspaceState>) {
+    *state.workspace.lock().unwrap() = None;
+}
+
+/// Search the open workspace; see `search` for the query syntax.
+#[tauri::command]
+pub fn workspace_search(state: State<'_, WorkspaceState>, query: String, limit: Option<usize>) -> Result<Vec<SearchHit>, String> {
+    let query = Query::parse(&query)?;
+    state.with(|workspace| {
+        let index = workspace.index.lock().unwrap();
+        Ok(index.search(&workspace.root, &query, limit.unwrap_or(search::DEFAULT_LIMIT)))
+    })
+}
+
+/// Notes linking to `path`, with context lines.
+#[tauri::command]
+pub fn workspace_backlinks(state: State<'_, WorkspaceState>, path: String) -> Result<Vec<graph::Backlink>, String> {
+    state.with(|workspace| {
+        let key = relative_path(&workspace.root, Path::new(&path));
+        Ok(graph::backlinks(&workspace.index.lock().unwrap(), &workspace.root, &key))
+    })
+}
+
+/// Unlinked mentions of the note at `path` by title, file name or alias.
+#[tauri::command]
+pub fn workspace_unlinked_mentions(state: State<'_, WorkspaceState>, path: String) -> Result<Vec<graph::Mention>, String> {
+    state.with(|workspace| {
+        let key = relative_path(&workspace.root, Path::new(&path));
+        Ok(graph::unlinked_mentions(&workspace.index.lock().unwrap(), &workspace.root, &key))
+    })
+}
+
+/// Nodes and edges of the workspace link graph.
+#[tauri::command]
+pub fn workspace_graph(state: State<'_, WorkspaceState>) -> Result<graph::Graph, String> {
+    state.with(|workspace| Ok(graph::graph(&workspace.index.lock().unwrap())))
+}
+
+/// Every tag in the workspace with note counts.
+#[tauri::command]
+pub fn workspace_tags(state: State<'_, WorkspaceState>) -> Result<Vec<tags::TagCount>, String> {
+    state.with(|workspace| Ok(tags::tag_counts(&workspace.index.lock().unwrap())))
+}
+
+/// Notes carrying `tag` or a tag nested below it.
+#[tauri::command]
+pub fn workspace_notes_for_tag(state: State<'_, WorkspaceState>, tag: String) -> Result<Vec<tags::TaggedNote>, String> {
+    state.with(|workspace| Ok(tags::notes_for_tag(&workspace.index.lock().unwrap(), &workspace.root, &tag)))
+}
+
+/// Task items across the workspace; `done` filters by status.
+#[tauri::command]
+pub fn workspace_tasks(state: State<'_, WorkspaceState>, done: Option<bool>) -> Result<Vec<tasks::WorkspaceTask>, String> {
+    state.with(|workspace| Ok(tasks::all_tasks(&workspace.index.lock().unwrap(), &workspace.root, done)))
+}
+
+/// Run a ```` ```query ```` block from the note at `path`, linking results
+/// relative to it.
+#[tauri::command]
+pub fn workspace_query(state: State<'_, WorkspaceState>, query: String, path: Option<String>) -> Result<query::QueryResult, String> {
+    let parsed = query::MetadataQuery::parse(&query)?;
+    let (markdown, files) = state.with(|workspace| {
+        let from = path.map(|path| relative_path(&workspace.root, Path::new(&path)));
+        Ok(query::run(&workspace.index.lock().unwrap(), &workspace.root, &parsed, from.as_deref()))
+    })?;
+    let html = crate::convert_markdown(&markdown);
+    Ok(query::QueryResult { markdown, html, files })
+}
+
+/// Fuzzy-find files and headings for the quick-open palette; `recent` lists
+/// recently opened paths, most recent first.
+#[tauri::command]
+pub fn workspace_quick_open(
+    state: State<'_, WorkspaceState>,
+    query: String,
+    recent: Option<Vec<String>>,
+    limit: Option<usize>,
+) -> Result<Vec<QuickOpenItem>, String> {
+    state.with(|workspace| {
+        let recent: Vec<String> =
+            recent.unwrap_or_default().iter().map(|path| relative_path(&workspace.root, Path::new(path))).collect();
+        let mut files = workspace.files.lock().unwrap();
+        files.refresh(&workspace.root);
+        let index = workspace.index.lock().unwrap();
+        Ok(quick_open::quick_open(
+            &files,
+            &index,
+            &workspace.root,
+            &query,
+            &recent,
+            limit.unwrap_or(quick_open::DEFAULT_LIMIT),
+        ))
+    })
+}
+
+fn plan_rename(workspace: &Workspace, from: &str, to: &str, options: &RenameOptions) -> Result<RenamePlan, String> {
+    let mut files = workspace.files.lock().unwrap();
+    files.refresh(&workspace.root);
+    let index = workspace.index.lock().unwrap();
+    refactor::plan(&index, &files, &workspace.root, Path::new(from), Path::new(to), options)
+}
+
+/// The moves and link edits renaming `from` to `to` would make.
+#[tauri::command]
+pub fn workspace_rename_preview(
+    state: State<'_, WorkspaceState>,
+    from: String,
+    to: String,
+    options: Option<RenameOptions>,
+) -> Result<RenamePlan, String> {
+    state.with(|workspace| plan_rename(workspace, &from, &to, &options.unwrap_or_default()))
+}
+
+/// Rename or move a note, image or folder and fix the links to it across the
+/// workspace, as one batch for `workspace_undo_rename`.
+#[tauri::command]
+pub fn workspace_rename(
+    state: State<'_, WorkspaceState>,
+    from: String,
+    to: String,
+    options: Option<RenameOptions>,
+) -> Result<RenamePlan, String> {
+    let options = options.unwrap_or_default();
+    state.with(|workspace| {
+        let plan = plan_rename(workspace, &from, &to, &options)?;
+        refactor::apply(&plan, &options)?;
+        workspace.renames.lock().unwrap().push(plan.clone());
+        Ok(plan)
+    })
+}
+
+/// Undo the latest rename; `None` when there is nothing to undo.
+#[tauri::command]
+pub fn workspace_undo_rename(state: State<'_, WorkspaceState>) -> Result<Option<RenamePlan>, String> {
+    state.with(|workspace| {
+        let mut renames = workspace.renames.lock().unwrap();
+        let Some(plan) = renames.last() else { return Ok(None) };
+        refactor::undo(&workspace.root, plan)?;
+        Ok(renames.pop())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_watcher_updates_index() {
+        let root = TempDir::with_files("workspace", &[("a.md", "alpha\n")]);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        let index_path = root.join(".index/index.json");
+
+        let (sender, receiver) = mpsc::channel();
+        let workspace = Workspace::open(root.to_path_buf(), index_path.clone(), move |changed| {
+            let _ = sender.send(changed);
+        })
+        .unwrap();
+        assert_eq!(workspace.index.lock().unwrap().len(), 1);
+        assert!(index_path.is_file());
+
+        fs::write(root.join("sub/b.md"), "beta\n").unwrap();
+        let changed = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(changed.contains(&"sub/b.md".to_string()), "{:?}", changed);
+        let query = Query::parse("beta").unwrap();
+        let hits = workspace.index.lock().unwrap().search(&root, &query, 10);
+        assert_eq!(hits.len(), 1);
+
+        drop(workspace);
+        assert_eq!(SearchIndex::load(&index_path).len(), 2);
+    }
+}
@@ -0,0 +1,681 @@
+//! Metadata queries for ```` ```query ```` blocks
+//!
+//! ```text
+//! TABLE status, owner AS "Owner" FROM "projects" and -#archived
+//! WHERE status != "done" and contains(tags, "client")
+//! SORT due ASC, file.name
+//! LIMIT 20
+//! ```
+//!
+//! `TABLE [WITHOUT ID] columns` or `LIST [expression]`, then optional `FROM`,
+//! `WHERE`, `SORT` and `LIMIT` clauses. Sources are folders (`"projects"`)
+//! and tags (`#project`, nested tags included), combined with `and`, `or`,
+//! `-` and parentheses. Expressions read frontmatter fields, with keys
+//! matched case-insensitively, and the implicit `file.name`, `file.path`,
+//! `file.folder`, `file.title` and `file.tags`; they compare with `=`, `!=`,
+//! `<`, `<=`, `>`, `>=`, combine with `and`, `or`, `not` and call
+//! `contains(list or text, value)`. Dates are compared as quoted
+//! `"YYYY-MM-DD"` strings. Missing fields are null: unequal to everything,
+//! never ordered, and sorted last.
+//!
+//! Results are Markdown, linking each note relative to the querying note.
+
+use super::graph::{parent_key, relative_key};
+use super::search::{IndexedDocument, SearchIndex};
+use crate::tags;
+use serde::Serialize;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(f64),
+    Tag(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: &[&str] = &["!=", "<=", ">=", "=", "<", ">", "(", ")", ",", "-", "!"];
+
+const KEYWORDS: &[&str] =
+    &["TABLE", "LIST", "WITHOUT", "FROM", "WHERE", "SORT", "LIMIT", "AND", "OR", "NOT", "AS", "ASC", "DESC"];
+
+fn tokenize(query: &str) -> Result<Vec<(Token, usize, usize)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = if c == '"' || c == '\'' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '\\')) => text.extend(chars.next().map(|(_, c)| c)),
+                    Some((_, q)) if q == c => break,
+                    Some((_, other)) => text.push(other),
+                    None => return Err(format!("Unterminated string at column {}", start + 1)),
+                }
+            }
+            Token::Str(text)
+        } else if c.is_ascii_digit() {
+            let mut text = String::new();
+            while let Some(&(_, d)) = chars.peek() {
+                if !(d.is_ascii_digit() || d == '.') {
+                    break;
+                }
+                text.push(d);
+                chars.next();
+            }
+            Token::Number(text.parse().map_err(|_| format!("Invalid number `{}`", text))?)
+        } else if c == '#' {
+            chars.next();
+            let mut text = String::new();
+            while let Some(&(_, d)) = chars.peek() {
+                if !(d.is_alphanumeric() || matches!(d, '_' | '/' | '-')) {
+                    break;
+                }
+                text.push(d);
+                chars.next();
+            }
+            Token::Tag(text)
+        } else if c.is_alphabetic() || c == '_' {
+            let mut text = String::new();
+            while let Some(&(_, d)) = chars.peek() {
+                if !(d.is_alphanumeric() || matches!(d, '_' | '.' | '-')) {
+                    break;
+                }
+                text.push(d);
+                chars.next();
+            }
+            Token::Ident(text)
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|s| query[start..].starts_with(**s))
+                .ok_or_else(|| format!("Unexpected `{}` at column {}", c, start + 1))?;
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            Token::Symbol(symbol)
+        };
+        let end = chars.peek().map_or(query.len(), |&(i, _)| i);
+        tokens.push((token, start, end));
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Field(String),
+    Literal(Value),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
+    Contains(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    Folder(String),
+    Tag(String),
+    Not(Box<Source>),
+    And(Box<Source>, Box<Source>),
+    Or(Box<Source>, Box<Source>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Column {
+    expr: Expr,
+    label: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Output {
+    Table { columns: Vec<Column>, without_id: bool },
+    List(Option<Expr>),
+}
+
+/// A parsed query block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataQuery {
+    output: Output,
+    source: Option<Source>,
+    filters: Vec<Expr>,
+    /// Expressions with `true` for descending
+    sort: Vec<(Expr, bool)>,
+    limit: Option<usize>,
+}
+
+struct Parser<'q> {
+    query: &'q str,
+    tokens: Vec<(Token, usize, usize)>,
+    position: usize,
+}
+
+impl<'q> Parser<'q> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).map(|(token, _, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens.get(self.position).map_or(self.query.len(), |&(_, start, _)| start)
+    }
+
+    fn unexpected(&self) -> String {
+        match self.tokens.get(self.position) {
+            Some((_, start, end)) => format!("Unexpected `{}` at column {}", &self.query[*start..*end], start + 1),
+            None => "Unexpected end of query".to_string(),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(format!("Expected `{}`: {}", symbol, self.unexpected()))
+        }
+    }
+
+    /// Whether the next token starts a clause rather than an expression.
+    fn at_clause(&self) -> bool {
+        self.peek().is_none() || ["FROM", "WHERE", "SORT", "LIMIT"].iter().any(|k| self.is_keyword(k))
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.eat_keyword("OR") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.not()?;
+        while self.eat_keyword("AND") {
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat_keyword("NOT") || self.eat_symbol("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        let left = self.primary()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("=")) => CompareOp::Eq,
+            Some(Token::Symbol("!=")) => CompareOp::Ne,
+            Some(Token::Symbol("<")) => CompareOp::Lt,
+            Some(Token::Symbol("<=")) => CompareOp::Le,
+            Some(Token::Symbol(">")) => CompareOp::Gt,
+            Some(Token::Symbol(">=")) => CompareOp::Ge,
+            _ => return Ok(left),
+        };
+        self.position += 1;
+        Ok(Expr::Compare(Box::new(left), op, Box::new(self.primary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let unexpected = self.unexpected();
+        match self.next() {
+            Some(Token::Str(text)) => Ok(Expr::Literal(Value::String(text))),
+            Some(Token::Number(n)) => Ok(Expr::Literal(Value::from(n))),
+            Some(Token::Symbol("-")) => match self.next() {
+                Some(Token::Number(n)) => Ok(Expr::Literal(Value::from(-n))),
+                _ => Err(unexpected),
+            },
+            Some(Token::Symbol("(")) => {
+                let expr = self.expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(word)) => match word.to_ascii_uppercase().as_str() {
+                "TRUE" => Ok(Expr::Literal(Value::Bool(true))),
+                "FALSE" => Ok(Expr::Literal(Value::Bool(false))),
+                "NULL" => Ok(Expr::Literal(Value::Null)),
+                "CONTAINS" if self.eat_symbol("(") => {
+                    let haystack = self.expr()?;
+                    self.expect_symbol(",")?;
+                    let needle = self.expr()?;
+                    self.expect_symbol(")")?;
+                    Ok(Expr::Contains(Box::new(haystack), Box::new(needle)))
+                }
+                upper if KEYWORDS.contains(&upper) => Err(unexpected),
+                _ if matches!(self.peek(), Some(Token::Symbol("("))) => Err(format!("Unknown function `{}`", word)),
+                _ => Ok(Expr::Field(word)),
+            },
+            _ => Err(unexpected),
+        }
+    }
+
+    fn source(&mut self) -> Result<Source, String> {
+        let mut left = self.source_and()?;
+        while self.eat_keyword("OR") {
+            left = Source::Or(Box::new(left), Box::new(self.source_and()?));
+        }
+        Ok(left)
+    }
+
+    fn source_and(&mut self) -> Result<Source, String> {
+        let mut left = self.source_term()?;
+        while self.eat_keyword("AND") {
+            left = Source::And(Box::new(left), Box::new(self.source_term()?));
+        }
+        Ok(left)
+    }
+
+    fn source_term(&mut self) -> Result<Source, String> {
+        let unexpected = self.unexpected();
+        match self.next() {
+            Some(Token::Str(folder)) => Ok(Source::Folder(folder.trim_matches('/').to_string())),
+            Some(Token::Tag(tag)) => Ok(Source::Tag(tags::key(tag.trim_end_matches('/')))),
+            Some(Token::Symbol("-" | "!")) => Ok(Source::Not(Box::new(self.source_term()?))),
+            Some(Token::Symbol("(")) => {
+                let source = self.source()?;
+                self.expect_symbol(")")?;
+                Ok(source)
+            }
+            _ => Err(unexpected),
+        }
+    }
+
+    fn columns(&mut self) -> Result<Vec<Column>, String> {
+        let mut columns = Vec::new();
+        if self.at_clause() {
+            return Ok(columns);
+        }
+        loop {
+            let start = self.offset();
+            let expr = self.expr()?;
+            let end = self.tokens.get(self.position - 1).map_or(self.query.len(), |&(_, _, end)| end);
+            let label = if self.eat_keyword("AS") {
+                match self.next() {
+                    Some(Token::Str(label) | Token::Ident(label)) => label,
+                    _ => return Err("Expected a column name after AS".to_string()),
+                }
+            } else {
+                self.query[start..end].trim().to_string()
+            };
+            columns.push(Column { expr, label });
+            if !self.eat_symbol(",") {
+                return Ok(columns);
+            }
+        }
+    }
+}
+
+impl MetadataQuery {
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut parser = Parser { query, tokens: tokenize(query)?, position: 0 };
+        let output = if parser.eat_keyword("TABLE") {
+            let without_id = parser.eat_keyword("WITHOUT");
+            if without_id && !parser.eat_keyword("ID") {
+                return Err(format!("Expected `ID`: {}", parser.unexpected()));
+            }
+            Output::Table { columns: parser.columns()?, without_id }
+        } else if parser.eat_keyword("LIST") {
+            Output::List(if parser.at_clause() { None } else { Some(parser.expr()?) })
+        } else {
+            return Err("A query starts with TABLE or LIST".to_string());
+        };
+
+        let mut query = MetadataQuery { output, source: None, filters: Vec::new(), sort: Vec::new(), limit: None };
+        if parser.eat_keyword("FROM") {
+            query.source = Some(parser.source()?);
+        }
+        while parser.peek().is_some() {
+            if parser.eat_keyword("WHERE") {
+                query.filters.push(parser.expr()?);
+            } else if parser.eat_keyword("SORT") {
+                loop {
+                    let expr = parser.expr()?;
+                    let descending = parser.eat_keyword("DESC");
+                    if !descending {
+                        parser.eat_keyword("ASC");
+                    }
+                    query.sort.push((expr, descending));
+                    if !parser.eat_symbol(",") {
+                        break;
+                    }
+                }
+            } else if parser.eat_keyword("LIMIT") {
+                match parser.next() {
+                    Some(Token::Number(n)) if n >= 0.0 && n.fract() == 0.0 => query.limit = Some(n as usize),
+                    _ => return Err("LIMIT takes a whole number".to_string()),
+                }
+            } else {
+                return Err(parser.unexpected());
+            }
+        }
+        Ok(query)
+    }
+}
+
+/// A note as seen by a query.
+struct Note<'i> {
+    key: &'i str,
+    document: &'i IndexedDocument,
+}
+
+impl Note<'_> {
+    fn folder(&self) -> &str {
+        self.key.rsplit_once('/').map_or("", |(folder, _)| folder)
+    }
+
+    fn has_tag(&self, wanted: &str) -> bool {
+        let nested = format!("{}/", wanted);
+        self.document.tags.iter().any(|tag| {
+            let key = tags::key(&tag.name);
+            key == wanted || key.starts_with(&nested)
+        })
+    }
+
+    fn field(&self, name: &str) -> Value {
+        match name.to_ascii_lowercase().as_str() {
+            "file.name" => {
+                let name = self.key.rsplit('/').next().unwrap_or(self.key);
+                Value::from(name.rsplit_once('.').map_or(name, |(stem, _)| stem))
+            }
+            "file.path" => Value::from(self.key),
+            "file.folder" => Value::from(self.folder()),
+            "file.title" => Value::from(self.document.title.as_str()),
+            "file.tags" => {
+                let mut names: Vec<String> = Vec::new();
+                for tag in &self.document.tags {
+                    let name = format!("#{}", tag.name);
+                    if !names.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+                        names.push(name);
+                    }
+                }
+                Value::from(names)
+            }
+            lower => self
+                .document
+                .frontmatter
+                .iter()
+                .find(|(key, _)| key.to_lowercase() == lower)
+                .map_or(Value::Null, |(_, value)| value.clone()),
+        }
+    }
+
+    fn matches(&self, source: &Source) -> bool {
+        match source {
+            Source::Folder(folder) => {
+                folder.is_empty() || self.key == folder || self.key.starts_with(&format!("{}/", folder))
+            }
+            Source::Tag(tag) => self.has_tag(tag),
+            Source::Not(inner) => !self.matches(inner),
+            Source::And(a, b) => self.matches(a) && self.matches(b),
+            Source::Or(a, b) => self.matches(a) || self.matches(b),
+        }
+    }
+
+    fn eval(&self, expr: &Expr) -> Value {
+        match expr {
+            Expr::Field(name) => self.field(name),
+            Expr::Literal(value) => value.clone(),
+            Expr::Not(inner) => Value::Bool(!truthy(&self.eval(inner))),
+            Expr::And(a, b) => Value::Bool(truthy(&self.eval(a)) && truthy(&self.eval(b))),
+            Expr::Or(a, b) => Value::Bool(truthy(&self.eval(a)) || truthy(&self.eval(b))),
+            Expr::Compare(a, op, b) => {
+                let (a, b) = (self.eval(a), self.eval(b));
+                Value::Bool(match op {
+                    CompareOp::Eq => equal(&a, &b),
+                    CompareOp::Ne => !equal(&a, &b),
+                    CompareOp::Lt => compare(&a, &b) == Some(Ordering::Less),
+                    CompareOp::Le => matches!(compare(&a, &b), Some(Ordering::Less | Ordering::Equal)),
+                    CompareOp::Gt => compare(&a, &b) == Some(Ordering::Greater),
+                    CompareOp::Ge => matches!(compare(&a, &b), Some(Ordering::Greater | Ordering::Equal)),
+                })
+            }
+            Expr::Contains(haystack, needle) => {
+                let needle = self.eval(needle);
+                Value::Bool(match self.eval(haystack) {
+                    Value::Array(items) => items.iter().any(|item| equal(item, &needle)),
+                    Value::String(text) => needle.as_str().is_some_and(|n| text.contains(n)),
+                    _ => false,
+                })
+            }
+        }
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        _ => a == b,
+    }
+}
+
+/// Order of two values of the same kind; `None` when they can't be ordered.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64()?.partial_cmp(&y.as_f64()?),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
+/// Sort order with nulls last, whichever the direction.
+fn sort_order(a: &Value, b: &Value, descending: bool) -> Ordering {
+    match (a.is_null(), b.is_null()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        _ => {
+            let order = compare(a, b).unwrap_or(Ordering::Equal);
+            if descending {
+                order.reverse()
+            } else {
+                order
+            }
+        }
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(display).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+/// A table cell or list entry on one line.
+fn inline(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryResult {
+    pub markdown: String,
+    pub html: String,
+    /// Relative paths of the matched notes, in result order
+    pub files: Vec<String>,
+}
+
+/// Run `query` and render it as Markdown, linking notes relative to the
+/// querying note at `from` (a workspace key) or by absolute path.
+pub fn run(index: &SearchIndex, root: &Path, query: &MetadataQuery, from: Option<&str>) -> (String, Vec<String>) {
+    let mut notes: Vec<Note> = index
+        .documents()
+        .iter()
+        .map(|(key, document)| Note { key, document })
+        .filter(|note| query.source.as_ref().is_none_or(|source| note.matches(source)))
+        .filter(|note| query.filters.iter().all(|filter| truthy(&note.eval(filter))))
+        .collect();
+    notes.sort_by(|a, b| {
+        query
+            .sort
+            .iter()
+            .map(|(expr, descending)| sort_order(&a.eval(expr), &b.eval(expr), *descending))
+            .find(|order| order.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    notes.truncate(query.limit.unwrap_or(usize::MAX));
+
+    let link = |note: &Note| {
+        let href = match from {
+            Some(from) => relative_key(parent_key(from), note.key),
+            None => root.join(note.key).to_string_lossy().replace('\\', "/"),
+        };
+        let title = note.document.title.replace('[', "\\[").replace(']', "\\]");
+        format!("[{}](<{}>)", inline(&title), href)
+    };
+
+    let mut markdown = String::new();
+    if notes.is_empty() {
+        markdown.push_str("*No results*\n");
+    } else {
+        match &query.output {
+            Output::Table { columns, without_id } => {
+                let mut labels: Vec<String> = columns.iter().map(|c| inline(&c.label)).collect();
+                if !without_id {
+                    labels.insert(0, "File".to_string());
+                }
+                markdown.push_str(&format!("| {} |\n", labels.join(" | ")));
+                markdown.push_str(&format!("|{}\n", " --- |".repeat(labels.len())));
+                for note in &notes {
+                    let mut cells: Vec<String> = columns.iter().map(|c| inline(&display(&note.eval(&c.expr)))).collect();
+                    if !without_id {
+                        cells.insert(0, link(note));
+                    }
+                    markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+                }
+            }
+            Output::List(expr) => {
+                for note in &notes {
+                    let value = expr.as_ref().map(|e| display(&note.eval(e))).unwrap_or_default();
+                    if value.is_empty() {
+                        markdown.push_str(&format!("- {}\n", link(note)));
+                    } else {
+                        markdown.push_str(&format!("- {}: {}\n", link(note), inline(&value)));
+                    }
+                }
+            }
+        }
+    }
+    (markdown, notes.iter().map(|note| note.key.to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn workspace() -> (TempDir, SearchIndex) {
+        let files = [
+            ("projects/alpha.md", "---\nstatus: active\nOwner: Ann\ndue: 2026-03-01\npriority: 2\ntags: [client]\n---\n# Alpha\n"),
+            ("projects/beta.md", "---\nstatus: done\nowner: Bob\ndue: 2026-01-15\npriority: 1\n---\n# Beta | B\n"),
+            ("projects/sub/gamma.md", "---\nstatus: waiting\nowner: Cy\npriority: 3\n---\nA #client/big note\n"),
+            ("notes/delta.md", "---\nstatus: active\n---\n#client\n"),
+        ];
+        let root = TempDir::with_files("query", &files);
+        let mut index = SearchIndex::default();
+        index.sync(&root);
+        (root, index)
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(MetadataQuery::parse("TABLE status FROM \"projects\" WHERE status != \"done\" SORT due DESC LIMIT 3").is_ok());
+        assert_eq!(MetadataQuery::parse("SELECT x").unwrap_err(), "A query starts with TABLE or LIST");
+        assert_eq!(MetadataQuery::parse("LIST WHERE (a = 1").unwrap_err(), "Expected `)`: Unexpected end of query");
+        assert_eq!(MetadataQuery::parse("LIST WHERE a = 1 b").unwrap_err(), "Unexpected `b` at column 18");
+        assert_eq!(MetadataQuery::parse("LIST WHERE lower(a)").unwrap_err(), "Unknown function `lower`");
+        assert!(MetadataQuery::parse("LIST LIMIT 1.5").is_err());
+    }
+
+    #[test]
+    fn test_table_from_folder_where_sort() {
+        let (root, index) = workspace();
+        let query = MetadataQuery::parse(
+            "TABLE status, owner AS \"Who\" FROM \"projects\" WHERE status != \"done\" SORT due, file.name DESC",
+        )
+        .unwrap();
+        let (markdown, files) = run(&index, &root, &query, Some("notes/delta.md"));
+        assert_eq!(files, vec!["projects/alpha.md", "projects/sub/gamma.md"]);
+        assert_eq!(
+            markdown,
+            "| File | status | Who |\n| --- | --- | --- |\n\
+             | [Alpha](<../projects/alpha.md>) | active | Ann |\n\
+             | [gamma](<../projects/sub/gamma.md>) | waiting | Cy |\n"
+        );
+
+        let query = MetadataQuery::parse("TABLE WITHOUT ID file.title SORT priority DESC LIMIT 2").unwrap();
+        let (markdown, _) = run(&index, &root, &query, None);
+        assert_eq!(markdown, "| file.title |\n| --- |\n| gamma |\n| Alpha |\n");
+    }
+
+    #[test]
+    fn test_list_from_tags_and_functions() {
+        let (root, index) = workspace();
+        let query = MetadataQuery::parse("LIST owner FROM #client and -\"notes\"").unwrap();
+        let (markdown, files) = run(&index, &root, &query, Some("index.md"));
+        assert_eq!(files, vec!["projects/alpha.md", "projects/sub/gamma.md"]);
+        assert_eq!(markdown, "- [Alpha](<projects/alpha.md>): Ann\n- [gamma](<projects/sub/gamma.md>): Cy\n");
+
+        let query = MetadataQuery::parse("LIST WHERE contains(file.tags, \"#client/big\") or (priority >= 1 and priority < 2)").unwrap();
+        let (_, files) = run(&index, &root, &query, None);
+        assert_eq!(files, vec!["projects/beta.md", "projects/sub/gamma.md"]);
+
+        let query = MetadataQuery::parse("LIST FROM \"missing\"").unwrap();
+        assert_eq!(run(&index, &root, &query, None).0, "*No results*\n");
+    }
+}
@@ -0,0 +1,481 @@
+//! Quick open: fuzzy matching over workspace files and note headings
+//!
+//! Files come from a cached walk that honours `.gitignore` and `.ignore`,
+//! kept current by the workspace watcher: edits and deletions update it in
+//! place, while new files mark it stale so the next lookup walks again.
+//! Headings come from the outline stored in the search index.
+//!
+//! A query matches file paths and heading text; `file#heading` (or
+//! `#heading`) matches headings within the files matching `file`.
+//! Characters match in order, scoring higher at word boundaries, in runs and
+//! in the file name. Recently opened and recently modified files get a bonus.
+
+use super::search::SearchIndex;
+use super::{is_hidden, is_skipped_dir, relative_path};
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Anchorizer, Arena, ComrakOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_LIMIT: usize = 50;
+
+/// Heading results rank below files that match as well
+const HEADING_PENALTY: i64 = 8;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Heading {
+    pub level: u8,
+    pub text: String,
+    /// Anchor id, as in the rendered preview
+    pub anchor: String,
+    /// 1-based line in the document, frontmatter included
+    pub line: usize,
+}
+
+/// Headings of a document in order.
+pub(crate) fn outline(content: &str) -> Vec<Heading> {
+    let (body, _metadata) = crate::split_frontmatter(content);
+    let line_offset = content[..content.len() - body.len()].matches('\n').count();
+    let mut options = ComrakOptions::default();
+    crate::configure_markdown_options(&mut options);
+    let arena = Arena::new();
+    let root = parse_document(&arena, body, &options);
+    let mut anchorizer = Anchorizer::new();
+    root.descendants()
+        .filter_map(|node| {
+            let NodeValue::Heading(heading) = node.data.borrow().value else { return None };
+            let text = crate::links::heading_text(node);
+            Some(Heading {
+                level: heading.level,
+                anchor: anchorizer.anchorize(text.clone()),
+                text: text.trim().to_string(),
+                line: node.data.borrow().sourcepos.start.line + line_offset,
+            })
+        })
+        .collect()
+}
+
+fn modified_ms(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Every file in the workspace with its modification time.
+#[derive(Debug, Default)]
+pub struct FileList {
+    /// Milliseconds since the epoch by relative path
+    files: BTreeMap<String, u64>,
+    stale: bool,
+}
+
+impl FileList {
+    pub fn scan(root: &Path) -> Self {
+        let mut files = BTreeMap::new();
+        let walker = ignore::WalkBuilder::new(root)
+            .require_git(false)
+            .filter_entry(|entry| entry.depth() == 0 || !is_skipped_dir(&entry.file_name().to_string_lossy()))
+            .build();
+        for entry in walker.flatten() {
+            if entry.file_type().is_some_and(|t| t.is_file()) {
+                let modified = entry.metadata().map(|m| modified_ms(&m)).unwrap_or(0);
+                files.insert(relative_path(root, entry.path()), modified);
+            }
+        }
+        Self { files, stale: false }
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Relative paths, sorted.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.files.keys()
+    }
+
+    /// Apply a watcher event for `path`.
+    pub fn update(&mut self, root: &Path, path: &Path) {
+        let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if path == root || hidden || is_hidden(root, path) {
+            return;
+        }
+        let key = relative_path(root, path);
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => match self.files.get_mut(&key) {
+                Some(modified) => *modified = modified_ms(&metadata),
+                // Whether a new file is ignored is settled by the next walk
+                None => self.stale = true,
+            },
+            Ok(_) => self.stale = true,
+            Err(_) => {
+                let folder = format!("{}/", key);
+                self.files.retain(|file, _| *file != key && !file.starts_with(&folder));
+            }
+        }
+    }
+
+    /// Walk again if files were created since the last walk.
+    pub fn refresh(&mut self, root: &Path) {
+        if self.stale {
+            *self = Self::scan(root);
+        }
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '/' | '\\' | '_' | '-' | '.' | ' ')
+}
+
+fn lower(c: char) -> char {
+    if c.is_ascii() {
+        c.to_ascii_lowercase()
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Fuzzy matcher for one pattern, reusing its buffers across candidates.
+///
+/// Candidates containing the pattern as a subsequence are aligned by dynamic
+/// programming for the best score: each character scores, more at word
+/// starts, in runs and from `name_start` (a character index) on; each gap
+/// costs.
+struct Matcher {
+    /// Lower-cased, without whitespace
+    pattern: Vec<char>,
+    chars: Vec<char>,
+    bonus: Vec<i64>,
+    /// Best score with pattern[j] matched at text[i], by `j * len + i`
+    scores: Vec<i64>,
+    /// Where pattern[j - 1] matched for that score
+    from: Vec<usize>,
+}
+
+const GAP: i64 = 5;
+const NONE: i64 = i64::MIN / 2;
+
+impl Matcher {
+    fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.chars().filter(|c| !c.is_whitespace()).map(lower).collect(),
+            chars: Vec::new(),
+            bonus: Vec::new(),
+            scores: Vec::new(),
+            from: Vec::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pattern.is_empty()
+    }
+
+    /// Whether the pattern is a subsequence of `text`; most candidates fail
+    /// this before anything is allocated.
+    fn matches(&self, text: &str) -> bool {
+        let mut pattern = self.pattern.iter().peekable();
+        for c in text.chars() {
+            if pattern.peek().is_some_and(|&&p| p == lower(c)) {
+                pattern.next();
+            }
+        }
+        pattern.peek().is_none()
+    }
+
+    /// Score of `text`, or `None` when the pattern doesn't match it.
+    fn score(&mut self, text: &str, name_start: usize) -> Option<i64> {
+        if self.pattern.is_empty() {
+            return Some(0);
+        }
+        if !self.matches(text) {
+            return None;
+        }
+        self.align(text, name_start).map(|(score, _)| score)
+    }
+
+    /// UTF-16 offsets of the matched characters in `text`.
+    fn positions(&mut self, text: &str, name_start: usize) -> Vec<usize> {
+        if self.pattern.is_empty() || !self.matches(text) {
+            return Vec::new();
+        }
+        let Some((_, end)) = self.align(text, name_start) else { return Vec::new() };
+        let (n, m) = (self.chars.len(), self.pattern.len());
+        let mut indices = vec![end; m];
+        for j in (1..m).rev() {
+            indices[j - 1] = self.from[j * n + indices[j]];
+        }
+        let mut positions = Vec::with_capacity(m);
+        let mut offset = 0;
+        let mut next = indices.iter().peekable();
+        for (i, c) in self.chars.iter().enumerate() {
+            if next.peek() == Some(&&i) {
+                positions.push(offset);
+                next.next();
+            }
+            offset += c.len_utf16();
+        }
+        positions
+    }
+
+    /// Best alignment: its score and where the last pattern character matched.
+    fn align(&mut self, text: &str, name_start: usize) -> Option<(i64, usize)> {
+        self.chars.clear();
+        self.chars.extend(text.chars());
+        let chars = &self.chars;
+        let (n, m) = (chars.len(), self.pattern.len());
+        self.bonus.clear();
+        self.bonus.extend((0..n).map(|i| {
+            let mut bonus = 16;
+            if i == 0 || is_separator(chars[i - 1]) {
+                bonus += if i == name_start { 12 } else { 8 };
+            } else if chars[i - 1].is_lowercase() && chars[i].is_uppercase() {
+                bonus += 6;
+            }
+            if i >= name_start {
+                bonus += 4;
+            }
+            bonus
+        }));
+        self.scores.clear();
+        self.scores.resize(m * n, NONE);
+        self.from.clear();
+        self.from.resize(m * n, 0);
+
+        for j in 0..m {
+            let mut best_gapped = (NONE, 0);
+            for (i, &c) in chars.iter().enumerate().skip(j) {
+                if j > 0 && i >= 2 && self.scores[(j - 1) * n + i - 2] > best_gapped.0 {
+                    best_gapped = (self.scores[(j - 1) * n + i - 2], i - 2);
+                }
+                if lower(c) != self.pattern[j] {
+                    continue;
+                }
+                if j == 0 {
+                    self.scores[i] = self.bonus[i];
+                    continue;
+                }
+                let consecutive = self.scores[(j - 1) * n + i - 1];
+                let (previous, at) = if consecutive > NONE && consecutive + 12 >= best_gapped.0 - GAP {
+                    (consecutive + 12, i - 1)
+                } else if best_gapped.0 > NONE {
+                    (best_gapped.0 - GAP, best_gapped.1)
+                } else {
+                    continue;
+                };
+                self.scores[j * n + i] = previous + self.bonus[i];
+                self.from[j * n + i] = at;
+            }
+        }
+
+        let last = &self.scores[(m - 1) * n..];
+        let end = (0..n).filter(|&i| last[i] > NONE).max_by_key(|&i| (last[i], std::cmp::Reverse(i)))?;
+        // Shorter candidates win ties
+        Some((last[end] - (n / 16) as i64, end))
+    }
+}
+
+/// Bonus for files opened recently (`recent` is most recent first) or modified lately.
+fn recency_bonus(rank: Option<usize>, modified: u64, now: u64) -> i64 {
+    const DAY: u64 = 24 * 60 * 60 * 1000;
+    let opened = rank.map_or(0, |rank| 40 - 4 * rank.min(10) as i64);
+    let age = now.saturating_sub(modified);
+    let edited = if age < DAY {
+        10
+    } else if age < 7 * DAY {
+        6
+    } else if age < 30 * DAY {
+        3
+    } else {
+        0
+    };
+    opened + edited
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickOpenItem {
+    pub path: String,
+    pub relative_path: String,
+    /// UTF-16 offsets of matched characters in `relative_path`
+    pub positions: Vec<usize>,
+    /// Set for heading results
+    pub heading: Option<String>,
+    pub anchor: Option<String>,
+    pub level: Option<u8>,
+    /// Line to open at; 1 for files
+    pub line: usize,
+    /// UTF-16 offsets of matched characters in `heading`
+    pub heading_positions: Vec<usize>,
+    pub score: i64,
+}
+
+/// A scored result, expanded into a `QuickOpenItem` only if it makes the cut.
+struct Candidate<'i> {
+    key: &'i str,
+    name_start: usize,
+    heading: Option<&'i Heading>,
+    score: i64,
+}
+
+/// Files and headings matching `query`, best first.
+pub fn quick_open(
+    files: &FileList,
+    index: &SearchIndex,
+    root: &Path,
+    query: &str,
+    recent: &[String],
+    limit: usize,
+) -> Vec<QuickOpenItem> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+    let recent: HashMap<&str, usize> = recent.iter().enumerate().map(|(rank, key)| (key.as_str(), rank)).collect();
+    let headings_only = query.contains('#');
+    let (file_query, heading_query) = query.split_once('#').unwrap_or((query, query));
+    let mut file_matcher = Matcher::new(file_query);
+    let mut heading_matcher = Matcher::new(heading_query);
+
+    let mut candidates = Vec::new();
+    for (key, modified) in &files.files {
+        let name_start = key.rfind('/').map_or(0, |i| key[..=i].chars().count());
+        let file_score = file_matcher.score(key, name_start);
+        let bonus = recency_bonus(recent.get(key.as_str()).copied(), *modified, now);
+        let candidate = |heading, score| Candidate { key, name_start, heading, score };
+
+        // A bare query matches headings on their own; after `#` only in matching files
+        let heading_base = match file_score {
+            Some(score) if headings_only => score,
+            Some(score) => {
+                candidates.push(candidate(None, score + bonus));
+                0
+            }
+            None if headings_only => continue,
+            None => 0,
+        };
+        if !headings_only && heading_matcher.is_empty() {
+            continue;
+        }
+        let Some(document) = index.documents().get(key) else { continue };
+        for heading in &document.headings {
+            if let Some(score) = heading_matcher.score(&heading.text, 0) {
+                candidates.push(candidate(Some(heading), heading_base + score + bonus - HEADING_PENALTY));
+            }
+        }
+    }
+
+    let order = |a: &Candidate, b: &Candidate| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.key.len().cmp(&b.key.len()))
+            .then_with(|| a.key.cmp(b.key))
+            .then_with(|| a.heading.map(|h| h.line).cmp(&b.heading.map(|h| h.line)))
+    };
+    if candidates.len() > limit && limit > 0 {
+        candidates.select_nth_unstable_by(limit - 1, order);
+    }
+    candidates.truncate(limit);
+    candidates.sort_by(order);
+
+    candidates
+        .into_iter()
+        .map(|candidate| {
+            let positions = if candidate.heading.is_none() || headings_only {
+                file_matcher.positions(candidate.key, candidate.name_start)
+            } else {
+                Vec::new()
+            };
+            QuickOpenItem {
+                path: root.join(candidate.key).to_string_lossy().to_string(),
+                relative_path: candidate.key.to_string(),
+                positions,
+                heading: candidate.heading.map(|h| h.text.clone()),
+                anchor: candidate.heading.map(|h| h.anchor.clone()),
+                level: candidate.heading.map(|h| h.level),
+                line: candidate.heading.map_or(1, |h| h.line),
+                heading_positions: candidate.heading.map(|h| heading_matcher.positions(&h.text, 0)).unwrap_or_default(),
+                score: candidate.score,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn workspace(name: &str) -> (TempDir, FileList, SearchIndex) {
+        let files = [
+            (".gitignore", "build/\n*.log\n"),
+            ("notes/Meeting Notes.md", "# Weekly sync\n\n## Action items\n"),
+            ("notes/misc.md", "---\ntitle: Misc\n---\n# Set up CI\n## Action items\n"),
+            ("src/main.rs", "fn main() {}\n"),
+            ("build/out.md", "# Ignored\n"),
+            ("debug.log", "x\n"),
+        ];
+        let root = TempDir::with_files(&format!("quick-{}", name), &files);
+        let list = FileList::scan(&root);
+        let mut index = SearchIndex::default();
+        index.sync(&root);
+        (root, list, index)
+    }
+
+    fn found(items: &[QuickOpenItem]) -> Vec<(&str, Option<&str>, usize)> {
+        items.iter().map(|i| (i.relative_path.as_str(), i.anchor.as_deref(), i.line)).collect()
+    }
+
+    #[test]
+    fn test_fuzzy_match_prefers_boundaries_and_runs() {
+        let mut matcher = Matcher::new("mn");
+        let word_starts = matcher.score("notes/Meeting Notes.md", 6).unwrap();
+        let scattered = matcher.score("notes/commons.md", 6).unwrap();
+        assert!(word_starts > scattered, "{} {}", word_starts, scattered);
+        assert_eq!(matcher.positions("notes/Meeting Notes.md", 6), vec![6, 14]);
+        assert!(matcher.score("nm", 0).is_none());
+        assert_eq!(Matcher::new("É t").positions("📝 été", 0), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_files_respect_gitignore_and_updates() {
+        let (root, mut list, _) = workspace("files");
+        let keys: Vec<&str> = list.files.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["notes/Meeting Notes.md", "notes/misc.md", "src/main.rs"]);
+
+        fs::remove_file(root.join("src/main.rs")).unwrap();
+        list.update(&root, &root.join("src/main.rs"));
+        fs::write(root.join("new.md"), "").unwrap();
+        list.update(&root, &root.join("new.md"));
+        assert_eq!(list.len(), 2);
+        list.refresh(&root);
+        assert_eq!(list.len(), 3);
+    }
+
+    #[test]
+    fn test_files_and_headings_ranked() {
+        let (root, list, index) = workspace("rank");
+        let items = quick_open(&list, &index, &root, "meet", &[], DEFAULT_LIMIT);
+        assert_eq!(found(&items)[0], ("notes/Meeting Notes.md", None, 1));
+
+        // Headings match on their own text, and by file with `#`
+        let items = quick_open(&list, &index, &root, "setup", &[], DEFAULT_LIMIT);
+        assert_eq!(found(&items), vec![("notes/misc.md", Some("set-up-ci"), 4)]);
+        let items = quick_open(&list, &index, &root, "misc#action", &[], DEFAULT_LIMIT);
+        assert_eq!(found(&items), vec![("notes/misc.md", Some("action-items"), 5)]);
+        let items = quick_open(&list, &index, &root, "#action", &[], DEFAULT_LIMIT);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].heading_positions, vec![0, 1, 2, 3, 4, 5]);
+
+        // Recently opened files come first for an empty query
+        let recent = vec!["src/main.rs".to_string()];
+        let items = quick_open(&list, &index, &root, "", &recent, 2);
+        assert_eq!(found(&items), vec![("src/main.rs", None, 1), ("notes/misc.md", None, 1)]);
+    }
+}
@@ -0,0 +1,565 @@
+//! Refactoring rename: move a note, image or folder and fix the links to it
+//!
+//! The link index picks the notes that may point at a moved file; their links
+//! are then found in the source text (outside code) and rewritten in the
+//! style they were written in: relative or root-absolute paths, with or
+//! without `.md`, percent-encoded or in `<angle brackets>`, bare wikilink
+//! names or paths, and `<img src>` attributes. Links inside a moved note are
+//! rebased on its new folder. Images linked by bare name through the
+//! `image_directory` fallback get an explicit path once they leave that folder.
+//!
+//! A rename is planned first, so the edits can be previewed, then applied as
+//! one batch that can be undone as long as the edited files are untouched.
+
+use super::graph::{has_extension, has_markdown_extension, join_key, parent_key, relative_key, resolve_wiki, Files, WikiStyle};
+use super::quick_open::FileList;
+use super::relative_path;
+use super::search::SearchIndex;
+use crate::file_tree::is_image;
+use crate::links::{has_scheme, percent_decode, FoundLink, LinkKind};
+use crate::tasks::write_atomic;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RenameOptions {
+    /// Folder pasted images are saved to, relative to the document (see `save_image`)
+    pub image_directory: String,
+}
+
+impl Default for RenameOptions {
+    fn default() -> Self {
+        Self { image_directory: "img".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkChange {
+    pub line: usize,
+    /// Link target as written before and after
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileEdit {
+    /// Where the file is before the rename
+    pub path: String,
+    pub relative_path: String,
+    pub changes: Vec<LinkChange>,
+    #[serde(skip)]
+    original: String,
+    #[serde(skip)]
+    updated: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MovedFile {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamePlan {
+    pub from: String,
+    pub to: String,
+    /// Every file that moves, by relative path; one entry unless a folder moves
+    pub moved: Vec<MovedFile>,
+    pub edits: Vec<FileEdit>,
+}
+
+fn without_markdown_extension(key: &str) -> &str {
+    if has_markdown_extension(key) {
+        key.rsplit_once('.').map_or(key, |(stem, _)| stem)
+    } else {
+        key
+    }
+}
+
+/// How a Markdown link target was written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct LinkStyle {
+    /// `/from/the/root`
+    absolute: bool,
+    /// `./sibling`
+    dot_slash: bool,
+    /// An image found in the `image_directory` by bare name
+    image_fallback: bool,
+    /// `note` for `note.md`
+    without_extension: bool,
+    /// `my%20note.md`
+    encoded: bool,
+}
+
+/// Resolve a Markdown link target (without `<>`) from note `from`; returns
+/// the file, the `?query#fragment` as written and the style.
+fn resolve_link<'t>(files: &Files, from: &str, target: &'t str, image_dir: &str) -> Option<(String, &'t str, LinkStyle)> {
+    if target.is_empty() || target.starts_with('#') || has_scheme(target) {
+        return None;
+    }
+    let (path, suffix) = target.split_at(target.find(['?', '#']).unwrap_or(target.len()));
+    let decoded = percent_decode(path);
+    let dir = parent_key(from);
+    let mut style = LinkStyle {
+        absolute: decoded.starts_with('/'),
+        dot_slash: decoded.starts_with("./"),
+        encoded: decoded != path,
+        ..Default::default()
+    };
+    let key = join_key(dir, &decoded);
+    // As written, from the image folder, or with `.md` implied
+    let mut candidates = vec![(key.clone(), false, false)];
+    if is_image(Path::new(&decoded)) && !decoded.contains('/') {
+        candidates.push((join_key(dir, &format!("{}/{}", image_dir, decoded)), true, false));
+    }
+    if !has_extension(&decoded) {
+        candidates.push((format!("{}.md", key), false, true));
+    }
+    let (key, image_fallback, without_extension) = candidates.into_iter().find(|(key, ..)| files.contains(key))?;
+    style.image_fallback = image_fallback;
+    style.without_extension = without_extension;
+    Some((key, suffix, style))
+}
+
+/// Percent-encode what would end or break a bare link target.
+fn encode_target(path: &str) -> String {
+    path.chars()
+        .map(|c| match c {
+            ' ' | '(' | ')' | '%' | '<' | '>' | '#' | '?' | '"' => format!("%{:02X}", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Write a link from note `from` to `target` in `style`.
+fn link_target(from: &str, target: &str, style: LinkStyle, image_dir: &str) -> String {
+    let dir = parent_key(from);
+    if style.image_fallback && parent_key(target) == join_key(dir, image_dir) {
+        return target.rsplit('/').next().unwrap_or(target).to_string();
+    }
+    let target = if style.without_extension { without_markdown_extension(target) } else { target };
+    if style.absolute {
+        return format!("/{}", target);
+    }
+    let relative = relative_key(dir, target);
+    if style.dot_slash && !relative.starts_with("../") {
+        format!("./{}", relative)
+    } else {
+        relative
+    }
+}
+
+/// Link targets outside code: wikilinks and embeds, inline link destinations,
+/// reference definitions and `<img src>`.
+fn target_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?ms)^[ \t]*```.*?^[ \t]*```|^[ \t]*~~~.*?^[ \t]*~~~|`[^`\n]*`",
+            r"|(?P<bang>!?)\[\[(?P<wiki>[^\]\n]+)\]\]",
+            r"|\]\([ \t]*(?:<(?P<angle>[^>\n]*)>|(?P<bare>(?:[^\s()\\]|\\.|\([^\s()]*\))+))",
+            r"|^[ \t]{0,3}\[[^\]\n]+\]:[ \t]*(?:<(?P<def_angle>[^>\n]*)>|(?P<def>\S+))",
+            r#"|<img\b[^>\n]*?\bsrc\s*=\s*["'](?P<src>[^"'\n]+)["']"#,
+        ))
+        .unwrap()
+    })
+}
+
+/// The file a link from note `from` points at, if it exists.
+pub(crate) fn resolve(files: &Files, from: &str, link: &FoundLink, image_dir: &str) -> Option<String> {
+    match link.kind {
+        LinkKind::WikiLink | LinkKind::Embed => {
+            let name = link.target.split('#').next().unwrap_or("");
+            resolve_wiki(files, from, name, (link.kind == LinkKind::Embed).then_some(image_dir)).map(|(key, _)| key)
+        }
+        LinkKind::Link | LinkKind::Image => resolve_link(files, from, &link.target, image_dir).map(|(key, _, _)| key),
+    }
+}
+
+/// Files before and after the rename, and what moves where.
+pub(crate) struct Rename<'o> {
+    old: Files,
+    new: Files,
+    moves: HashMap<String, String>,
+    image_dir: &'o str,
+}
+
+impl<'o> Rename<'o> {
+    /// `files` are the relative paths before the rename; `moves` maps old
+    /// paths to new ones, possibly onto an existing file.
+    pub(crate) fn new(files: BTreeSet<String>, moves: HashMap<String, String>, image_dir: &'o str) -> Self {
+        let old: BTreeSet<String> = files.into_iter().chain(moves.keys().cloned()).collect();
+        let new: BTreeSet<String> = old.iter().map(|key| moves.get(key).unwrap_or(key).clone()).collect();
+        Self { old: Files::new(old), new: Files::new(new), moves, image_dir }
+    }
+
+    fn moved<'k>(&'k self, key: &'k str) -> &'k str {
+        self.moves.get(key).map_or(key, String::as_str)
+    }
+
+    /// Whether an indexed link from `from` points at a moved file.
+    fn affects(&self, from: &str, link: &FoundLink) -> bool {
+        resolve(&self.old, from, link, self.image_dir).is_some_and(|key| self.moves.contains_key(&key))
+    }
+
+    /// The link edits the note `key` under `root` needs, if any.
+    pub(crate) fn edit(&self, root: &Path, key: &str) -> Result<Option<FileEdit>, String> {
+        let path = root.join(key);
+        let original = fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let (updated, changes) = self.rewrite(&original, key, self.moved(key));
+        Ok((!changes.is_empty()).then(|| FileEdit {
+            path: path.to_string_lossy().to_string(),
+            relative_path: key.to_string(),
+            changes,
+            original,
+            updated,
+        }))
+    }
+
+    /// New name for a wikilink that no longer resolves as written.
+    fn wiki_name(&self, from: &str, target: &str, style: WikiStyle, name: &str, embed: bool) -> String {
+        let display = |key: &str| {
+            if has_extension(name) { key.to_string() } else { without_markdown_extension(key).to_string() }
+        };
+        let mut candidates = Vec::new();
+        match style {
+            WikiStyle::Bare => candidates.push(display(target.rsplit('/').next().unwrap_or(target))),
+            WikiStyle::Relative => {
+                if let Some(rest) = target.strip_prefix(&format!("{}/", parent_key(from))) {
+                    candidates.push(display(rest));
+                }
+            }
+            WikiStyle::Root => {}
+        }
+        candidates.push(display(target));
+        candidates
+            .into_iter()
+            .find(|candidate| {
+                resolve_wiki(&self.new, from, candidate, embed.then_some(self.image_dir)).is_some_and(|(key, _)| key == target)
+            })
+            // A root-level note shadowed by a namesake nearby
+            .unwrap_or_else(|| format!("/{}", display(target)))
+    }
+
+    /// A wikilink's replacement name, if it needs one.
+    fn rewrite_wiki(&self, from_old: &str, from_new: &str, name: &str, embed: bool) -> Option<String> {
+        let (target, style) = resolve_wiki(&self.old, from_old, name, embed.then_some(self.image_dir))?;
+        let target = self.moved(&target);
+        let still = resolve_wiki(&self.new, from_new, name, embed.then_some(self.image_dir));
+        if still.is_some_and(|(key, _)| key == target) {
+            return None;
+        }
+        Some(self.wiki_name(from_new, target, style, name.trim(), embed))
+    }
+
+    /// A Markdown link's replacement target, if it needs one.
+    fn rewrite_link(&self, from_old: &str, from_new: &str, written: &str, angle: bool) -> Option<String> {
+        let (old_target, suffix, style) = resolve_link(&self.old, from_old, written, self.image_dir)?;
+        let target = self.moved(&old_target);
+        if target == old_target && from_old == from_new {
+            return None;
+        }
+        let path = link_target(from_new, target, style, self.image_dir);
+        let path = if !angle && (style.encoded || path.contains([' ', '(', ')'])) { encode_target(&path) } else { path };
+        let rewritten = format!("{}{}", path, suffix);
+        (rewritten != written).then_some(rewritten)
+    }
+
+    /// `content` of the note at `from_old` with its links fixed.
+    fn rewrite(&self, content: &str, from_old: &str, from_new: &str) -> (String, Vec<LinkChange>) {
+        let (body, _metadata) = crate::split_frontmatter(content);
+        let body_start = content.len() - body.len();
+        let mut changes = Vec::new();
+        let mut updated = String::with_capacity(content.len());
+        let mut copied = 0;
+        for caps in target_re().captures_iter(body) {
+            let Some((range, replacement)) = self.replacement(&caps, from_old, from_new) else { continue };
+            let (start, end) = (body_start + range.start, body_start + range.end);
+            changes.push(LinkChange {
+                line: content[..start].matches('\n').count() + 1,
+                before: content[start..end].to_string(),
+                after: replacement.clone(),
+            });
+            updated.push_str(&content[copied..start]);
+            updated.push_str(&replacement);
+            copied = end;
+        }
+        updated.push_str(&content[copied..]);
+        (updated, changes)
+    }
+
+    fn replacement(&self, caps: &Captures, from_old: &str, from_new: &str) -> Option<(std::ops::Range<usize>, String)> {
+        if let Some(inner) = caps.name("wiki") {
+            // Only the name: `#heading`, `^block`, `|alias` and `|size` stay
+            let end = inner.as_str().find(['#', '|']).unwrap_or(inner.len());
+            let name = &inner.as_str()[..end];
+            let trimmed = name.trim();
+            if trimmed.is_empty() {
+                return None;
+            }
+            let start = inner.start() + name.find(trimmed).unwrap_or(0);
+            let embed = !caps["bang"].is_empty();
+            let replacement = self.rewrite_wiki(from_old, from_new, trimmed, embed)?;
+            return Some((start..start + trimmed.len(), replacement));
+        }
+        // Targets in `<>` or quotes may contain spaces as they are
+        let (target, angle) = match ["angle", "def_angle", "src"].into_iter().find_map(|name| caps.name(name)) {
+            Some(target) => (target, true),
+            None => (caps.name("bare").or_else(|| caps.name("def"))?, false),
+        };
+        let replacement = self.rewrite_link(from_old, from_new, target.as_str(), angle)?;
+        Some((target.range(), replacement))
+    }
+}
+
+/// Plan moving `from` (a file or folder) to `to` and the link edits it needs.
+pub fn plan(
+    index: &SearchIndex,
+    files: &FileList,
+    root: &Path,
+    from: &Path,
+    to: &Path,
+    options: &RenameOptions,
+) -> Result<RenamePlan, String> {
+    if !from.starts_with(root) || !to.starts_with(root) || from == root {
+        return Err("Both paths must be inside the workspace".to_string());
+    }
+    if !from.exists() {
+        return Err(format!("{} does not exist", from.display()));
+    }
+    if to.exists() {
+        return Err(format!("{} already exists", to.display()));
+    }
+    if to.starts_with(from) {
+        return Err("A folder cannot be moved into itself".to_string());
+    }
+    let from_key = relative_path(root, from);
+    let to_key = relative_path(root, to);
+
+    let mut moves = HashMap::new();
+    if from.is_dir() {
+        let prefix = format!("{}/", from_key);
+        for key in files.keys() {
+            if let Some(rest) = key.strip_prefix(&prefix) {
+                moves.insert(key.clone(), format!("{}/{}", to_key, rest));
+            }
+        }
+    } else {
+        moves.insert(from_key.clone(), to_key.clone());
+    }
+
+    let rename = Rename::new(files.keys().cloned().collect(), moves, &options.image_directory);
+    let mut edits = Vec::new();
+    for (key, document) in index.documents() {
+        if rename.moved(key) == key && !document.links.iter().any(|link| rename.affects(key, link)) {
+            continue;
+        }
+        edits.extend(rename.edit(root, key)?);
+    }
+
+    let mut moved: Vec<MovedFile> = rename.moves.into_iter().map(|(from, to)| MovedFile { from, to }).collect();
+    moved.sort_by(|a, b| a.from.cmp(&b.from));
+    Ok(RenamePlan {
+        from: from.to_string_lossy().to_string(),
+        to: to.to_string_lossy().to_string(),
+        moved,
+        edits,
+    })
+}
+
+impl RenamePlan {
+    /// Where an edited file is once the rename is applied.
+    fn edited_path(&self, root: &Path, edit: &FileEdit) -> std::path::PathBuf {
+        let key = self.moved.iter().find(|m| m.from == edit.relative_path).map_or(&edit.relative_path, |m| &m.to);
+        root.join(key)
+    }
+}
+
+/// Restore the files written so far when a batch fails halfway.
+fn restore(edits: &[FileEdit]) {
+    for edit in edits {
+        if let Err(e) = write_atomic(Path::new(&edit.path), &edit.original) {
+            log::warn!("[workspace] Cannot restore {}: {}", edit.path, e);
+        }
+    }
+}
+
+/// Write `edits`, all or none, provided no file changed since they were planned.
+pub(crate) fn write_edits(edits: &[FileEdit]) -> Result<(), String> {
+    for edit in edits {
+        if fs::read_to_string(&edit.path).ok().as_deref() != Some(edit.original.as_str()) {
+            return Err(format!("{} changed since the edits were planned", edit.relative_path));
+        }
+    }
+    for (done, edit) in edits.iter().enumerate() {
+        if let Err(e) = write_atomic(Path::new(&edit.path), &edit.updated) {
+            restore(&edits[..done]);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Remove `dir` if it is an image folder left empty.
+pub(crate) fn remove_empty_image_dir(dir: &Path, image_directory: &str) {
+    let is_image_dir = dir.file_name().is_some_and(|name| name == image_directory);
+    if is_image_dir && fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_none()) {
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+/// Write the planned edits and move the file or folder.
+pub fn apply(plan: &RenamePlan, options: &RenameOptions) -> Result<(), String> {
+    let (from, to) = (Path::new(&plan.from), Path::new(&plan.to));
+    if to.exists() {
+        return Err(format!("{} already exists", to.display()));
+    }
+    write_edits(&plan.edits)?;
+    let moved = to
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::rename(from, to))
+        .map_err(|e| format!("Cannot move {}: {}", from.display(), e));
+    if let Err(e) = moved {
+        restore(&plan.edits);
+        return Err(e);
+    }
+
+    // Moving the last image out leaves an empty image folder behind
+    if let Some(parent) = from.parent() {
+        remove_empty_image_dir(parent, &options.image_directory);
+    }
+    Ok(())
+}
+
+/// Reverse an applied plan, if none of its files changed since.
+pub fn undo(root: &Path, plan: &RenamePlan) -> Result<(), String> {
+    let (from, to) = (Path::new(&plan.from), Path::new(&plan.to));
+    if from.exists() || !to.exists() {
+        return Err(format!("{} was moved again since the rename", to.display()));
+    }
+    for edit in &plan.edits {
+        let path = plan.edited_path(root, edit);
+        if fs::read_to_string(&path).ok().as_deref() != Some(edit.updated.as_str()) {
+            return Err(format!("{} changed since the rename", relative_path(root, &path)));
+        }
+    }
+    from.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::rename(to, from))
+        .map_err(|e| format!("Cannot move {} back: {}", to.display(), e))?;
+    for edit in &plan.edits {
+        write_atomic(Path::new(&edit.path), &edit.original)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn workspace(name: &str, files: &[(&str, &str)]) -> (TempDir, SearchIndex, FileList) {
+        let root = TempDir::with_files(&format!("refactor-{}", name), files);
+        let mut index = SearchIndex::default();
+        index.sync(&root);
+        let list = FileList::scan(&root);
+        (root, index, list)
+    }
+
+    fn edits(plan: &RenamePlan) -> Vec<(&str, Vec<(&str, &str)>)> {
+        plan.edits
+            .iter()
+            .map(|e| (e.relative_path.as_str(), e.changes.iter().map(|c| (c.before.as_str(), c.after.as_str())).collect()))
+            .collect()
+    }
+
+    const FILES: &[(&str, &str)] = &[
+        ("Target Note.md", "# Target\n\n[up](notes/a.md) ![pic](img/pic.png)\n\n<img src=\"img/pic.png\" width=\"40\">\n"),
+        (
+            "notes/a.md",
+            "[[Target Note#Setup|alias]] ![[Target Note]] [rel](../Target%20Note.md#setup) [abs](</Target Note.md>)\n[noext](../Target%20Note) [ref]\n\n[ref]: ../Target%20Note.md\n\n`[[Target Note]]`\n\n```\n[x](../Target%20Note.md)\n```\n",
+        ),
+        ("notes/b.md", "[[notes/a]] [[Elsewhere]] [a](a.md)\n"),
+        ("notes/img/shot.png", ""),
+        ("notes/c.md", "![shot](shot.png) ![[shot.png|100]]\n"),
+        ("img/pic.png", ""),
+    ];
+
+    #[test]
+    fn test_rename_rewrites_links_in_their_style() {
+        let (root, index, files) = workspace("note", FILES);
+        let options = RenameOptions::default();
+        let plan = plan(&index, &files, &root, &root.join("Target Note.md"), &root.join("archive/Old Target.md"), &options).unwrap();
+        assert_eq!(
+            edits(&plan),
+            vec![
+                ("Target Note.md", vec![("notes/a.md", "../notes/a.md"), ("img/pic.png", "../img/pic.png"), ("img/pic.png", "../img/pic.png")]),
+                (
+                    "notes/a.md",
+                    vec![
+                        ("Target Note", "Old Target"),
+                        ("Target Note", "Old Target"),
+                        ("../Target%20Note.md#setup", "../archive/Old%20Target.md#setup"),
+                        ("/Target Note.md", "/archive/Old Target.md"),
+                        ("../Target%20Note", "../archive/Old%20Target"),
+                        ("../Target%20Note.md", "../archive/Old%20Target.md"),
+                    ]
+                ),
+            ]
+        );
+
+        apply(&plan, &options).unwrap();
+        let a = fs::read_to_string(root.join("notes/a.md")).unwrap();
+        assert!(a.starts_with("[[Old Target#Setup|alias]] ![[Old Target]]"), "{}", a);
+        assert!(a.contains("`[[Target Note]]`") && a.contains("[x](../Target%20Note.md)"));
+        assert!(root.join("archive/Old Target.md").is_file() && !root.join("Target Note.md").exists());
+
+        undo(&root, &plan).unwrap();
+        assert_eq!(fs::read_to_string(root.join("notes/a.md")).unwrap(), FILES[1].1);
+        assert_eq!(fs::read_to_string(root.join("Target Note.md")).unwrap(), FILES[0].1);
+    }
+
+    #[test]
+    fn test_move_folder_and_image_out_of_image_directory() {
+        let (root, index, files) = workspace("folder", FILES);
+        let options = RenameOptions::default();
+        let plan = plan(&index, &files, &root, &root.join("notes"), &root.join("docs/notes"), &options).unwrap();
+        assert_eq!(plan.moved.len(), 4);
+        assert_eq!(
+            edits(&plan),
+            vec![
+                ("Target Note.md", vec![("notes/a.md", "docs/notes/a.md")]),
+                (
+                    "notes/a.md",
+                    vec![
+                        ("../Target%20Note.md#setup", "../../Target%20Note.md#setup"),
+                        ("../Target%20Note", "../../Target%20Note"),
+                        ("../Target%20Note.md", "../../Target%20Note.md"),
+                    ]
+                ),
+                ("notes/b.md", vec![("notes/a", "docs/notes/a")]),
+            ]
+        );
+
+        let plan = plan_image(&root, &index, &files, &options);
+        assert_eq!(edits(&plan), vec![("notes/c.md", vec![("shot.png", "../assets/shot.png")])]);
+        apply(&plan, &options).unwrap();
+        assert!(!root.join("notes/img").exists());
+        let err = super::plan(&index, &files, &root, &root.join("notes/b.md"), &root.join("notes/c.md"), &options);
+        assert!(err.unwrap_err().contains("already exists"));
+    }
+
+    fn plan_image(root: &Path, index: &SearchIndex, files: &FileList, options: &RenameOptions) -> RenamePlan {
+        plan(index, files, root, &root.join("notes/img/shot.png"), &root.join("assets/shot.png"), options).unwrap()
+    }
+}
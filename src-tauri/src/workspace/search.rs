@@ -0,0 +1,692 @@
+//! Full-text search index
+//!
+//! An inverted index from lower-cased terms to the documents and token
+//! positions they occur at. CJK characters are indexed one per token, so a
+//! phrase query finds CJK words without a segmenter. Frontmatter is indexed
+//! separately as fields for `field:value` queries.
+//!
+//! Each document also records its outgoing local links for the link graph.
+//! Only per-document data is persisted; postings are rebuilt on load.
+//!
+//! Query syntax (clauses are ANDed, `-` negates a clause):
+//!
+//! - `word` — a term
+//! - `"exact phrase"` — consecutive terms
+//! - `pre*` — any term starting with `pre`
+//! - `/regex/` — a regular expression over the file text
+//! - `field:value`, `field:"two words"` — a frontmatter field containing the value
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use super::quick_open::{outline, Heading};
+use super::{is_hidden, is_markdown, markdown_files, relative_path};
+use crate::links::{extract_links, has_scheme, FoundLink};
+use crate::tags::Tag;
+use crate::tasks::Task;
+
+/// Bumped when the persisted layout or tokeniser changes
+const INDEX_VERSION: u32 = 7;
+pub const DEFAULT_LIMIT: usize = 50;
+const MAX_MATCHES_PER_HIT: usize = 3;
+/// Characters of context kept before the first match in a snippet
+const SNIPPET_BEFORE: usize = 40;
+const SNIPPET_LENGTH: usize = 160;
+// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+fn token_re() -> &'static Regex {
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"[\p{Han}\p{Hiragana}\p{Katakana}\p{Hangul}]|[[\p{L}\p{M}\p{N}_]&&[^\p{Han}\p{Hiragana}\p{Katakana}\p{Hangul}]]+",
+        )
+        .unwrap()
+    })
+}
+
+/// Lower-cased tokens of `text`.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    token_re().find_iter(text).map(|m| m.as_str().to_lowercase())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct IndexedDocument {
+    /// Milliseconds since the epoch
+    modified: u64,
+    size: u64,
+    pub title: String,
+    /// Lower-cased frontmatter values by lower-cased key
+    pub fields: BTreeMap<String, Vec<String>>,
+    /// Frontmatter values as written, for metadata queries
+    pub frontmatter: BTreeMap<String, serde_json::Value>,
+    /// Number of tokens in the body
+    length: u32,
+    terms: HashMap<String, Vec<u32>>,
+    /// Outgoing links to local files and notes, unresolved
+    pub links: Vec<FoundLink>,
+    /// Frontmatter and inline tags
+    pub tags: Vec<Tag>,
+    pub tasks: Vec<Task>,
+    /// Outline for quick open
+    pub headings: Vec<Heading>,
+}
+
+fn yaml_scalar(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Scalar and list-of-scalar frontmatter values, lower-cased.
+fn frontmatter_fields(metadata: &serde_yaml::Value) -> BTreeMap<String, Vec<String>> {
+    let mut fields = BTreeMap::new();
+    let serde_yaml::Value::Mapping(map) = metadata else { return fields };
+    for (key, value) in map {
+        let Some(key) = yaml_scalar(key) else { continue };
+        let values: Vec<String> = match value {
+            serde_yaml::Value::Sequence(items) => items.iter().filter_map(yaml_scalar).collect(),
+            other => yaml_scalar(other).into_iter().collect(),
+        };
+        fields.insert(key.to_lowercase(), values.into_iter().map(|v| v.to_lowercase()).collect());
+    }
+    fields
+}
+
+/// Frontmatter values keyed by their scalar keys.
+fn frontmatter_values(metadata: &serde_yaml::Value) -> BTreeMap<String, serde_json::Value> {
+    let serde_yaml::Value::Mapping(map) = metadata else { return BTreeMap::new() };
+    map.iter()
+        .filter_map(|(key, value)| Some((yaml_scalar(key)?, serde_json::to_value(value).unwrap_or_default())))
+        .collect()
+}
+
+/// Frontmatter `title`, else the first ATX heading, else the file stem.
+fn document_title(path: &Path, body: &str, metadata: &serde_yaml::Value) -> String {
+    if let Some(title) = metadata.get("title").and_then(yaml_scalar) {
+        return title;
+    }
+    body.lines()
+        .find_map(|line| line.strip_prefix("# ").map(|t| t.trim().to_string()))
+        .unwrap_or_else(|| path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default())
+}
+
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64;
+    Some((modified, metadata.len()))
+}
+
+fn index_document(path: &Path, content: &str, modified: u64, size: u64) -> IndexedDocument {
+    let (body, metadata) = crate::split_frontmatter(content);
+    let metadata: serde_yaml::Value = serde_yaml::from_str(&metadata).unwrap_or_default();
+    let fields = frontmatter_fields(&metadata);
+    let frontmatter = frontmatter_values(&metadata);
+    let title = document_title(path, body, &metadata);
+    let mut terms: HashMap<String, Vec<u32>> = HashMap::new();
+    let mut length = 0;
+    for (position, token) in tokenize(body).enumerate() {
+        terms.entry(token).or_default().push(position as u32);
+        length = position as u32 + 1;
+    }
+    let links = extract_links(content).into_iter().filter(|link| !has_scheme(&link.target)).collect();
+    let tags = crate::tags::extract(content);
+    let tasks = crate::tasks::extract(content);
+    let headings = outline(content);
+    IndexedDocument { modified, size, title, fields, frontmatter, length, terms, links, tags, tasks, headings }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    /// By path relative to the workspace root, `/`-separated
+    documents: BTreeMap<String, IndexedDocument>,
+    #[serde(skip)]
+    postings: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Default for SearchIndex {
+    fn default() -> Self {
+        Self { version: INDEX_VERSION, documents: BTreeMap::new(), postings: BTreeMap::new() }
+    }
+}
+
+impl SearchIndex {
+    /// Load a persisted index; an unreadable or outdated file gives an empty index.
+    pub fn load(path: &Path) -> Self {
+        let mut index = fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<SearchIndex>(&bytes).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_default();
+        let documents: Vec<String> = index.documents.keys().cloned().collect();
+        for key in documents {
+            index.add_postings(&key);
+        }
+        index
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_vec(self).map_err(|e| e.to_string())?;
+        // Write then rename so a crash never leaves a truncated index
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Cannot write {}: {}", tmp.display(), e))?;
+        fs::rename(&tmp, path).map_err(|e| e.to_string())
+    }
+
+    pub(crate) fn documents(&self) -> &BTreeMap<String, IndexedDocument> {
+        &self.documents
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    fn add_postings(&mut self, key: &str) {
+        if let Some(document) = self.documents.get(key) {
+            for term in document.terms.keys() {
+                self.postings.entry(term.clone()).or_default().insert(key.to_string());
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        let Some(document) = self.documents.remove(key) else { return false };
+        for term in document.terms.keys() {
+            if let Some(keys) = self.postings.get_mut(term) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+        true
+    }
+
+    /// Re-index `path` if it changed since it was indexed.
+    fn update_file(&mut self, root: &Path, path: &Path) -> bool {
+        let key = relative_path(root, path);
+        let Some((modified, size)) = file_stamp(path) else {
+            return self.remove(&key);
+        };
+        if let Some(document) = self.documents.get(&key) {
+            if document.modified == modified && document.size == size {
+                return false;
+            }
+        }
+        let Ok(content) = fs::read_to_string(path) else {
+            return self.remove(&key);
+        };
+        self.remove(&key);
+        self.documents.insert(key.clone(), index_document(path, &content, modified, size));
+        self.add_postings(&key);
+        true
+    }
+
+    /// Drop documents under `prefix` (a folder) that aren't in `keep`.
+    fn remove_missing(&mut self, prefix: &str, keep: &BTreeSet<String>) -> Vec<String> {
+        let stale: Vec<String> = self
+            .documents
+            .keys()
+            .filter(|key| (prefix.is_empty() || key.starts_with(prefix)) && !keep.contains(*key))
+            .cloned()
+            .collect();
+        for key in &stale {
+            self.remove(key);
+        }
+        stale
+    }
+
+    /// Bring the whole index in line with the files under `root`; returns changed paths.
+    pub fn sync(&mut self, root: &Path) -> Vec<String> {
+        self.refresh(root, root)
+    }
+
+    /// Apply a change to a file or folder; returns the relative paths that changed.
+    pub fn refresh(&mut self, root: &Path, path: &Path) -> Vec<String> {
+        if path != root && is_hidden(root, path) {
+            return Vec::new();
+        }
+        let key = relative_path(root, path);
+        if path.is_dir() {
+            let files = markdown_files(path);
+            let keep: BTreeSet<String> = files.iter().map(|file| relative_path(root, file)).collect();
+            let mut changed: Vec<String> = files
+                .iter()
+                .filter(|file| self.update_file(root, file))
+                .map(|file| relative_path(root, file))
+                .collect();
+            let prefix = if key.is_empty() { key } else { format!("{}/", key) };
+            changed.extend(self.remove_missing(&prefix, &keep));
+            changed
+        } else if path.exists() {
+            if is_markdown(path) && self.update_file(root, path) {
+                vec![key]
+            } else {
+                Vec::new()
+            }
+        } else {
+            // A deleted file, or a deleted or renamed-away folder
+            let mut changed = if self.remove(&key) { vec![key.clone()] } else { Vec::new() };
+            changed.extend(self.remove_missing(&format!("{}/", key), &BTreeSet::new()));
+            changed
+        }
+    }
+
+    fn idf(&self, term_documents: usize) -> f64 {
+        let n = self.documents.len() as f64;
+        let df = term_documents as f64;
+        (1.0 + (n - df + 0.5) / (df + 0.5)).ln()
+    }
+
+    fn average_length(&self) -> f64 {
+        let total: u64 = self.documents.values().map(|d| d.length as u64).sum();
+        (total as f64 / self.documents.len().max(1) as f64).max(1.0)
+    }
+
+    fn bm25(&self, document: &IndexedDocument, tf: usize, df: usize, average_length: f64) -> f64 {
+        let tf = tf as f64;
+        let norm = 1.0 - B + B * document.length as f64 / average_length;
+        self.idf(df) * tf * (K1 + 1.0) / (tf + K1 * norm)
+    }
+
+    /// Run `query`, reading matched files under `root` for snippets.
+    pub fn search(&self, root: &Path, query: &Query, limit: usize) -> Vec<SearchHit> {
+        // Narrow candidates with the indexed positive clauses
+        let mut candidates: Option<BTreeSet<&String>> = None;
+        for clause in query.clauses.iter().filter(|c| !c.negated) {
+            let keys: BTreeSet<&String> = match &clause.kind {
+                ClauseKind::Terms(tokens) => match tokens.first() {
+                    Some(first) => self.postings.get(first).map(|keys| keys.iter().collect()).unwrap_or_default(),
+                    None => continue,
+                },
+                ClauseKind::Prefix(prefix) => self
+                    .postings
+                    .range(prefix.clone()..)
+                    .take_while(|(term, _)| term.starts_with(prefix.as_str()))
+                    .flat_map(|(_, keys)| keys.iter())
+                    .collect(),
+                _ => continue,
+            };
+            candidates = Some(match candidates {
+                Some(current) => current.intersection(&keys).copied().collect(),
+                None => keys,
+            });
+        }
+        let candidates: Vec<&String> = match candidates {
+            Some(keys) => keys.into_iter().collect(),
+            None => self.documents.keys().collect(),
+        };
+
+        let average_length = self.average_length();
+        let mut scored: Vec<(f64, &String)> = Vec::new();
+        'documents: for key in candidates {
+            let document = &self.documents[key];
+            let mut text: Option<String> = None;
+            let mut score = 0.0;
+            for clause in &query.clauses {
+                let clause_score = match &clause.kind {
+                    ClauseKind::Terms(tokens) => {
+                        let tf = phrase_count(document, tokens);
+                        let df = tokens.iter().map(|t| self.postings.get(t).map_or(0, BTreeSet::len)).max().unwrap_or(0);
+                        let mut s = (tf > 0).then(|| self.bm25(document, tf, df, average_length));
+                        let phrase = tokens.join(" ");
+                        if s.is_some() && tokenize(&document.title).collect::<Vec<_>>().join(" ").contains(&phrase) {
+                            s = s.map(|s| s * 2.0);
+                        }
+                        s
+                    }
+                    ClauseKind::Prefix(prefix) => {
+                        let tf: usize = document
+                            .terms
+                            .iter()
+                            .filter(|(term, _)| term.starts_with(prefix.as_str()))
+                            .map(|(_, positions)| positions.len())
+                            .sum();
+                        (tf > 0).then(|| self.bm25(document, tf, 1, average_length))
+                    }
+                    ClauseKind::Field(name, value) => document
+                        .fields
+                        .get(name)
+                        .is_some_and(|values| values.iter().any(|v| v.contains(value.as_str())))
+                        .then_some(1.0),
+                    ClauseKind::Regex(re) => {
+                        let text = text.get_or_insert_with(|| fs::read_to_string(root.join(key)).unwrap_or_default());
+                        re.is_match(text).then_some(1.0)
+                    }
+                };
+                match (clause_score, clause.negated) {
+                    (Some(s), false) => score += s,
+                    (None, true) => {}
+                    _ => continue 'documents,
+                }
+            }
+            scored.push((score, key));
+        }
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        scored.truncate(limit);
+
+        let highlight = query.highlight_regex();
+        scored
+            .into_iter()
+            .map(|(score, key)| {
+                let path = root.join(key);
+                let matches = match &highlight {
+                    Some(re) => fs::read_to_string(&path).map(|text| line_matches(&text, re)).unwrap_or_default(),
+                    None => Vec::new(),
+                };
+                SearchHit {
+                    path: path.to_string_lossy().to_string(),
+                    relative_path: key.clone(),
+                    title: self.documents[key].title.clone(),
+                    score: (score * 1000.0).round() / 1000.0,
+                    matches,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Occurrences of `tokens` as consecutive terms.
+pub(crate) fn phrase_count(document: &IndexedDocument, tokens: &[String]) -> usize {
+    let Some(first) = tokens.first().and_then(|t| document.terms.get(t)) else { return 0 };
+    let rest: Vec<&Vec<u32>> = match tokens[1..].iter().map(|t| document.terms.get(t)).collect::<Option<Vec<_>>>() {
+        Some(rest) => rest,
+        None => return 0,
+    };
+    first
+        .iter()
+        .filter(|&&start| {
+            rest.iter()
+                .enumerate()
+                .all(|(i, positions)| positions.binary_search(&(start + i as u32 + 1)).is_ok())
+        })
+        .count()
+}
+
+/// Up to `MAX_MATCHES_PER_HIT` matching lines, as snippets with highlight ranges.
+fn line_matches(text: &str, re: &Regex) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let ranges: Vec<(usize, usize)> = re.find_iter(line).filter(|m| !m.is_empty()).map(|m| (m.start(), m.end())).collect();
+        let Some(&(first, _)) = ranges.first() else { continue };
+
+        // Window of characters around the first match
+        let first_char = line[..first].chars().count();
+        let start_char = first_char.saturating_sub(SNIPPET_BEFORE);
+        let start = line.char_indices().nth(start_char).map_or(line.len(), |(i, _)| i);
+        let end = line[start..].char_indices().nth(SNIPPET_LENGTH).map_or(line.len(), |(i, _)| start + i);
+        let ellipsis = if start > 0 { "…" } else { "" };
+        let snippet = format!("{}{}{}", ellipsis, &line[start..end], if end < line.len() { "…" } else { "" });
+
+        let utf16 = |byte: usize| ellipsis.encode_utf16().count() + line[start..byte].encode_utf16().count();
+        let highlights = ranges
+            .into_iter()
+            .filter(|&(s, e)| s >= start && e <= end)
+            .map(|(s, e)| (utf16(s), utf16(e)))
+            .collect();
+        matches.push(SearchMatch { line: index + 1, snippet, highlights });
+        if matches.len() == MAX_MATCHES_PER_HIT {
+            break;
+        }
+    }
+    matches
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMatch {
+    /// 1-based line in the file
+    pub line: usize,
+    pub snippet: String,
+    /// UTF-16 `[start, end)` ranges in `snippet`
+    pub highlights: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub path: String,
+    pub relative_path: String,
+    pub title: String,
+    pub score: f64,
+    pub matches: Vec<SearchMatch>,
+}
+
+#[derive(Debug, Clone)]
+enum ClauseKind {
+    /// One term, or a phrase of consecutive terms
+    Terms(Vec<String>),
+    Prefix(String),
+    Regex(Regex),
+    /// Lower-cased field name and value
+    Field(String, String),
+}
+
+#[derive(Debug, Clone)]
+struct Clause {
+    kind: ClauseKind,
+    negated: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    clauses: Vec<Clause>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let chars: Vec<char> = input.chars().collect();
+        let mut clauses = Vec::new();
+        let mut i = 0;
+        let read_until = |from: usize, end: char| -> (String, usize) {
+            let mut j = from;
+            let mut out = String::new();
+            while j < chars.len() && chars[j] != end {
+                // `\/` inside a regex stands for a slash
+                if end == '/' && chars[j] == '\\' && chars.get(j + 1) == Some(&'/') {
+                    out.push('/');
+                    j += 2;
+                    continue;
+                }
+                out.push(chars[j]);
+                j += 1;
+            }
+            (out, (j + 1).min(chars.len()))
+        };
+
+        while i < chars.len() {
+            if chars[i].is_whitespace() {
+                i += 1;
+                continue;
+            }
+            let negated = chars[i] == '-' && chars.get(i + 1).is_some_and(|c| !c.is_whitespace());
+            if negated {
+                i += 1;
+            }
+            let kind = match chars[i] {
+                '"' => {
+                    let (phrase, next) = read_until(i + 1, '"');
+                    i = next;
+                    ClauseKind::Terms(tokenize(&phrase).collect())
+                }
+                '/' => {
+                    let (pattern, next) = read_until(i + 1, '/');
+                    i = next;
+                    let re = RegexBuilder::new(&pattern)
+                        .multi_line(true)
+                        .build()
+                        .map_err(|e| format!("Invalid regex /{}/: {}", pattern, e))?;
+                    ClauseKind::Regex(re)
+                }
+                _ => {
+                    let mut word = String::new();
+                    while i < chars.len() && !chars[i].is_whitespace() {
+                        if chars[i] == '"' {
+                            let (quoted, next) = read_until(i + 1, '"');
+                            word.push_str(&quoted);
+                            i = next;
+                        } else {
+                            word.push(chars[i]);
+                            i += 1;
+                        }
+                    }
+                    Self::word_clause(&word)
+                }
+            };
+            let empty = matches!(&kind, ClauseKind::Terms(t) if t.is_empty())
+                || matches!(&kind, ClauseKind::Prefix(p) if p.is_empty());
+            if !empty {
+                clauses.push(Clause { kind, negated });
+            }
+        }
+        if clauses.iter().all(|c| c.negated) {
+            return Err("Search for at least one term".to_string());
+        }
+        Ok(Self { clauses })
+    }
+
+    fn word_clause(word: &str) -> ClauseKind {
+        if let Some((name, value)) = word.split_once(':') {
+            let is_field = !name.is_empty()
+                && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+                && !value.is_empty()
+                && !value.starts_with("//");
+            if is_field {
+                return ClauseKind::Field(name.to_lowercase(), value.to_lowercase());
+            }
+        }
+        if let Some(prefix) = word.strip_suffix('*') {
+            let tokens: Vec<String> = tokenize(prefix).collect();
+            if tokens.len() == 1 {
+                return ClauseKind::Prefix(tokens.into_iter().next().unwrap());
+            }
+        }
+        ClauseKind::Terms(tokenize(word).collect())
+    }
+
+    /// Regex finding what the positive clauses matched, for snippets.
+    fn highlight_regex(&self) -> Option<Regex> {
+        let is_cjk = |t: &str| token_re().find(t).is_some_and(|m| m.as_str().chars().count() == 1 && !t.is_ascii());
+        let word = |t: &str| {
+            if is_cjk(t) {
+                regex::escape(t)
+            } else {
+                format!(r"\b{}\b", regex::escape(t))
+            }
+        };
+        let alternatives: Vec<String> = self
+            .clauses
+            .iter()
+            .filter(|c| !c.negated)
+            .filter_map(|c| match &c.kind {
+                ClauseKind::Terms(tokens) => Some(format!(
+                    "(?i:{})",
+                    tokens.iter().map(|t| word(t)).collect::<Vec<_>>().join(r"\W*")
+                )),
+                ClauseKind::Prefix(prefix) => Some(format!(r"(?i:\b{}\w*)", regex::escape(prefix))),
+                ClauseKind::Regex(re) => Some(format!("(?:{})", re.as_str())),
+                ClauseKind::Field(..) => None,
+            })
+            .collect();
+        if alternatives.is_empty() {
+            return None;
+        }
+        Regex::new(&alternatives.join("|")).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn search(index: &SearchIndex, root: &Path, query: &str) -> Vec<String> {
+        index
+            .search(root, &Query::parse(query).unwrap(), DEFAULT_LIMIT)
+            .into_iter()
+            .map(|hit| hit.relative_path)
+            .collect()
+    }
+
+    const FILES: &[(&str, &str)] = &[
+        ("rust.md", "---\ntitle: Rust Notes\ntags: [lang, systems]\n---\n# Ownership\n\nBorrowing rules keep memory safe.\nThe borrow checker is strict.\n"),
+        ("notes/python.md", "---\ntags: lang\nstatus: draft\n---\nPython memory is garbage collected.\n"),
+        ("notes/中文.md", "# 笔记\n\n这是中文搜索测试。\n"),
+        (".git/ignored.md", "memory\n"),
+        ("readme.txt", "memory\n"),
+    ];
+
+    #[test]
+    fn test_terms_phrases_and_prefixes() {
+        let root = TempDir::with_files("search-terms", FILES);
+        let mut index = SearchIndex::default();
+        assert_eq!(index.sync(&root).len(), 3);
+
+        assert_eq!(search(&index, &root, "memory"), vec!["notes/python.md", "rust.md"]);
+        assert_eq!(search(&index, &root, "memory safe"), vec!["rust.md"]);
+        assert_eq!(search(&index, &root, "\"garbage collected\""), vec!["notes/python.md"]);
+        assert!(search(&index, &root, "\"collected garbage\"").is_empty());
+        assert_eq!(search(&index, &root, "borrow*"), vec!["rust.md"]);
+        assert_eq!(search(&index, &root, "memory -python"), vec!["rust.md"]);
+        assert_eq!(search(&index, &root, "中文"), vec!["notes/中文.md"]);
+    }
+
+    #[test]
+    fn test_fields_regex_and_snippets() {
+        let root = TempDir::with_files("search-fields", FILES);
+        let mut index = SearchIndex::default();
+        index.sync(&root);
+
+        assert_eq!(search(&index, &root, "tags:lang"), vec!["notes/python.md", "rust.md"]);
+        assert_eq!(search(&index, &root, "tags:lang status:draft"), vec!["notes/python.md"]);
+        assert_eq!(search(&index, &root, "title:\"rust notes\""), vec!["rust.md"]);
+        assert_eq!(search(&index, &root, r"/borrow\w* checker/"), vec!["rust.md"]);
+        assert!(Query::parse("/[/").is_err());
+
+        let hits = index.search(&root, &Query::parse("borrow*").unwrap(), DEFAULT_LIMIT);
+        assert_eq!(hits[0].title, "Rust Notes");
+        let lines: Vec<(usize, &str)> = hits[0].matches.iter().map(|m| (m.line, m.snippet.as_str())).collect();
+        assert_eq!(lines, vec![(7, "Borrowing rules keep memory safe."), (8, "The borrow checker is strict.")]);
+        assert_eq!(hits[0].matches[1].highlights, vec![(4, 10)]);
+    }
+
+    #[test]
+    fn test_incremental_updates_and_persistence() {
+        let root = TempDir::with_files("search-incremental", FILES);
+        let mut index = SearchIndex::default();
+        index.sync(&root);
+        assert!(index.sync(&root).is_empty());
+
+        fs::write(root.join("notes/python.md"), "Now about snakes.\n").unwrap();
+        assert_eq!(index.refresh(&root, &root.join("notes/python.md")), vec!["notes/python.md"]);
+        assert_eq!(search(&index, &root, "memory"), vec!["rust.md"]);
+        assert_eq!(search(&index, &root, "snakes"), vec!["notes/python.md"]);
+
+        fs::remove_dir_all(root.join("notes")).unwrap();
+        let mut removed = index.refresh(&root, &root.join("notes"));
+        removed.sort();
+        assert_eq!(removed, vec!["notes/python.md", "notes/中文.md"]);
+
+        let saved = root.join("index.json");
+        index.save(&saved).unwrap();
+        let loaded = SearchIndex::load(&saved);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(search(&loaded, &root, "borrow*"), vec!["rust.md"]);
+    }
+}
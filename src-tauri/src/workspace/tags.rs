@@ -0,0 +1,132 @@
+//! Tag index: every tag in the workspace and the notes carrying it
+//!
+//! Tags come from the search index, so they follow the watcher like search
+//! and backlinks. A note tagged `#project/alpha` counts towards `project`
+//! as well.
+
+use super::search::SearchIndex;
+use crate::tags::{key, with_parents};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+    /// As first written in the workspace
+    pub tag: String,
+    /// Notes with the tag or one of its children
+    pub notes: usize,
+    /// Times the tag itself is written
+    pub occurrences: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaggedNote {
+    pub path: String,
+    pub relative_path: String,
+    pub title: String,
+    /// Matching tags as written, the tag itself or nested below it
+    pub tags: Vec<String>,
+    /// Lines of the inline occurrences; frontmatter tags have none
+    pub lines: Vec<usize>,
+}
+
+/// All tags with note counts, sorted by name.
+pub fn tag_counts(index: &SearchIndex) -> Vec<TagCount> {
+    let mut counts: BTreeMap<String, TagCount> = BTreeMap::new();
+    for document in index.documents().values() {
+        let mut seen = BTreeSet::new();
+        for tag in &document.tags {
+            for (depth, name) in with_parents(&tag.name).enumerate() {
+                let entry = counts
+                    .entry(key(name))
+                    .or_insert_with(|| TagCount { tag: name.to_string(), notes: 0, occurrences: 0 });
+                if depth == 0 {
+                    entry.occurrences += 1;
+                }
+                if seen.insert(key(name)) {
+                    entry.notes += 1;
+                }
+            }
+        }
+    }
+    counts.into_values().collect()
+}
+
+/// Notes tagged `tag` or a tag nested below it, case-insensitively.
+pub fn notes_for_tag(index: &SearchIndex, root: &Path, tag: &str) -> Vec<TaggedNote> {
+    let wanted = key(tag.trim().trim_start_matches('#').trim_end_matches('/'));
+    let nested = format!("{}/", wanted);
+    index
+        .documents()
+        .iter()
+        .filter_map(|(relative, document)| {
+            let matching: Vec<_> = document
+                .tags
+                .iter()
+                .filter(|t| {
+                    let k = key(&t.name);
+                    k == wanted || k.starts_with(&nested)
+                })
+                .collect();
+            if matching.is_empty() {
+                return None;
+            }
+            let mut tags: Vec<String> = Vec::new();
+            for t in &matching {
+                if !tags.contains(&t.name) {
+                    tags.push(t.name.clone());
+                }
+            }
+            Some(TaggedNote {
+                path: root.join(relative).to_string_lossy().to_string(),
+                relative_path: relative.clone(),
+                title: document.title.clone(),
+                tags,
+                lines: matching.iter().filter_map(|t| t.line).collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_tag_counts_and_notes() {
+        let root = TempDir::with_files(
+            "tags",
+            &[
+                ("a.md", "---\ntags: [Project/Alpha]\n---\nWork on #project/alpha and #todo.\n"),
+                ("sub/b.md", "# Beta\n\n#project/beta #project\n\n`#todo`\n"),
+            ],
+        );
+        let mut index = SearchIndex::default();
+        index.sync(&root);
+
+        let counts = tag_counts(&index);
+        let counts: Vec<(&str, usize, usize)> = counts.iter().map(|c| (c.tag.as_str(), c.notes, c.occurrences)).collect();
+        assert_eq!(
+            counts,
+            vec![("Project", 2, 1), ("Project/Alpha", 1, 2), ("project/beta", 1, 1), ("todo", 1, 1)]
+        );
+
+        let notes = notes_for_tag(&index, &root, "#PROJECT");
+        let found: Vec<(&str, Vec<&str>, Vec<usize>)> = notes
+            .iter()
+            .map(|n| (n.relative_path.as_str(), n.tags.iter().map(String::as_str).collect(), n.lines.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("a.md", vec!["Project/Alpha", "project/alpha"], vec![4]),
+                ("sub/b.md", vec!["project/beta", "project"], vec![3, 3]),
+            ]
+        );
+        assert!(notes_for_tag(&index, &root, "proj").is_empty());
+    }
+}
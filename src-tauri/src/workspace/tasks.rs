@@ -0,0 +1,69 @@
+//! Task overview: open and done task items from every note in the workspace
+
+use super::search::SearchIndex;
+use crate::tasks::Task;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceTask {
+    pub path: String,
+    pub relative_path: String,
+    /// Title of the note
+    pub title: String,
+    #[serde(flatten)]
+    pub task: Task,
+}
+
+/// Tasks matching `done` (all when `None`), by due date with undated tasks
+/// last, then priority, then file and line.
+pub fn all_tasks(index: &SearchIndex, root: &Path, done: Option<bool>) -> Vec<WorkspaceTask> {
+    let mut tasks: Vec<WorkspaceTask> = index
+        .documents()
+        .iter()
+        .flat_map(|(relative, document)| {
+            document
+                .tasks
+                .iter()
+                .filter(move |task| done.is_none_or(|done| task.done == done))
+                .map(move |task| WorkspaceTask {
+                    path: root.join(relative).to_string_lossy().to_string(),
+                    relative_path: relative.clone(),
+                    title: document.title.clone(),
+                    task: task.clone(),
+                })
+        })
+        .collect();
+    tasks.sort_by(|a, b| {
+        (a.task.due.is_none(), &a.task.due, a.task.priority.is_none(), a.task.priority)
+            .cmp(&(b.task.due.is_none(), &b.task.due, b.task.priority.is_none(), b.task.priority))
+            .then_with(|| (&a.relative_path, a.task.line).cmp(&(&b.relative_path, b.task.line)))
+    });
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_tasks_sorted_by_due_and_priority() {
+        let root = TempDir::with_files(
+            "tasks",
+            &[
+                ("a.md", "- [ ] later 📅 2026-03-01\n- [ ] undated\n- [x] done 📅 2026-01-01\n"),
+                ("b.md", "# B\n\n- [ ] urgent 🔺\n- [ ] sooner due:2026-02-01\n"),
+            ],
+        );
+        let mut index = SearchIndex::default();
+        index.sync(&root);
+
+        let open = all_tasks(&index, &root, Some(false));
+        let open: Vec<(&str, usize)> = open.iter().map(|t| (t.relative_path.as_str(), t.task.line)).collect();
+        assert_eq!(open, vec![("b.md", 4), ("a.md", 1), ("b.md", 3), ("a.md", 2)]);
+        assert_eq!(all_tasks(&index, &root, None).len(), 5);
+        assert_eq!(all_tasks(&index, &root, Some(true))[0].task.text, "done 📅 2026-01-01");
+    }
+}
@@ -0,0 +1,68 @@
+/**
+ * 图片资源清理：未引用、丢失与重复的图片
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import type { FileEdit } from './workspace';
+
+export interface AssetOptions {
+	/** 粘贴图片保存的文件夹名，默认 img */
+	imageDirectory?: string;
+}
+
+export interface Asset {
+	path: string;
+	relativePath: string;
+	size: number;
+	/** 被扫描笔记引用的次数 */
+	references: number;
+}
+
+export interface MissingAsset {
+	file: string;
+	/** 1 起始行号 */
+	line: number;
+	/** 1 起始 UTF-16 列 */
+	column: number;
+	target: string;
+}
+
+export interface DuplicateGroup {
+	/** 内容的 SHA-256 */
+	hash: string;
+	size: number;
+	/** 保留引用最多的一份 */
+	keep: Asset;
+	copies: Asset[];
+}
+
+export interface AssetReport {
+	filesChecked: number;
+	/** 仅统计图片文件夹内的图片 */
+	unused: Asset[];
+	missing: MissingAsset[];
+	duplicates: DuplicateGroup[];
+}
+
+export interface CleanupRequest {
+	/** 要删除的未引用图片路径 */
+	delete?: string[];
+	/** 要合并到保留副本的重复组 hash */
+	deduplicate?: string[];
+}
+
+export interface CleanupResult {
+	deleted: string[];
+	/** 链接改为指向保留副本的笔记 */
+	edits: FileEdit[];
+}
+
+/** path 为文件夹或单个文档；单个文档只报告其自身的图片文件夹。引用按已打开工作区的全部笔记统计 */
+export function scanAssets(path: string, options: AssetOptions = {}): Promise<AssetReport> {
+	return invoke<AssetReport>('scan_assets', { path, options });
+}
+
+/** 先重新扫描，只处理仍被报告的项；path 须在已打开的工作区内 */
+export function cleanAssets(path: string, request: CleanupRequest, options: AssetOptions = {}): Promise<CleanupResult> {
+	return invoke<CleanupResult>('clean_assets', { path, options, request });
+}
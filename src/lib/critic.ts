@@ -0,0 +1,51 @@
+/**
+ * CriticMarkup 审阅
+ * 接受/拒绝修订标记，偏移量在 JS (UTF-16) 与 Rust (UTF-8 字节) 之间转换
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+
+export type CriticChangeKind = 'addition' | 'deletion' | 'substitution' | 'highlight' | 'comment';
+
+export interface CriticChange {
+	kind: CriticChangeKind;
+	/** UTF-16 偏移 (已从字节偏移转换) */
+	start: number;
+	end: number;
+	original: string;
+	replacement: string;
+	comment: string | null;
+}
+
+const encoder = new TextEncoder();
+
+function toByteOffset(text: string, offset: number): number {
+	return encoder.encode(text.slice(0, offset)).length;
+}
+
+function toCharOffset(text: string, byteOffset: number): number {
+	const bytes = encoder.encode(text);
+	return new TextDecoder().decode(bytes.slice(0, byteOffset)).length;
+}
+
+export async function listCriticChanges(content: string): Promise<CriticChange[]> {
+	const changes = await invoke<CriticChange[]>('critic_list_changes', { content });
+	return changes.map((change) => ({
+		...change,
+		start: toCharOffset(content, change.start),
+		end: toCharOffset(content, change.end),
+	}));
+}
+
+export function acceptAllChanges(content: string): Promise<string> {
+	return invoke<string>('critic_accept_all', { content });
+}
+
+export function rejectAllChanges(content: string): Promise<string> {
+	return invoke<string>('critic_reject_all', { content });
+}
+
+/** 接受或拒绝光标 (UTF-16 偏移) 所在的修订 */
+export function applyChangeAt(content: string, offset: number, accept: boolean): Promise<string> {
+	return invoke<string>('critic_apply_change', { content, offset: toByteOffset(content, offset), accept });
+}
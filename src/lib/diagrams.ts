@@ -94,6 +94,13 @@ export const RUST_RENDERERS = {
 		name: 'svgbob',
 		package: 'svgbob',
 		description: '纯 Rust ASCII 转 SVG'
+	},
+	// Pikchr 渲染库 (C 库，随 Rust 后端编译)
+	'pikchr-c': {
+		id: 'pikchr-c',
+		name: 'pikchr',
+		package: 'pikchr.c',
+		description: 'Pikchr 官方 C 实现，离线渲染'
 	}
 } as const;
 
@@ -212,8 +219,11 @@ export const DIAGRAM_TYPES: DiagramType[] = [
 	{
 		id: 'pikchr',
 		name: 'Pikchr',
-		supportedModes: ['kroki', 'source'],
+		supportedModes: ['rust', 'kroki', 'source'],
+		// 后端编译了 pikchr.c 时由 settings 改为 rust (见 pikchr_available)
 		defaultMode: 'kroki',
+		rustRenderers: [RUST_RENDERERS['pikchr-c']],
+		defaultRustRenderer: 'pikchr-c',
 		description: '简单图表'
 	},
 	{
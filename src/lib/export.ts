@@ -630,6 +630,12 @@ overflow-x: auto;
 	border: 0;
 }
 
+.markdown-body .math-display {
+	margin: 1em 0;
+	overflow-x: auto;
+	text-align: center;
+}
+
 /* Code highlighting - hljs */
 .hljs-comment, .hljs-quote { color: var(--hljs-comment, #8b949e); }
 .hljs-keyword, .hljs-selector-tag { color: var(--hljs-keyword, #ff7b72); }
@@ -1076,11 +1082,14 @@ export function generateExportHtml(
 	showToc: boolean,
 	pageSize: PdfPageSize = 'dynamic',
 	forPrint: boolean = false,
-	title: string = 'Exported Document'
+	title: string = 'Exported Document',
+	mathmlHtml?: string
 ): string {
 	// Clone the container
 	const clone = container.cloneNode(true) as HTMLElement;
 
+	if (mathmlHtml) replaceMathWithMathml(clone, mathmlHtml);
+
 	// Remove interactive elements (but keep diagram-toggle-btn for HTML export)
 	if (forPrint) {
 		// For PDF: remove TOC sidebar, diagram toggle buttons, and other interactive elements
@@ -1443,13 +1452,43 @@ ${dynamicHeightStyle}
 	return html;
 }
 
+/**
+ * Swap KaTeX output (which needs KaTeX's CSS and fonts) for MathML rendered by the backend.
+ * Preview formulas are marked with data-math and matched to the backend's math spans in
+ * document order; nothing is replaced if the counts differ, and formulas the backend
+ * couldn't convert keep their KaTeX rendering.
+ */
+function replaceMathWithMathml(clone: HTMLElement, mathmlHtml: string): void {
+	const rendered = Array.from(clone.querySelectorAll('[data-math]'));
+	const doc = new DOMParser().parseFromString(mathmlHtml, 'text/html');
+	const converted = Array.from(doc.querySelectorAll('span[data-math-style]'));
+	if (rendered.length !== converted.length) return;
+
+	rendered.forEach((el, i) => {
+		const math = converted[i];
+		if (!math.classList.contains('math-mathml')) return;
+		if (math.getAttribute('data-math-style') === 'display') {
+			const div = document.createElement('div');
+			div.className = 'math-display';
+			div.innerHTML = math.innerHTML;
+			el.replaceWith(div);
+		} else {
+			el.innerHTML = math.innerHTML;
+			el.classList.remove('katex');
+		}
+	});
+}
+
 /**
  * Export as HTML file
+ *
+ * With the markdown source, formulas are exported as MathML so the file doesn't depend on KaTeX.
  */
 export async function exportAsHtml(
 	container: HTMLElement,
 	showToc: boolean,
-	defaultFileName: string
+	defaultFileName: string,
+	markdown?: string
 ): Promise<boolean> {
 	const filePath = await save({
 		defaultPath: `${defaultFileName}.html`,
@@ -1458,7 +1497,12 @@ export async function exportAsHtml(
 
 	if (!filePath) return false;
 
-	const html = generateExportHtml(container, showToc, 'dynamic', false, defaultFileName);
+	const mathmlHtml = markdown
+		? await invoke<{ html: string }>('render_markdown', { content: markdown, options: { mathml: true } })
+				.then((res) => res.html)
+				.catch(() => undefined)
+		: undefined;
+	const html = generateExportHtml(container, showToc, 'dynamic', false, defaultFileName, mathmlHtml);
 	await invoke('save_file_content', { path: filePath, content: html });
 	return true;
 }
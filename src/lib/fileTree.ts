@@ -0,0 +1,66 @@
+/**
+ * 文件树 (侧边栏资源管理器)：遵循 .gitignore / .ignore，可限定深度与过滤
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export type EntryKind = 'file' | 'directory' | 'symlink';
+
+export interface FileEntry {
+	name: string;
+	path: string;
+	relativePath: string;
+	kind: EntryKind;
+	/** 字节；目录为 0 */
+	size: number;
+	/** 毫秒时间戳 */
+	modified: number | null;
+	isMarkdown: boolean;
+	isImage: boolean;
+	/** 已列出的子项；文件及超出深度的目录为 null */
+	children: FileEntry[] | null;
+}
+
+export interface TreeOptions {
+	/** 1 仅列出当前目录；不设则不限 */
+	depth?: number;
+	/** 显示点文件 (.git 始终隐藏) */
+	showHidden?: boolean;
+	/** 默认 true */
+	respectIgnore?: boolean;
+	/** 非 all 时省略没有匹配文件的目录 */
+	filter?: 'all' | 'markdown' | 'images' | 'notes';
+	/** 目录始终在前 */
+	sort?: 'name' | 'modified' | 'size';
+}
+
+export type ChangeKind = 'add' | 'remove' | 'rename' | 'modify';
+
+export interface TreeEvent {
+	kind: ChangeKind;
+	path: string;
+	relativePath: string;
+	/** 重命名前的路径 */
+	oldPath: string | null;
+	/** 删除时为 null */
+	entry: FileEntry | null;
+}
+
+export function getFileTree(path: string, options: TreeOptions = {}): Promise<FileEntry[]> {
+	return invoke<FileEntry[]>('list_file_tree', { path, options });
+}
+
+/** 同一时间只监听一个目录，再次调用会替换 */
+export function watchDirectory(path: string, options: TreeOptions = {}): Promise<void> {
+	return invoke('watch_directory', { path, options });
+}
+
+export function unwatchDirectory(): Promise<void> {
+	return invoke('unwatch_directory');
+}
+
+/** 每批变更回调一次 */
+export function onDirectoryChanged(callback: (events: TreeEvent[]) => void): Promise<UnlistenFn> {
+	return listen<TreeEvent[]>('directory-changed', (event) => callback(event.payload));
+}
@@ -0,0 +1,17 @@
+/**
+ * Markdown 格式化 (comrak CommonMark writer)
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+
+export interface FormatOptions {
+	listMarker?: 'dash' | 'star' | 'plus';
+	emphasis?: 'star' | 'underscore';
+	strong?: 'star' | 'underscore';
+	alignTables?: boolean;
+	headingStyle?: 'atx' | 'setext';
+}
+
+export function formatMarkdown(content: string, options?: FormatOptions): Promise<string> {
+	return invoke<string>('format_markdown', { content, options });
+}
@@ -62,6 +62,9 @@ export interface Translations {
 	copyReference: string;
 	saveImageAs: string;
 	saveDiagramAsSvg: string;
+	saveDiagramAsPng: string;
+	copyDiagramAsImage: string;
+	copyCodeAsImage: string;
 	undo: string;
 	redo: string;
 	editFile: string;
@@ -144,6 +147,9 @@ const translations: Record<Locale, Translations> = {
 		copyReference: '复制引用',
 		saveImageAs: '图片另存为',
 		saveDiagramAsSvg: '图表另存为 SVG',
+		saveDiagramAsPng: '图表另存为 PNG',
+		copyDiagramAsImage: '复制图表为图片',
+		copyCodeAsImage: '复制代码为图片',
 		undo: '撤销',
 		redo: '重做',
 		editFile: '编辑文件',
@@ -225,6 +231,9 @@ const translations: Record<Locale, Translations> = {
 		copyReference: 'Copy Reference',
 		saveImageAs: 'Save Image As',
 		saveDiagramAsSvg: 'Save Diagram as SVG',
+		saveDiagramAsPng: 'Save Diagram as PNG',
+		copyDiagramAsImage: 'Copy Diagram as Image',
+		copyCodeAsImage: 'Copy Code as Image',
 		undo: 'Undo',
 		redo: 'Redo',
 		editFile: 'Edit File',
@@ -0,0 +1,53 @@
+/**
+ * 图片粘贴管线：剪贴板、拖放与 data URL 图片统一缩放、转码、去重并按模板命名
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+
+/** original 保留源格式 (剪贴板图片为 PNG)；WebP 为无损编码 */
+export type ImageFormat = 'original' | 'png' | 'jpeg' | 'webp';
+
+export interface ImageOptions {
+	/** 文档旁的图片文件夹，默认 img */
+	imageDirectory?: string;
+	format?: ImageFormat;
+	/** JPEG 质量 1-100，默认 85 */
+	quality?: number;
+	/** 超出则按比例缩小；null 或 0 不限 */
+	maxWidth?: number | null;
+	/**
+	 * 文件名模板 (不含扩展名)，默认 {note}-{date}-{n}。
+	 * 占位符：{note} {name} {date} {time} {timestamp} {hash} {n}
+	 */
+	nameTemplate?: string;
+	/** 图片文件夹中已有相同内容的文件时直接复用，默认 true */
+	deduplicate?: boolean;
+	/** 仅 macOS：剪贴板图片缩小一半 */
+	macosImageScaling?: boolean;
+}
+
+export interface SavedImage {
+	path: string;
+	/** {imageDirectory}/{filename}，用于链接 */
+	relativePath: string;
+	filename: string;
+	/** 复用了已有文件，未写入新文件 */
+	reused: boolean;
+}
+
+/** 剪贴板中没有图片时 pasteClipboardImage 的错误信息，与 image_paste.rs 一致 */
+export const NO_CLIPBOARD_IMAGE = 'No image on the clipboard';
+
+/** 剪贴板中没有图片时以 NO_CLIPBOARD_IMAGE reject */
+export function pasteClipboardImage(document: string, options: ImageOptions = {}): Promise<SavedImage> {
+	return invoke<SavedImage>('paste_clipboard_image', { document, options });
+}
+
+/** base64Data 可为 data URL；filename 用于 {name} 及判断源格式 */
+export function saveImage(document: string, filename: string, base64Data: string, options: ImageOptions = {}): Promise<SavedImage> {
+	return invoke<SavedImage>('save_image', { document, filename, base64Data, options });
+}
+
+export function copyFileToImg(srcPath: string, document: string, options: ImageOptions = {}): Promise<SavedImage> {
+	return invoke<SavedImage>('copy_file_to_img', { srcPath, document, options });
+}
@@ -1,4 +1,5 @@
 import pako from 'pako';
+import { invoke } from '@tauri-apps/api/core';
 
 const DEFAULT_KROKI_HOST = 'https://kroki.io';
 
@@ -31,3 +32,43 @@ export function createKrokiUrl(type: string, text: string, host?: string): strin
 
     return `${krokiHost}/${type}/svg/${base64}`;
 }
+
+export interface KrokiResponse {
+    svg: string;
+    /** 是否命中磁盘缓存 */
+    cached: boolean;
+}
+
+export type KrokiErrorKind = 'invalid_type' | 'offline' | 'timeout' | 'network' | 'http';
+
+export class KrokiError extends Error {
+    constructor(public kind: KrokiErrorKind, message: string, public status?: number) {
+        super(message);
+        this.name = 'KrokiError';
+    }
+}
+
+/**
+ * 通过 Rust 端 Kroki 客户端渲染 (带磁盘缓存，可离线使用已缓存的图表)
+ */
+export async function renderKroki(
+    type: string,
+    text: string,
+    host?: string,
+    options: { timeoutMs?: number; offline?: boolean } = {}
+): Promise<KrokiResponse> {
+    try {
+        return await invoke<KrokiResponse>('render_kroki', {
+            diagramType: type,
+            source: text,
+            baseUrl: host || DEFAULT_KROKI_HOST,
+            timeoutMs: options.timeoutMs,
+            offline: options.offline,
+        });
+    } catch (e: any) {
+        if (e && typeof e === 'object' && 'kind' in e) {
+            throw new KrokiError(e.kind, e.message, e.status);
+        }
+        throw e;
+    }
+}
@@ -0,0 +1,37 @@
+/**
+ * 断链与缺失资源检查
+ * 行从 1 起始，列为 UTF-16 (与 lint 一致)
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+
+export interface LinkCheckOptions {
+	/** 粘贴图片的保存目录，相对于文档 (默认 img) */
+	imageDirectory?: string;
+	/** 是否请求 http(s) 外链 */
+	checkExternal?: boolean;
+	/** 外链检查服务，以 `{endpoint}?url=<link>` 调用 */
+	externalEndpoint?: string | null;
+	timeoutMs?: number;
+}
+
+export interface LinkIssue {
+	file: string;
+	line: number;
+	column: number;
+	target: string;
+	/** unreadable：文件本身无法读取 (如非 UTF-8)，不中断目录扫描 */
+	kind: 'missing_file' | 'missing_image' | 'missing_anchor' | 'missing_note' | 'external' | 'unreadable';
+	message: string;
+}
+
+export interface LinkReport {
+	filesChecked: number;
+	linksChecked: number;
+	issues: LinkIssue[];
+}
+
+/** 检查单个文档，或目录下所有 Markdown 文件 */
+export function checkLinks(path: string, options?: LinkCheckOptions): Promise<LinkReport> {
+	return invoke<LinkReport>('check_links', { path, options });
+}
@@ -0,0 +1,25 @@
+/**
+ * Markdown lint (Rust markdownlint 风格规则)
+ * 范围为 1 起始、UTF-16 列、结束列不含，可直接用于 Monaco marker
+ */
+
+export interface LintRange {
+	startLine: number;
+	startColumn: number;
+	endLine: number;
+	endColumn: number;
+}
+
+export interface LintFix {
+	range: LintRange;
+	text: string;
+}
+
+export interface LintDiagnostic {
+	rule: string;
+	name: string;
+	severity: 'error' | 'warning' | 'info';
+	message: string;
+	range: LintRange;
+	fix: LintFix | null;
+}
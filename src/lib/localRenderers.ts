@@ -258,14 +258,43 @@ export function supportsLocalRender(diagramId: string): boolean {
  * 检查图表类型是否支持 Rust 渲染
  */
 export function supportsRustRender(diagramId: string): boolean {
-	return ['graphviz', 'svgbob'].includes(diagramId);
+	return ['graphviz', 'svgbob', 'pikchr'].includes(diagramId);
+}
+
+/**
+ * Rust 渲染选项
+ * info: 代码块信息字符串中语言之后的部分 (如 `debug no-opt theme=dark`)
+ * theme: 当前代码高亮主题，用于节点/边配色
+ */
+export interface RustRenderOptions {
+	info?: string;
+	theme?: string;
+	/** 深色界面 (Pikchr 使用深色配色) */
+	darkMode?: boolean;
+}
+
+/**
+ * DOT 解析错误 (行列号从 1 开始)
+ */
+export class DotParseError extends Error {
+	constructor(message: string, public line: number, public column: number) {
+		super(`${message} (line ${line}, column ${column})`);
+		this.name = 'DotParseError';
+	}
 }
 
 /**
  * Rust 渲染 GraphViz (使用 layout-rs)
  */
-export async function renderGraphVizRust(code: string): Promise<string> {
-	return await invoke<string>('render_graphviz_rust', { code });
+export async function renderGraphVizRust(code: string, options: RustRenderOptions = {}): Promise<string> {
+	try {
+		return await invoke<string>('render_graphviz_rust', { code, info: options.info, theme: options.theme });
+	} catch (e: any) {
+		if (e && typeof e === 'object' && typeof e.line === 'number') {
+			throw new DotParseError(e.message, e.line, e.column);
+		}
+		throw e;
+	}
 }
 
 /**
@@ -275,26 +304,66 @@ export async function renderSvgbobRust(code: string): Promise<string> {
 	return await invoke<string>('render_svgbob_rust', { code });
 }
 
+/**
+ * Rust 渲染 Pikchr (使用随后端编译的 pikchr.c)
+ */
+export async function renderPikchrRust(code: string, options: RustRenderOptions = {}): Promise<string> {
+	return await invoke<string>('render_pikchr_rust', { code, darkMode: options.darkMode ?? false });
+}
+
 /**
  * 统一 Rust 渲染入口
  * @param diagramId 图表类型 ID
  * @param code 源代码
  * @param rendererId 渲染器 ID
+ * @param options 代码块选项和主题
  * @returns 渲染后的 SVG 字符串
  */
 export async function renderRustDiagram(
 	diagramId: string,
 	code: string,
-	rendererId: string
+	rendererId: string,
+	options: RustRenderOptions = {}
 ): Promise<string> {
 	switch (diagramId) {
 		case 'graphviz':
-			return renderGraphVizRust(code);
+			return renderGraphVizRust(code, options);
 		
 		case 'svgbob':
 			return renderSvgbobRust(code);
 		
+		case 'pikchr':
+			return renderPikchrRust(code, options);
+		
 		default:
 			throw new Error(`No Rust renderer for diagram type: ${diagramId}`);
 	}
 }
+
+export interface RasterOptions {
+	/** 相对 SVG 原始尺寸的缩放倍数，默认 1 */
+	scale?: number;
+	/** 背景色 (#rgb / #rrggbb / #rrggbbaa)，默认透明 */
+	background?: string;
+}
+
+/**
+ * 将 SVG 栅格化为 PNG (resvg)，用于不支持 SVG 的导出和粘贴场景；返回 base64
+ */
+export async function rasterizeSvg(svg: string, options: RasterOptions = {}): Promise<string> {
+	return await invoke<string>('rasterize_svg', { svg, ...options });
+}
+
+/**
+ * 将 SVG 栅格化后以图片形式写入剪贴板
+ */
+export async function copySvgAsImage(svg: string, options: RasterOptions = {}): Promise<void> {
+	await invoke('copy_svg_as_image', { svg, ...options });
+}
+
+/**
+ * 将代码高亮为内联颜色的 SVG (tree-sitter)，可配合 rasterizeSvg 导出为 PNG
+ */
+export async function highlightCodeSvg(code: string, language: string, theme: string): Promise<string> {
+	return await invoke<string>('highlight_code_svg', { code, language, theme });
+}
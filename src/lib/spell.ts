@@ -0,0 +1,12 @@
+/**
+ * 离线拼写检查 (Hunspell 词典, Rust spellbook)
+ * 词典放在配置目录的 dictionaries/ 下: {lang}.aff + {lang}.dic
+ */
+
+import type { LintRange } from './lint';
+
+export interface Misspelling {
+	word: string;
+	range: LintRange;
+	suggestions: string[];
+}
@@ -0,0 +1,39 @@
+/**
+ * 文档统计 (基于 AST，不含代码与 frontmatter；CJK 按字计)
+ */
+
+export interface TextCounts {
+	words: number;
+	characters: number;
+	charactersNoSpaces: number;
+	cjkCharacters: number;
+	readingSeconds: number;
+}
+
+export interface Readability {
+	fleschReadingEase: number;
+	fleschKincaidGrade: number;
+	sentences: number;
+	syllables: number;
+}
+
+export interface SectionStats extends TextCounts {
+	/** 与预览中标题锚点一致；首个标题前的内容为空字符串 */
+	id: string;
+	title: string;
+	level: number;
+	line: number;
+	/** 含子章节 */
+	totalWords: number;
+}
+
+export interface DocumentStats extends TextCounts {
+	headings: number;
+	links: number;
+	images: number;
+	codeBlocks: number;
+	tasks: number;
+	tasksDone: number;
+	readability: Readability | null;
+	sections: SectionStats[];
+}
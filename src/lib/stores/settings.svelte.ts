@@ -1,5 +1,6 @@
 import { invoke } from '@tauri-apps/api/core';
 import type { DiagramRenderMode } from '../diagrams';
+import type { ImageFormat } from '../imagePaste';
 import { getDefaultDiagramSettings, getDefaultRendererSettings, getDefaultRustRendererSettings, getDiagramType } from '../diagrams';
 
 export type OSType = 'macos' | 'windows' | 'linux' | 'unknown';
@@ -182,6 +183,16 @@ export class SettingsStore {
 	osType = $state<OSType>('unknown');
 	imageDirectory = $state('img');
 	macosImageScaling = $state(true);
+	// 粘贴 / 拖放图片的处理方式，见 imagePaste.ts
+	imageFormat = $state<ImageFormat>('original');
+	imageQuality = $state(85);
+	// 0 表示不限
+	imageMaxWidth = $state(0);
+	imageNameTemplate = $state('{note}-{date}-{n}');
+	// Hunspell 词典名 (如 en_US)，空字符串表示关闭拼写检查
+	spellCheckLanguage = $state('');
+	// 工作区根目录 (索引、搜索、反向链接)，空字符串表示使用当前笔记所在文件夹
+	workspaceRoot = $state('');
 	language = $state<LanguageCode>('en');
 
 	editorFont = $state('Consolas');
@@ -193,6 +204,8 @@ export class SettingsStore {
 	
 	// Kroki 自定义 host（支持自托管）
 	krokiHost = $state('https://kroki.io');
+	// 只使用已缓存的 Kroki 图表，不发请求
+	krokiOffline = $state(false);
 	
 	// 图表渲染设置：每种图表的渲染模式
 	diagramSettings = $state<Record<string, DiagramRenderMode>>(getDefaultDiagramSettings());
@@ -230,6 +243,12 @@ export class SettingsStore {
 			const savedRestoreStateOnReopen = localStorage.getItem('editor.restoreStateOnReopen');
 			const savedImageDirectory = localStorage.getItem('editor.imageDirectory');
 			const savedMacosImageScaling = localStorage.getItem('editor.macosImageScaling');
+			const savedImageFormat = localStorage.getItem('editor.imageFormat');
+			const savedImageQuality = localStorage.getItem('editor.imageQuality');
+			const savedImageMaxWidth = localStorage.getItem('editor.imageMaxWidth');
+			const savedImageNameTemplate = localStorage.getItem('editor.imageNameTemplate');
+			const savedSpellCheckLanguage = localStorage.getItem('editor.spellCheckLanguage');
+			const savedWorkspaceRoot = localStorage.getItem('workspace.root');
 			const savedLanguage = localStorage.getItem('editor.language');
 
 			const savedEditorFont = localStorage.getItem('editor.font');
@@ -239,6 +258,7 @@ export class SettingsStore {
 			const savedCodeFont = localStorage.getItem('preview.codeFont');
 			const savedCodeFontSize = localStorage.getItem('preview.codeFontSize');
 			const savedKrokiHost = localStorage.getItem('kroki.host');
+			const savedKrokiOffline = localStorage.getItem('kroki.offline');
 			const savedDiagramSettings = localStorage.getItem('diagram.settings');
 			const savedDiagramRendererSettings = localStorage.getItem('diagram.rendererSettings');
 			const savedDiagramRustRendererSettings = localStorage.getItem('diagram.rustRendererSettings');
@@ -288,6 +308,12 @@ export class SettingsStore {
 			if (savedRestoreStateOnReopen !== null) this.restoreStateOnReopen = savedRestoreStateOnReopen === 'true';
 			if (savedImageDirectory !== null) this.imageDirectory = savedImageDirectory;
 			if (savedMacosImageScaling !== null) this.macosImageScaling = savedMacosImageScaling === 'true';
+			if (savedImageFormat !== null) this.imageFormat = savedImageFormat as ImageFormat;
+			if (savedImageQuality !== null) this.imageQuality = parseFontSize(savedImageQuality, 85, 1, 100);
+			if (savedImageMaxWidth !== null) this.imageMaxWidth = parseFontSize(savedImageMaxWidth, 0, 0, 16384);
+			if (savedImageNameTemplate !== null) this.imageNameTemplate = savedImageNameTemplate;
+			if (savedSpellCheckLanguage !== null) this.spellCheckLanguage = savedSpellCheckLanguage;
+			if (savedWorkspaceRoot !== null) this.workspaceRoot = savedWorkspaceRoot;
 			if (savedLanguage !== null) {
 				const lang = savedLanguage as LanguageCode;
 				const supportedCodes = SUPPORTED_LANGUAGES.map(l => l.code);
@@ -333,6 +359,7 @@ export class SettingsStore {
 
 			// Load diagram settings
 			if (savedKrokiHost !== null) this.krokiHost = savedKrokiHost;
+			if (savedKrokiOffline !== null) this.krokiOffline = savedKrokiOffline === 'true';
 			if (savedDiagramSettings !== null) {
 				try {
 					const parsed = JSON.parse(savedDiagramSettings);
@@ -342,6 +369,14 @@ export class SettingsStore {
 					console.error('Failed to parse diagram settings', e);
 				}
 			}
+			// Pikchr 仅在后端编译了 pikchr.c 时可离线渲染，否则回退到 Kroki
+			const pikchrChosen = savedDiagramSettings?.includes('"pikchr"') ?? false;
+			invoke<boolean>('pikchr_available')
+				.then((available) => {
+					if (available && !pikchrChosen) this.diagramSettings.pikchr = 'rust';
+					if (!available && this.diagramSettings.pikchr === 'rust') this.diagramSettings.pikchr = 'kroki';
+				})
+				.catch(() => {});
 			if (savedDiagramRendererSettings !== null) {
 				try {
 					const parsed = JSON.parse(savedDiagramRendererSettings);
@@ -391,6 +426,12 @@ export class SettingsStore {
 				localStorage.setItem('editor.restoreStateOnReopen', String(this.restoreStateOnReopen));
 				localStorage.setItem('editor.imageDirectory', this.imageDirectory);
 				localStorage.setItem('editor.macosImageScaling', String(this.macosImageScaling));
+				localStorage.setItem('editor.imageFormat', this.imageFormat);
+				localStorage.setItem('editor.imageQuality', String(this.imageQuality));
+				localStorage.setItem('editor.imageMaxWidth', String(this.imageMaxWidth));
+				localStorage.setItem('editor.imageNameTemplate', this.imageNameTemplate);
+				localStorage.setItem('editor.spellCheckLanguage', this.spellCheckLanguage);
+				localStorage.setItem('workspace.root', this.workspaceRoot);
 				localStorage.setItem('editor.language', this.language);
 				localStorage.setItem('editor.font', this.editorFont);
 					localStorage.setItem('editor.fontSize', String(this.editorFontSize));
@@ -399,6 +440,7 @@ export class SettingsStore {
 					localStorage.setItem('preview.codeFont', this.codeFont);
 					localStorage.setItem('preview.codeFontSize', String(this.codeFontSize));
 					localStorage.setItem('kroki.host', this.krokiHost);
+					localStorage.setItem('kroki.offline', String(this.krokiOffline));
 					localStorage.setItem('diagram.settings', JSON.stringify(this.diagramSettings));
 					localStorage.setItem('diagram.rendererSettings', JSON.stringify(this.diagramRendererSettings));
 					localStorage.setItem('diagram.rustRendererSettings', JSON.stringify(this.diagramRustRendererSettings));
@@ -533,6 +575,10 @@ export class SettingsStore {
 		this.tocSide = this.tocSide === 'left' ? 'right' : 'left';
 		}
 
+	toggleKrokiOffline() {
+		this.krokiOffline = !this.krokiOffline;
+		}
+
 	toggleMacosImageScaling() {
 		this.macosImageScaling = !this.macosImageScaling;
 		}
@@ -1,6 +1,15 @@
 import { t } from '../utils/i18n.js';
 import { settings } from './settings.svelte.js';
 
+/** 预览渲染时发现的问题，行列从 1 开始 (按整个文件计)，编辑器显示为标记 */
+export interface RenderDiagnostic {
+	line: number;
+	column?: number;
+	message: string;
+	severity: 'error' | 'warning';
+	source: string;
+}
+
 export interface Tab {
 	id: string;
 	path: string;
@@ -19,6 +28,8 @@ export interface Tab {
 	isSplit: boolean;
 	splitRatio: number;
 	isScrollSynced: boolean;
+	/** 按来源分组 (如 diagrams)，每组整体替换 */
+	renderDiagnostics?: Record<string, RenderDiagnostic[]>;
 }
 
 class TabManager {
@@ -48,7 +59,7 @@ class TabManager {
 	serializeState(): string {
 		const stateData = {
 			activeTabId: this.activeTabId,
-			tabs: this.tabs.map(t => ({ ...t, editorViewState: null, content: '' }))
+			tabs: this.tabs.map(t => ({ ...t, editorViewState: null, content: '', renderDiagnostics: {} }))
 		};
 		return JSON.stringify(stateData);
 	}
@@ -190,6 +201,11 @@ class TabManager {
 		}
 	}
 
+	setRenderDiagnostics(id: string, group: string, diagnostics: RenderDiagnostic[]) {
+		const tab = this.tabs.find((t) => t.id === id);
+		if (tab) tab.renderDiagnostics = { ...tab.renderDiagnostics, [group]: diagnostics };
+	}
+
 	setTabRawContent(id: string, raw: string) {
 		const tab = this.tabs.find((t) => t.id === id);
 		if (tab) {
@@ -177,6 +177,11 @@ export const translations: Record<LanguageCode, Translation> = {
             nextTab: 'Next Tab',
             previousTab: 'Previous Tab',
             commandPalette: 'Command Palette',
+            formatDocument: 'Format Document',
+            acceptChange: 'Accept Change',
+            rejectChange: 'Reject Change',
+            acceptAllChanges: 'Accept All Changes',
+            rejectAllChanges: 'Reject All Changes',
             toggleMinimap: 'Toggle Minimap',
             toggleWordWrap: 'Toggle Word Wrap',
             toggleLineNumbers: 'Toggle Line Numbers',
@@ -432,6 +437,11 @@ export const translations: Record<LanguageCode, Translation> = {
             nextTab: '下一个标签页',
             previousTab: '上一个标签页',
             commandPalette: '命令面板',
+            formatDocument: '格式化文档',
+            acceptChange: '接受修订',
+            rejectChange: '拒绝修订',
+            acceptAllChanges: '接受所有修订',
+            rejectAllChanges: '拒绝所有修订',
             toggleMinimap: '切换小地图',
             toggleWordWrap: '切换自动换行',
             toggleLineNumbers: '切换行号',
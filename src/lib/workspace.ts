@@ -0,0 +1,266 @@
+/**
+ * 工作区：以文件夹为单位的笔记集合 (全文索引、文件监听)
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export interface WorkspaceInfo {
+	root: string;
+	documents: number;
+}
+
+export interface SearchMatch {
+	/** 1 起始行号 */
+	line: number;
+	snippet: string;
+	/** snippet 内的 UTF-16 [start, end) 高亮范围 */
+	highlights: [number, number][];
+}
+
+export interface SearchHit {
+	path: string;
+	relativePath: string;
+	title: string;
+	score: number;
+	matches: SearchMatch[];
+}
+
+let openRoot: string | null = null;
+
+/** 当前打开的工作区根目录 */
+export function currentWorkspaceRoot(): string | null {
+	return openRoot;
+}
+
+export async function openWorkspace(root: string): Promise<WorkspaceInfo> {
+	const info = await invoke<WorkspaceInfo>('workspace_open', { root });
+	openRoot = root;
+	return info;
+}
+
+export async function closeWorkspace(): Promise<void> {
+	await invoke('workspace_close');
+	openRoot = null;
+}
+
+function parentDir(path: string): string {
+	return path.replace(/[\\/][^\\/]*$/, '');
+}
+
+function isInside(root: string, path: string): boolean {
+	const normalize = (p: string) => p.replace(/\\/g, '/').replace(/\/+$/, '');
+	const base = normalize(root);
+	return normalize(path).startsWith(base + '/');
+}
+
+/**
+ * 确保文档所在的工作区已打开：优先使用设置中的文件夹 (文档在其中时)，否则为文档所在文件夹。
+ * 文档已在当前工作区内时不重新打开。
+ */
+export async function ensureWorkspaceFor(path: string, preferredRoot = ''): Promise<WorkspaceInfo | null> {
+	if (!path) return null;
+	if (preferredRoot && isInside(preferredRoot, path)) {
+		return openRoot === preferredRoot ? null : openWorkspace(preferredRoot);
+	}
+	if (openRoot && isInside(openRoot, path)) return null;
+	return openWorkspace(parentDir(path));
+}
+
+/**
+ * 查询语法：词、"短语"、前缀*、/正则/、field:value (frontmatter)，-x 排除
+ */
+export function searchWorkspace(query: string, limit?: number): Promise<SearchHit[]> {
+	return invoke<SearchHit[]>('workspace_search', { query, limit });
+}
+
+/** 索引增量更新后回调，参数为变更文件的相对路径 */
+export function onWorkspaceIndexUpdated(callback: (paths: string[]) => void): Promise<UnlistenFn> {
+	return listen<string[]>('workspace-index-updated', (event) => callback(event.payload));
+}
+
+export type LinkKind = 'link' | 'image' | 'wiki_link' | 'embed';
+
+export interface ContextLine {
+	line: number;
+	text: string;
+}
+
+export interface Backlink {
+	path: string;
+	relativePath: string;
+	title: string;
+	line: number;
+	column: number;
+	kind: LinkKind;
+	/** 链接指向的标题或 ^block */
+	heading: string | null;
+	context: ContextLine[];
+}
+
+export interface Mention {
+	path: string;
+	relativePath: string;
+	title: string;
+	line: number;
+	column: number;
+	text: string;
+	context: string;
+}
+
+export interface GraphNode {
+	/** 相对路径 */
+	id: string;
+	title: string;
+	/** 链接目标尚未创建时为 false */
+	exists: boolean;
+	outgoing: number;
+	incoming: number;
+}
+
+export interface GraphEdge {
+	source: string;
+	target: string;
+	count: number;
+}
+
+export interface Graph {
+	nodes: GraphNode[];
+	edges: GraphEdge[];
+}
+
+export function getBacklinks(path: string): Promise<Backlink[]> {
+	return invoke<Backlink[]>('workspace_backlinks', { path });
+}
+
+/** 其他笔记中未加链接的标题/文件名/别名 */
+export function getUnlinkedMentions(path: string): Promise<Mention[]> {
+	return invoke<Mention[]>('workspace_unlinked_mentions', { path });
+}
+
+export function getGraph(): Promise<Graph> {
+	return invoke<Graph>('workspace_graph');
+}
+
+export interface TagCount {
+	/** 标签名（不含 #），取工作区中首次出现的写法 */
+	tag: string;
+	/** 带有该标签或其子标签的笔记数 */
+	notes: number;
+	/** 该标签本身出现的次数 */
+	occurrences: number;
+}
+
+export interface TaggedNote {
+	path: string;
+	relativePath: string;
+	title: string;
+	/** 匹配到的标签（含子标签），保留原写法 */
+	tags: string[];
+	/** 行内标签所在行；frontmatter 中的标签没有行号 */
+	lines: number[];
+}
+
+/** 工作区中的全部标签，嵌套标签同时计入父标签 */
+export function getTags(): Promise<TagCount[]> {
+	return invoke<TagCount[]>('workspace_tags');
+}
+
+/** 带有该标签或其子标签的笔记，不区分大小写 */
+export function getNotesForTag(tag: string): Promise<TaggedNote[]> {
+	return invoke<TaggedNote[]>('workspace_notes_for_tag', { tag });
+}
+
+export type TaskPriority = 'highest' | 'high' | 'medium' | 'low' | 'lowest';
+
+export interface WorkspaceTask {
+	path: string;
+	relativePath: string;
+	/** 所在笔记的标题 */
+	title: string;
+	/** 文档中的行号（含 frontmatter） */
+	line: number;
+	/** 方括号中的字符：' '、'x' 或 'X' */
+	status: string;
+	done: boolean;
+	text: string;
+	/** YYYY-MM-DD，来自 📅 或 due: */
+	due: string | null;
+	/** 来自 🔺 ⏫ 🔼 🔽 ⏬ */
+	priority: TaskPriority | null;
+}
+
+/** 工作区中的任务，按截止日期、优先级、文件排序；done 为空时返回全部 */
+export function getTasks(done?: boolean): Promise<WorkspaceTask[]> {
+	return invoke<WorkspaceTask[]>('workspace_tasks', { done: done ?? null });
+}
+
+export interface QueryResult {
+	markdown: string;
+	html: string;
+	/** 匹配到的笔记（相对路径），按结果顺序 */
+	files: string[];
+}
+
+/** 执行 ```query 代码块；path 为所在笔记，结果中的链接相对于它 */
+export function runQuery(query: string, path?: string): Promise<QueryResult> {
+	return invoke<QueryResult>('workspace_query', { query, path: path ?? null });
+}
+
+export interface QuickOpenItem {
+	path: string;
+	relativePath: string;
+	/** relativePath 中匹配字符的位置（UTF-16） */
+	positions: number[];
+	/** 标题结果才有 */
+	heading: string | null;
+	anchor: string | null;
+	level: number | null;
+	/** 打开时定位的行；文件结果为 1 */
+	line: number;
+	/** heading 中匹配字符的位置（UTF-16） */
+	headingPositions: number[];
+	score: number;
+}
+
+/** Ctrl+P 快速打开：模糊匹配文件与标题，`文件#标题` 在匹配的文件中查找标题 */
+export function quickOpen(query: string, recent: string[] = [], limit?: number): Promise<QuickOpenItem[]> {
+	return invoke<QuickOpenItem[]>('workspace_quick_open', { query, recent, limit: limit ?? null });
+}
+
+export interface LinkChange {
+	line: number;
+	/** 链接目标改写前后的原文 */
+	before: string;
+	after: string;
+}
+
+export interface FileEdit {
+	/** 重命名前的位置 */
+	path: string;
+	relativePath: string;
+	changes: LinkChange[];
+}
+
+export interface RenamePlan {
+	from: string;
+	to: string;
+	/** 移动的文件 (相对路径)；移动文件夹时包含其中每个文件 */
+	moved: { from: string; to: string }[];
+	edits: FileEdit[];
+}
+
+/** 预览重命名 / 移动需要改写的链接，不修改任何文件 */
+export function previewRename(from: string, to: string, imageDirectory?: string): Promise<RenamePlan> {
+	return invoke<RenamePlan>('workspace_rename_preview', { from, to, options: { imageDirectory } });
+}
+
+/** 重命名 / 移动笔记、图片或文件夹，并按各文件原有写法改写指向它的链接 */
+export function renameWithLinks(from: string, to: string, imageDirectory?: string): Promise<RenamePlan> {
+	return invoke<RenamePlan>('workspace_rename', { from, to, options: { imageDirectory } });
+}
+
+/** 整批撤销最近一次重命名；没有可撤销的操作时返回 null */
+export function undoRename(): Promise<RenamePlan | null> {
+	return invoke<RenamePlan | null>('workspace_undo_rename');
+}